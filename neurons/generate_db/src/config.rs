@@ -0,0 +1,97 @@
+//! Generation defaults read from `--config FILE`.
+//!
+//! The file is TOML with one top-level key per flag, named like the flag
+//! without its dashes:
+//!
+//! ```toml
+//! size = 4096
+//! hash_algo = "blake3"
+//! synchronous = "NORMAL"
+//! verify_after = true
+//! ```
+//!
+//! Every setting is turned back into the flag it stands for and appended to
+//! the command line, unless that flag is already on it, so the usual parsing
+//! and validation apply and the command line always wins. Only the settings
+//! that are shared by all tables of a miner are accepted; the table name,
+//! partition index and the destructive flags stay on the command line.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+/// Settings taking a value, written as `--name=value`.
+const VALUE_KEYS: &[&str] = &[
+    "size", "batch_size", "hash_algo", "hash_bytes", "hash_encoding", "threads", "max_runtime_secs",
+    "max_write_mbps", "progress", "synchronous", "journal", "auto_vacuum", "id_stride",
+    "checkpoint_interval", "rng", "charset", "compress", "cache_mb", "wal_autocheckpoint",
+];
+
+/// Settings for flags without a value, set with `true`.
+const FLAG_KEYS: &[&str] = &[
+    "hash", "strict_resume", "fill_data", "verify_after", "json", "quiet", "no_fsync_on_exit", "no_salt", "no_mkdir",
+    "reader_safe",
+];
+
+fn invalid(path: &str, reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("config {}: {}", path, reason))
+}
+
+/// Path given to `--config` in `argv`, if any.
+fn config_path(argv: &[OsString]) -> Option<&str> {
+    argv.iter().enumerate().find_map(|(i, arg)| match arg.to_str()?.strip_prefix("--config") {
+        Some("") => argv.get(i + 1)?.to_str(),
+        Some(rest) => rest.strip_prefix('='),
+        None => None,
+    })
+}
+
+/// Whether `--name` is already on the command line, as `--name` or `--name=value`.
+fn given(argv: &[OsString], name: &str) -> bool {
+    argv.iter().any(|arg| {
+        arg.to_str()
+            .and_then(|arg| arg.strip_prefix("--"))
+            .and_then(|arg| arg.strip_prefix(name))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('='))
+    })
+}
+
+/// `argv` with the settings of its `--config` file appended as flags, or
+/// unchanged without one. Appended flags land in the scope of the last
+/// subcommand, so they apply to `generate-all` as well as to the top-level
+/// generation command.
+pub fn with_defaults(mut argv: Vec<OsString>) -> io::Result<Vec<OsString>> {
+    let Some(path) = config_path(&argv).map(str::to_string) else {
+        return Ok(argv);
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|err| io::Error::new(err.kind(), format!("config {}: {}", path, err)))?;
+    let table: toml::Table = text.parse().map_err(|err: toml::de::Error| invalid(&path, err.message().to_string()))?;
+
+    let mut defaults: Vec<OsString> = Vec::new();
+    for (key, value) in &table {
+        if FLAG_KEYS.contains(&key.as_str()) {
+            match value {
+                toml::Value::Boolean(set) => {
+                    if *set && !given(&argv, key) {
+                        defaults.push(format!("--{}", key).into());
+                    }
+                }
+                _ => return Err(invalid(&path, format!("{} must be true or false", key))),
+            }
+        } else if VALUE_KEYS.contains(&key.as_str()) {
+            let value = match value {
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Integer(value) => value.to_string(),
+                _ => return Err(invalid(&path, format!("{} must be a string or an integer", key))),
+            };
+            if !given(&argv, key) {
+                defaults.push(format!("--{}={}", key, value).into());
+            }
+        } else {
+            return Err(invalid(&path, format!("unknown setting '{}'", key)));
+        }
+    }
+    argv.extend(defaults);
+    Ok(argv)
+}
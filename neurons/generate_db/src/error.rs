@@ -0,0 +1,246 @@
+//! Errors of generation and the subcommands that read tables back.
+
+use std::{fmt, io};
+use std::num::{IntErrorKind, ParseIntError};
+
+use indicatif::HumanBytes;
+use rusqlite::ErrorCode;
+
+/// How long to wait for another process, e.g. a miner answering a query, to
+/// release the database before giving up.
+pub const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Exit code of a process that ran out of disk space, the value of `ENOSPC`
+/// on Linux so scripts can tell it apart. Only `exit_code` reports it; io
+/// errors are recognised by their kind.
+const EXIT_DISK_FULL: i32 = 28;
+
+#[derive(Debug)]
+pub enum GenError {
+    Sqlite(rusqlite::Error),
+    Io(io::Error),
+    /// SQLite or a file write ran out of space. SQLite and io errors with
+    /// that cause are converted to this variant rather than kept as
+    /// `Sqlite` or `Io`, so callers can match on it.
+    DiskFull(Box<dyn std::error::Error + Send + Sync>),
+    Parse { name: &'static str, value: String, source: ParseIntError },
+    /// The stored row `id` is not the chunk its seed chain regenerates, in
+    /// `column`.
+    Mismatch { id: usize, column: &'static str },
+    /// `diff` found the databases apart at `id`, in `column` or, if
+    /// `missing_from` is set, because only one of them holds the row.
+    Diverged { id: usize, column: &'static str, missing_from: Option<&'static str> },
+    InvalidMetadata { key: &'static str, value: String },
+    CorruptRow { id: usize, reason: String },
+    /// The chain can't be walked past row `id`: its `rng_state` is not a
+    /// seed of the table's hash algorithm, so no later chunk can be
+    /// regenerated from it.
+    CorruptSeedChain { id: usize },
+    /// `value` of the flag `--{name}` can't be used, for `reason`.
+    InvalidArg { name: &'static str, value: String, reason: String },
+    /// The table was generated with another `field` than the one requested.
+    ParamMismatch { field: &'static str, stored: String, requested: String },
+    InsufficientSpace { needed: u64, available: u64, path: String },
+    InvalidProof,
+    /// `DB{table}` exists with other columns than generated tables have,
+    /// both listed as `name TYPE`, `found` from `PRAGMA table_info`.
+    SchemaMismatch { table: String, expected: Vec<String>, found: Vec<String> },
+    /// The Merkle root recomputed by `verify-manifest` differs from the one
+    /// recorded after generation, each given as `(rows, hex root)`.
+    ManifestMismatch { table: String, recorded: (usize, String), computed: (usize, String) },
+    /// An insert hit a row that is already stored, which only a wrong
+    /// resume point leads to.
+    DuplicateId { table: String, id: i64 },
+    /// `--continue_on_error` went on past these tables, whose own errors
+    /// were reported as they happened.
+    TablesFailed { failed: Vec<String>, of: usize },
+    /// `doctor` found these checks failing.
+    ChecksFailed { failed: Vec<&'static str> },
+}
+
+impl GenError {
+    /// Whether SQLite gave up waiting for another connection's lock.
+    pub fn is_busy(&self) -> bool {
+        match self {
+            GenError::Sqlite(rusqlite::Error::SqliteFailure(err, _)) => {
+                matches!(err.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+            }
+            _ => false,
+        }
+    }
+
+    /// Stable name of the kind of error, reported as `code` in the error
+    /// object `--json` runs print so callers don't have to parse messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GenError::Sqlite(_) if self.is_busy() => "busy",
+            GenError::Sqlite(_) => "database",
+            GenError::Io(_) => "io",
+            GenError::DiskFull(_) => "disk_full",
+            GenError::Parse { .. } | GenError::InvalidArg { .. } => "invalid_arg",
+            GenError::Mismatch { .. } => "mismatch",
+            GenError::Diverged { .. } => "diverged",
+            GenError::InvalidMetadata { .. } => "invalid_metadata",
+            GenError::CorruptRow { .. } => "corrupt_row",
+            GenError::CorruptSeedChain { .. } => "corrupt_seed_chain",
+            GenError::ParamMismatch { .. } => "param_mismatch",
+            GenError::InsufficientSpace { .. } => "insufficient_space",
+            GenError::InvalidProof => "invalid_proof",
+            GenError::SchemaMismatch { .. } => "schema_mismatch",
+            GenError::ManifestMismatch { .. } => "manifest_mismatch",
+            GenError::DuplicateId { .. } => "duplicate_id",
+            GenError::TablesFailed { .. } => "tables_failed",
+            GenError::ChecksFailed { .. } => "checks_failed",
+        }
+    }
+
+    /// Whether running the same command again can succeed once the cause is
+    /// gone, i.e. the disk has room or the lock was released. Generation
+    /// resumes where it stopped, so nothing is lost by retrying; every other
+    /// error needs different arguments or a repaired database.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, GenError::InsufficientSpace { .. } | GenError::DiskFull(_)) || self.is_busy()
+    }
+
+    /// The error as one JSON object, for `--json` runs.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.code(),
+            "recoverable": self.is_recoverable(),
+            "message": self.to_string(),
+        })
+    }
+
+    /// Exit status of a process that fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GenError::DiskFull(_) => EXIT_DISK_FULL,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenError::Sqlite(err) if self.is_busy() => write!(
+                f,
+                "database is still locked by another process after {}s: {}",
+                BUSY_TIMEOUT.as_secs(), err
+            ),
+            GenError::Sqlite(err) => write!(f, "database error: {}", err),
+            GenError::Io(err) => write!(f, "io error: {}", err),
+            GenError::DiskFull(err) => write!(f, "disk full: {}", err),
+            GenError::Parse { name, value, source } => match source.kind() {
+                IntErrorKind::PosOverflow => {
+                    write!(f, "invalid value '{}' for --{}: larger than the maximum {}", value, name, i64::MAX)
+                }
+                _ => write!(f, "invalid value '{}' for --{}: expected a non-negative integer", value, name),
+            },
+            GenError::Mismatch { id, column } => {
+                write!(f, "row {} does not match the regenerated chunk ({} differs)", id, column)
+            }
+            GenError::Diverged { id, missing_from: Some(db), .. } => write!(f, "row {} is missing from --{}", id, db),
+            GenError::Diverged { id, column, missing_from: None } => {
+                write!(f, "the databases differ at row {} ({} differs)", id, column)
+            }
+            GenError::InvalidMetadata { key, value } => {
+                write!(f, "invalid metadata value '{}' for {}", value, key)
+            }
+            GenError::CorruptRow { id, reason } => write!(f, "row {} is corrupt: {}", id, reason),
+            GenError::CorruptSeedChain { id } => write!(
+                f,
+                "the seed chain breaks at row {}: its rng_state is not a seed of the table's hash; run `repair`",
+                id
+            ),
+            GenError::InvalidArg { name, value, reason } => {
+                write!(f, "invalid value '{}' for --{}: {}", value, name, reason)
+            }
+            GenError::ParamMismatch { field, stored, requested } => {
+                write!(
+                    f,
+                    "table was generated with {} {}, not {} (pass --overwrite to regenerate it with the new settings)",
+                    field, stored, requested
+                )
+            }
+            GenError::InsufficientSpace { needed, available, path } => write!(
+                f,
+                "need {}, only {} free on {} (pass --force to generate anyway)",
+                HumanBytes(*needed), HumanBytes(*available), path
+            ),
+            GenError::InvalidProof => write!(f, "proof does not match the Merkle root"),
+            GenError::SchemaMismatch { table, expected, found } => write!(
+                f,
+                "schema mismatch in DB{}: expected columns [{}], found [{}]",
+                table, expected.join(", "), found.join(", ")
+            ),
+            GenError::DuplicateId { table, id } => write!(
+                f,
+                "duplicate id {} in DB{}: the row is already stored, so generation resumed from the wrong point",
+                id, table
+            ),
+            GenError::TablesFailed { failed, of } => {
+                write!(f, "{} of {} tables failed: {}", failed.len(), of, failed.join(", "))
+            }
+            GenError::ChecksFailed { failed } => write!(f, "failed checks: {}", failed.join(", ")),
+            GenError::ManifestMismatch { table, recorded, computed } => write!(
+                f,
+                "manifest mismatch in DB{}: recorded root {} over {} rows, the table now gives {} over {} rows",
+                table, recorded.1, recorded.0, computed.1, computed.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GenError::Sqlite(err) => Some(err),
+            GenError::Io(err) => Some(err),
+            GenError::DiskFull(err) => Some(err.as_ref()),
+            GenError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for GenError {
+    fn from(err: rusqlite::Error) -> Self {
+        match &err {
+            rusqlite::Error::SqliteFailure(failure, _) if failure.code == ErrorCode::DiskFull => {
+                GenError::DiskFull(Box::new(err))
+            }
+            _ => GenError::Sqlite(err),
+        }
+    }
+}
+
+impl From<io::Error> for GenError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::StorageFull {
+            GenError::DiskFull(Box::new(err))
+        } else {
+            GenError::Io(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_disks_map_to_disk_full() {
+        let sqlite = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_FULL), None);
+        let mut errors = vec![GenError::from(sqlite), GenError::from(io::Error::from(io::ErrorKind::StorageFull))];
+        // ENOSPC, as a failed write reports it.
+        #[cfg(target_os = "linux")]
+        errors.push(GenError::from(io::Error::from_raw_os_error(28)));
+        for err in errors {
+            assert!(matches!(err, GenError::DiskFull(_)), "{:?}", err);
+            assert_eq!((err.code(), err.is_recoverable(), err.exit_code()), ("disk_full", true, EXIT_DISK_FULL));
+        }
+        let other = GenError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!((other.code(), other.is_recoverable(), other.exit_code()), ("io", false, 1));
+    }
+}
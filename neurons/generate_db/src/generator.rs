@@ -0,0 +1,465 @@
+//! The deterministic chunk chain.
+
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+use rand::distributions::{Alphanumeric, Uniform};
+use rand_chacha::ChaCha20Rng;
+
+use crate::hash::ChunkHasher;
+
+/// PRNG that expands a seed into the characters of a chunk.
+///
+/// `Std` is whatever `rand` currently ships as `StdRng`, which is not
+/// guaranteed to stay the same across `rand` releases. `ChaCha20` is pinned
+/// to a fixed algorithm, so tables generated with it stay reproducible.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RngAlgo {
+    Std,
+    ChaCha20,
+}
+
+impl RngAlgo {
+    pub const NAMES: [&'static str; 2] = ["std", "chacha20"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "std" => Some(RngAlgo::Std),
+            "chacha20" => Some(RngAlgo::ChaCha20),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RngAlgo::Std => "std",
+            RngAlgo::ChaCha20 => "chacha20",
+        }
+    }
+}
+
+/// Characters a chunk is drawn from. `bytes` covers the full 0-255 range,
+/// so its chunks are binary and barely compress.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Charset {
+    Alphanumeric,
+    /// The printable ASCII characters, space to `~`.
+    Ascii,
+    Bytes,
+}
+
+impl Charset {
+    pub const NAMES: [&'static str; 3] = ["alphanumeric", "ascii", "bytes"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "alphanumeric" => Some(Charset::Alphanumeric),
+            "ascii" => Some(Charset::Ascii),
+            "bytes" => Some(Charset::Bytes),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Alphanumeric => "alphanumeric",
+            Charset::Ascii => "ascii",
+            Charset::Bytes => "bytes",
+        }
+    }
+
+    /// Fills `buf` with the next characters from `rng`. Filling a chunk in
+    /// several calls gives the same characters as one call, as long as every
+    /// call but the last fills a multiple of 4 bytes.
+    fn fill(&self, rng: &mut Prng, buf: &mut [u8]) {
+        match self {
+            Charset::Alphanumeric => buf.iter_mut().for_each(|slot| *slot = rng.sample(Alphanumeric)),
+            Charset::Ascii => {
+                let printable = Uniform::new_inclusive(b' ', b'~');
+                buf.iter_mut().for_each(|slot| *slot = rng.sample(printable));
+            }
+            Charset::Bytes => rng.fill_bytes(buf),
+        }
+    }
+}
+
+/// What `--incompressible` stands for: every chunk is the ChaCha20 keystream
+/// keyed by its seed, taken byte for byte. A keystream is as close to random
+/// as the cipher, so a chunk can't be stored in fewer bytes than it has and
+/// still be regenerated only by someone who knows the seed.
+pub const INCOMPRESSIBLE: (RngAlgo, Charset) = (RngAlgo::ChaCha20, Charset::Bytes);
+
+/// Index of a table among the partitions an allocator hands out, e.g. the
+/// 256 tables of one miner. It is mixed into the genesis seed ahead of the
+/// table name, so two partitions never share a chain even if they were
+/// given the same name, while the same index and name always give the same
+/// one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PartitionId(pub u64);
+
+impl PartitionId {
+    /// Seed of the first chunk of partition `self` of `table`: the hash of the
+    /// little-endian index, the table name and an all-zero chunk.
+    pub fn genesis_seed(self, hasher: &dyn ChunkHasher, table: &str, chunk_size: usize) -> Vec<u8> {
+        let mut state = hasher.start();
+        state.update(&self.0.to_le_bytes());
+        state.update(table.as_bytes());
+        state.update(&vec![0u8; chunk_size]);
+        state.finalize()
+    }
+}
+
+/// The seeded PRNG for one chunk. An enum rather than a trait object so the
+/// per-character sampling loop stays free of dynamic dispatch.
+enum Prng {
+    Std(StdRng),
+    ChaCha20(ChaCha20Rng),
+}
+
+impl RngCore for Prng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Prng::Std(rng) => rng.next_u32(),
+            Prng::ChaCha20(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Prng::Std(rng) => rng.next_u64(),
+            Prng::ChaCha20(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Prng::Std(rng) => rng.fill_bytes(dest),
+            Prng::ChaCha20(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            Prng::Std(rng) => rng.try_fill_bytes(dest),
+            Prng::ChaCha20(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+/// Produces the chain of chunks for a table.
+///
+/// Each chunk is `chunk_size` characters of the charset, alphanumeric unless
+/// set otherwise, drawn from a PRNG seeded with the current seed, followed by
+/// the hex hash of those characters. The
+/// hash of the whole chunk becomes the seed of the next one.
+pub struct ChunkGenerator {
+    seed: Vec<u8>,
+    chunk_size: usize,
+    /// The last chunk from `next_chunk`, reused so that large chunks aren't
+    /// allocated afresh for every row.
+    buf: Vec<u8>,
+    hasher: Box<dyn ChunkHasher>,
+    rng: RngAlgo,
+    charset: Charset,
+}
+
+impl ChunkGenerator {
+    pub fn new(seed: Vec<u8>, chunk_size: usize, hasher: Box<dyn ChunkHasher>) -> Self {
+        ChunkGenerator {
+            seed,
+            chunk_size,
+            buf: Vec::new(),
+            hasher,
+            rng: RngAlgo::Std,
+            charset: Charset::Alphanumeric,
+        }
+    }
+
+    /// Draws the chunk characters from `rng` instead of `StdRng`.
+    pub fn with_rng(mut self, rng: RngAlgo) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Draws the chunk characters from `charset` instead of the alphanumeric ones.
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Seed of the next chunk, i.e. the hash of the last one generated.
+    pub fn seed(&self) -> &[u8] {
+        &self.seed
+    }
+
+    /// Moves the chain to `seed`, e.g. after rows were deleted.
+    pub fn set_seed(&mut self, seed: Vec<u8>) {
+        self.seed = seed;
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn hasher(&self) -> &dyn ChunkHasher {
+        self.hasher.as_ref()
+    }
+
+    /// Seed of the first chunk: the hash of an all-zero chunk. Tables
+    /// generated before genesis seeds were salted start from this.
+    pub fn genesis_seed(hasher: &dyn ChunkHasher, chunk_size: usize) -> Vec<u8> {
+        hasher.hash(&vec![0u8; chunk_size])
+    }
+
+    /// Seed of the first chunk of `table`: the hash of the table name
+    /// followed by an all-zero chunk. Every table gets its own chain, so a
+    /// single stored copy can't answer challenges for several tables.
+    pub fn salted_genesis_seed(hasher: &dyn ChunkHasher, table: &str, chunk_size: usize) -> Vec<u8> {
+        let mut state = hasher.start();
+        state.update(table.as_bytes());
+        state.update(&vec![0u8; chunk_size]);
+        state.finalize()
+    }
+
+    fn prng(&self, seed: &[u8]) -> Prng {
+        // Both PRNGs take exactly 32 bytes, so longer digests are truncated.
+        let mut rng_seed = [0u8; 32];
+        rng_seed.copy_from_slice(&seed[..32]);
+        match self.rng {
+            RngAlgo::Std => Prng::Std(StdRng::from_seed(rng_seed)),
+            RngAlgo::ChaCha20 => Prng::ChaCha20(ChaCha20Rng::from_seed(rng_seed)),
+        }
+    }
+
+    pub fn generate_string_chunk(&self, seed: &[u8]) -> Vec<u8> {
+        let mut chunk = vec![0u8; self.chunk_size];
+        self.charset.fill(&mut self.prng(seed), &mut chunk);
+        chunk
+    }
+
+    pub fn hash_data(&self, data: &[u8]) -> Vec<u8> {
+        self.hasher.hash(data)
+    }
+
+    // Kept alongside `Iterator::next` so callers that want the pair directly
+    // don't have to unwrap.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> (Vec<u8>, Vec<u8>) {
+        let (chunk, hash) = self.next_chunk();
+        (chunk.to_vec(), hash.to_vec())
+    }
+
+    /// Advances the chain like `next`, but returns the chunk in a buffer the
+    /// generator keeps and overwrites on the next call, along with its hash.
+    /// After the first call no memory is allocated for the chunk itself.
+    pub fn next_chunk(&mut self) -> (&[u8], &[u8]) {
+        // Every byte is overwritten below, so shrinking the buffer back to the
+        // characters avoids zeroing them again.
+        self.buf.truncate(self.chunk_size);
+        self.buf.resize(self.chunk_size, 0);
+        self.charset.fill(&mut self.prng(&self.seed), &mut self.buf);
+        let hash_base = self.hasher.hash(&self.buf);
+        self.buf.resize(self.chunk_size + 2 * hash_base.len(), 0);
+        hex::encode_to_slice(&hash_base, &mut self.buf[self.chunk_size..])
+            .expect("buffer sized for the hex hash");
+
+        self.seed = self.hasher.hash(&self.buf);
+        (&self.buf, &self.seed)
+    }
+
+    /// Advances the chain like `next`, but only returns the hash.
+    ///
+    /// The chunk is streamed through a small buffer into both hashes at once
+    /// instead of being materialized, so memory use doesn't grow with
+    /// `chunk_size`.
+    pub fn next_hash_only(&mut self) -> Vec<u8> {
+        let mut rng = self.prng(&self.seed);
+        let mut base_hash = self.hasher.start();
+        let mut chunk_hash = self.hasher.start();
+        let mut buf = [0u8; 4096];
+        let mut remaining = self.chunk_size;
+        while remaining > 0 {
+            let len = remaining.min(buf.len());
+            self.charset.fill(&mut rng, &mut buf[..len]);
+            base_hash.update(&buf[..len]);
+            chunk_hash.update(&buf[..len]);
+            remaining -= len;
+        }
+        chunk_hash.update(hex::encode(base_hash.finalize()).as_bytes());
+
+        let hash = chunk_hash.finalize();
+        self.seed = hash.clone();
+        hash
+    }
+}
+
+/// The chain as an endless stream of `(chunk, hash)` pairs, so callers can
+/// write `chunk_gen.by_ref().take(n)`. The hash is a `Vec` because its length
+/// depends on the hash algorithm.
+impl Iterator for ChunkGenerator {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(ChunkGenerator::next(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{HashAlgo, Sha256Hasher};
+
+    fn zero_seeded(chunk_size: usize) -> ChunkGenerator {
+        let hasher = HashAlgo::Sha256.hasher();
+        let seed = ChunkGenerator::genesis_seed(hasher.as_ref(), chunk_size);
+        ChunkGenerator::new(seed, chunk_size, hasher)
+    }
+
+    #[test]
+    fn next_chunk_reuses_its_buffer() {
+        let mut owned = zero_seeded(1 << 16);
+        let mut reused = zero_seeded(1 << 16);
+        let buf = reused.next_chunk().0.as_ptr();
+        owned.next();
+        for _ in 0..10 {
+            let expected = owned.next();
+            let (chunk, hash) = reused.next_chunk();
+            assert_eq!(chunk.as_ptr(), buf);
+            assert_eq!((chunk, hash), (expected.0.as_slice(), expected.1.as_slice()));
+        }
+    }
+
+    #[test]
+    fn genesis_seed_is_hash_of_zero_chunk() {
+        let seed = ChunkGenerator::genesis_seed(&Sha256Hasher, 10);
+        assert_eq!(hex::encode(seed), "01d448afd928065458cf670b60f5a594d735af0172c8d67f22a81680132681ca");
+    }
+
+    #[test]
+    fn salted_genesis_seed_depends_on_table() {
+        let seed = ChunkGenerator::salted_genesis_seed(&Sha256Hasher, "DBx", 10);
+        assert_eq!(seed, Sha256Hasher.hash(b"DBx\0\0\0\0\0\0\0\0\0\0"));
+        assert_ne!(seed, ChunkGenerator::salted_genesis_seed(&Sha256Hasher, "DBy", 10));
+        assert_ne!(seed, ChunkGenerator::genesis_seed(&Sha256Hasher, 10));
+    }
+
+    #[test]
+    fn partitions_have_disjoint_chains() {
+        let chain = |partition: u64| {
+            let hasher = HashAlgo::Sha256.hasher();
+            let seed = PartitionId(partition).genesis_seed(hasher.as_ref(), "DBx", 10);
+            ChunkGenerator::new(seed, 10, hasher).take(50).collect::<Vec<_>>()
+        };
+        let seed = PartitionId(1).genesis_seed(&Sha256Hasher, "DBx", 10);
+        assert_eq!(seed, Sha256Hasher.hash(b"\x01\0\0\0\0\0\0\0DBx\0\0\0\0\0\0\0\0\0\0"));
+        assert_ne!(seed, ChunkGenerator::salted_genesis_seed(&Sha256Hasher, "DBx", 10));
+
+        assert_eq!(chain(7), chain(7));
+        let chunks: std::collections::HashSet<_> = chain(7).into_iter().map(|(chunk, _)| chunk).collect();
+        for partition in [0, 1, 8, 255] {
+            assert!(chain(partition).iter().all(|(chunk, _)| !chunks.contains(chunk)), "{} aliases 7", partition);
+        }
+    }
+
+    #[test]
+    fn chain_matches_golden_values() {
+        let golden: [(&[u8], &str); 3] = [
+            (
+                b"DqfDDmrOQC7bc00644d406a7759df2aa02a803936a73289e8bbd1f53172206c9fc37803f9e",
+                "701076120b588487ef52b2aa9e971bb2f464602103be93094a4433c60d2338e1",
+            ),
+            (
+                b"3tnJ9tbdBn9a280554c7d644dcd2102afcaaba4ddb5c50502d07a6644efd78b1134ae59e9d",
+                "fbe107046ae22b5c650240428a5915d35ea6b94b1f555945826979f7aa973987",
+            ),
+            (
+                b"VKM2Zyxioz683f1237dd4c120576d23d5a4e5427f3b570aedf982e5692360358d79a0cff14",
+                "a644d4b603a4d19761f3e54aab247149217a13619e49d382eeac3d679c7a873a",
+            ),
+        ];
+
+        let mut chunk_gen = zero_seeded(10);
+        for (data, hash) in golden.iter() {
+            let (chunk_data, chunk_hash) = chunk_gen.next();
+            assert_eq!(chunk_data, *data);
+            assert_eq!(hex::encode(chunk_hash), *hash);
+        }
+    }
+
+    #[test]
+    fn chunk_is_characters_then_their_hex_hash() {
+        let mut chunk_gen = zero_seeded(40);
+        let seed = chunk_gen.seed().to_vec();
+        let (chunk, hash) = chunk_gen.next();
+
+        let (chars, hex_hash) = chunk.split_at(40);
+        assert_eq!(chars, chunk_gen.generate_string_chunk(&seed).as_slice());
+        assert!(chars.iter().all(u8::is_ascii_alphanumeric));
+        assert_eq!(hex_hash, hex::encode(Sha256Hasher.hash(chars)).as_bytes());
+        // No further transform: the chunk as stored is what gets hashed into
+        // the next seed.
+        assert_eq!(hash, Sha256Hasher.hash(&chunk));
+        assert_eq!(chunk_gen.seed(), hash.as_slice());
+    }
+
+    #[test]
+    fn iterator_yields_the_chain() {
+        let mut expected = zero_seeded(10);
+        let mut chunk_gen = zero_seeded(10);
+        for chunk in chunk_gen.by_ref().take(2) {
+            assert_eq!(chunk, expected.next());
+        }
+        // Taking from a borrowed generator leaves it at the next chunk.
+        assert_eq!(chunk_gen.next(), expected.next());
+    }
+
+    #[test]
+    fn chacha20_chain_matches_golden_values() {
+        let golden: [(&[u8], &str); 2] = [
+            (
+                b"fWTDNiRsN38afc16fa4769f0f7484b576107cafc86a4e4db5da2a1fb500c31d605d414d4e5",
+                "1791e2b4e9c0dfab8373f74eb3e6651e3c5cc8fb11c408a63ca35c2cd07e5715",
+            ),
+            (
+                b"ptBJRKJJFge4368c0a9122d35afd654e9f23b31d743411dcee5432c3ff3990dbc7fffc4094",
+                "612935c0798c21838d0d216f7c769b0edc6c4941be7cce85e749b0550495b526",
+            ),
+        ];
+
+        let mut chunk_gen = zero_seeded(10).with_rng(RngAlgo::ChaCha20);
+        for (data, hash) in golden.iter() {
+            let (chunk_data, chunk_hash) = chunk_gen.next();
+            assert_eq!(chunk_data, *data);
+            assert_eq!(hex::encode(chunk_hash), *hash);
+        }
+    }
+
+    #[test]
+    fn hash_only_chain_matches_full_chunks() {
+        // Larger than the streaming buffer and not a multiple of 4.
+        for charset in [Charset::Alphanumeric, Charset::Ascii, Charset::Bytes] {
+            let mut full = zero_seeded(10_003).with_charset(charset);
+            let mut hash_only = zero_seeded(10_003).with_charset(charset);
+            for _ in 0..3 {
+                assert_eq!(full.next().1, hash_only.next_hash_only(), "{}", charset.name());
+            }
+        }
+    }
+
+    #[test]
+    fn charsets_draw_from_their_range() {
+        let chunk = |charset| zero_seeded(4096).with_charset(charset).next().0[..4096].to_vec();
+        assert!(chunk(Charset::Ascii).iter().all(|&char| (b' '..=b'~').contains(&char)));
+        assert!(chunk(Charset::Ascii).iter().any(|char| !char.is_ascii_alphanumeric()));
+        assert!(chunk(Charset::Bytes).iter().any(|&byte| byte >= 0x80));
+        // The default keeps the chain of existing tables.
+        assert_eq!(zero_seeded(10).with_charset(Charset::Alphanumeric).next(), zero_seeded(10).next());
+    }
+
+    #[test]
+    fn generators_with_same_seed_agree() {
+        let mut a = zero_seeded(64);
+        let mut b = zero_seeded(64);
+        for _ in 0..100 {
+            assert_eq!(a.next(), b.next());
+        }
+    }
+}
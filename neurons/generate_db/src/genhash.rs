@@ -0,0 +1,123 @@
+use std::process;
+
+use clap::{App, Arg};
+use storer_db_project::{Charset, ChunkGenerator, HashAlgo, INCOMPRESSIBLE, RngAlgo};
+
+/// Prints the seed the chain reaches after `n` chunks, i.e. the `rng_state`
+/// of row `n - 1` in a table generated with the same parameters, without
+/// writing any data.
+fn main() {
+    let matches = App::new("Seed Chain Hasher")
+        .arg(Arg::with_name("n")
+            .long("n")
+            .value_name("NUM_CHUNKS")
+            .help("Number of chunks in the table")
+            .required(true)
+            .takes_value(true))
+        .arg(Arg::with_name("size")
+            .long("size")
+            .value_name("CHUNK_SIZE")
+            .help("Size of each chunk in bytes")
+            .required(true)
+            .takes_value(true))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .value_name("seed")
+            .help("Seed of the table, which salts its genesis seed")
+            .required_unless_one(&["no_salt", "start_seed"])
+            .takes_value(true))
+        .arg(Arg::with_name("no_salt")
+            .long("no_salt")
+            .help("Start from the unsalted genesis seed of tables generated with --no_salt")
+            .conflicts_with("seed")
+            .takes_value(false))
+        .arg(Arg::with_name("start_index")
+            .long("start_index")
+            .value_name("ID")
+            .help("Id to start walking from, requires --start_seed")
+            .requires("start_seed")
+            .takes_value(true))
+        .arg(Arg::with_name("start_seed")
+            .long("start_seed")
+            .value_name("HEX")
+            .help("Seed of the chunk at --start_index (the previous row's rng_state)")
+            .takes_value(true))
+        .arg(Arg::with_name("hash_algo")
+            .long("hash_algo")
+            .value_name("HASH_ALGO")
+            .help("Hash used for chunk hashes and the seed chain")
+            .possible_values(&HashAlgo::NAMES)
+            .default_value("sha256")
+            .takes_value(true))
+        .arg(Arg::with_name("rng")
+            .long("rng")
+            .value_name("RNG")
+            .help("PRNG for the chunk characters")
+            .possible_values(&RngAlgo::NAMES)
+            .default_value("std")
+            .takes_value(true))
+        .arg(Arg::with_name("charset")
+            .long("charset")
+            .value_name("CHARSET")
+            .help("Characters of the chunks")
+            .possible_values(&Charset::NAMES)
+            .default_value("alphanumeric")
+            .takes_value(true))
+        .arg(Arg::with_name("incompressible")
+            .long("incompressible")
+            .help("Chunks are the ChaCha20 keystream of their seeds, as with --rng chacha20 --charset bytes")
+            .conflicts_with_all(&["rng", "charset"])
+            .takes_value(false))
+        .get_matches();
+
+    let parse = |arg: &str| -> usize {
+        let value = matches.value_of(arg).unwrap_or_default();
+        match value.parse() {
+            Ok(parsed) if !value.starts_with('+') => parsed,
+            _ => {
+                eprintln!("Error: invalid value '{}' for --{}: expected a non-negative integer", value, arg);
+                process::exit(1);
+            }
+        }
+    };
+    let n_chunks = parse("n");
+    let chunk_size = parse("size");
+    if chunk_size == 0 {
+        eprintln!("Error: invalid value '0' for --size: must be at least 1");
+        process::exit(1);
+    }
+    let start_index = if matches.is_present("start_index") { parse("start_index") } else { 0 };
+    let hash_algo = HashAlgo::from_name(matches.value_of("hash_algo").unwrap_or_default())
+        .unwrap_or(HashAlgo::Sha256);
+    let (rng, charset) = if matches.is_present("incompressible") {
+        INCOMPRESSIBLE
+    } else {
+        (
+            RngAlgo::from_name(matches.value_of("rng").unwrap_or_default()).unwrap_or(RngAlgo::Std),
+            Charset::from_name(matches.value_of("charset").unwrap_or_default()).unwrap_or(Charset::Alphanumeric),
+        )
+    };
+
+    let hasher = hash_algo.hasher();
+    let seed = match matches.value_of("start_seed") {
+        Some(value) => match hex::decode(value) {
+            Ok(seed) if seed.len() == hash_algo.output_len() => seed,
+            _ => {
+                eprintln!("Error: --start_seed must be {} bytes of hex", hash_algo.output_len());
+                process::exit(1);
+            }
+        },
+        None => match matches.value_of("seed") {
+            Some(table_seed) => {
+                ChunkGenerator::salted_genesis_seed(hasher.as_ref(), &format!("DB{}", table_seed), chunk_size)
+            }
+            None => ChunkGenerator::genesis_seed(hasher.as_ref(), chunk_size),
+        },
+    };
+
+    let mut chunk_gen = ChunkGenerator::new(seed, chunk_size, hasher).with_rng(rng).with_charset(charset);
+    for _ in start_index..n_chunks {
+        chunk_gen.next_hash_only();
+    }
+    println!("{}", hex::encode(chunk_gen.seed()));
+}
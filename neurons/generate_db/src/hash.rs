@@ -0,0 +1,127 @@
+//! Hash algorithms used for chunk hashes and the seed chain.
+
+use sha2::{Digest, Sha256, Sha512};
+
+/// Digest used to hash chunks and derive the next seed in the chain.
+pub trait ChunkHasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Starts an incremental hash, for data that is never held in memory at once.
+    fn start(&self) -> Box<dyn HashState>;
+}
+
+/// An in-progress incremental hash.
+pub trait HashState {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+pub struct Sha256Hasher;
+
+impl ChunkHasher for Sha256Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        Sha256::digest(data).to_vec()
+    }
+
+    fn start(&self) -> Box<dyn HashState> {
+        Box::new(Sha256::new())
+    }
+}
+
+impl HashState for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Digest::finalize(*self).to_vec()
+    }
+}
+
+pub struct Sha512Hasher;
+
+impl ChunkHasher for Sha512Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        Sha512::digest(data).to_vec()
+    }
+
+    fn start(&self) -> Box<dyn HashState> {
+        Box::new(Sha512::new())
+    }
+}
+
+impl HashState for Sha512 {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Digest::finalize(*self).to_vec()
+    }
+}
+
+pub struct Blake3Hasher;
+
+impl ChunkHasher for Blake3Hasher {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+
+    fn start(&self) -> Box<dyn HashState> {
+        Box::new(blake3::Hasher::new())
+    }
+}
+
+impl HashState for blake3::Hasher {
+    fn update(&mut self, data: &[u8]) {
+        blake3::Hasher::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        blake3::Hasher::finalize(&self).as_bytes().to_vec()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashAlgo {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgo {
+    pub const NAMES: [&'static str; 3] = ["sha256", "sha512", "blake3"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(HashAlgo::Sha256),
+            "sha512" => Some(HashAlgo::Sha512),
+            "blake3" => Some(HashAlgo::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Sha512 => "sha512",
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+
+    /// Length of the digest in bytes.
+    pub fn output_len(&self) -> usize {
+        match self {
+            HashAlgo::Sha256 | HashAlgo::Blake3 => 32,
+            HashAlgo::Sha512 => 64,
+        }
+    }
+
+    pub fn hasher(&self) -> Box<dyn ChunkHasher> {
+        match self {
+            HashAlgo::Sha256 => Box::new(Sha256Hasher),
+            HashAlgo::Sha512 => Box::new(Sha512Hasher),
+            HashAlgo::Blake3 => Box::new(Blake3Hasher),
+        }
+    }
+}
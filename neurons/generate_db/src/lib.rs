@@ -0,0 +1,19 @@
+//! Deterministic chunk generation shared by the generate_db binaries, and
+//! the generation pipeline for services that embed it.
+
+mod config;
+mod error;
+mod generator;
+mod hash;
+pub mod merkle;
+mod pipeline;
+mod rawfile;
+#[cfg(feature = "async")]
+mod service;
+
+pub use error::{BUSY_TIMEOUT, GenError};
+pub use generator::{Charset, ChunkGenerator, INCOMPRESSIBLE, PartitionId, RngAlgo};
+pub use hash::{Blake3Hasher, ChunkHasher, HashAlgo, HashState, Sha256Hasher, Sha512Hasher};
+pub use pipeline::{CancelToken, GenerateConfig, ProgressUpdate, Summary, TableSummary, generate_blocking, run_cli};
+#[cfg(feature = "async")]
+pub use service::generate;
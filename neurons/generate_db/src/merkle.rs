@@ -0,0 +1,339 @@
+//! Binary Merkle tree over the per-chunk hashes of a table.
+//!
+//! Leaves are the stored chunk hashes in `id` order, each entering the tree
+//! as `hash(0x00 || leaf)`, and each parent is `hash(0x01 || left || right)`,
+//! so no inner node hashes like a leaf. A node without a sibling on its level
+//! is carried up unchanged. A proof names the index of its leaf and the
+//! number of leaves, which fix the side of every step and the levels where
+//! the node is carried up, so a leaf has exactly one valid path.
+//!
+//! `TreeBuilder` computes roots and proofs from a stream of leaves without
+//! holding them, and can resume from the frontier of an earlier, shorter
+//! tree.
+
+use std::fmt;
+
+use crate::hash::ChunkHasher;
+
+/// Which side of the running hash the sibling sits on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One level of a Merkle authentication path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofStep {
+    pub side: Side,
+    pub sibling: Vec<u8>,
+}
+
+impl fmt::Display for ProofStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.side {
+            Side::Left => "L",
+            Side::Right => "R",
+        };
+        write!(f, "{}:{}", side, hex::encode(&self.sibling))
+    }
+}
+
+impl ProofStep {
+    /// Parses a step in the `L:<hex>` / `R:<hex>` form produced by `Display`.
+    pub fn parse(step: &str) -> Option<Self> {
+        let (side, sibling) = step.split_once(':')?;
+        let side = match side {
+            "L" => Side::Left,
+            "R" => Side::Right,
+            _ => return None,
+        };
+        Some(ProofStep { side, sibling: hex::decode(sibling).ok()? })
+    }
+}
+
+/// A proof of one leaf: its `index` among `leaves` and the path up the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proof {
+    pub index: usize,
+    pub leaves: usize,
+    pub path: Vec<ProofStep>,
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.leaves)?;
+        self.path.iter().try_for_each(|step| write!(f, ",{}", step))
+    }
+}
+
+impl Proof {
+    /// Parses a proof in the `<index>/<leaves>,L:<hex>,R:<hex>...` form
+    /// produced by `Display`.
+    pub fn parse(proof: &str) -> Option<Self> {
+        let mut parts = proof.split(',');
+        let (index, leaves) = parts.next()?.split_once('/')?;
+        Some(Proof {
+            index: index.parse().ok()?,
+            leaves: leaves.parse().ok()?,
+            path: parts.map(ProofStep::parse).collect::<Option<_>>()?,
+        })
+    }
+}
+
+fn leaf(hasher: &dyn ChunkHasher, chunk_hash: &[u8]) -> Vec<u8> {
+    let mut state = hasher.start();
+    state.update(&[0x00]);
+    state.update(chunk_hash);
+    state.finalize()
+}
+
+fn parent(hasher: &dyn ChunkHasher, left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut state = hasher.start();
+    state.update(&[0x01]);
+    state.update(left);
+    state.update(right);
+    state.finalize()
+}
+
+/// Builds a tree one leaf at a time, keeping only the roots of its complete
+/// subtrees, so `n` leaves take `O(log n)` hashes of memory. Folding those
+/// roots from the right gives the same tree as carrying the odd node up
+/// level by level.
+pub struct TreeBuilder<'a> {
+    hasher: &'a dyn ChunkHasher,
+    prefixed: bool,
+    leaves: usize,
+    /// Roots of the complete subtrees, largest first; their sizes are the
+    /// set bits of `leaves`.
+    frontier: Vec<Vec<u8>>,
+    /// Leaf to prove, with the steps of its path found so far.
+    target: Option<(usize, Vec<ProofStep>)>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    pub fn new(hasher: &'a dyn ChunkHasher) -> Self {
+        TreeBuilder { hasher, prefixed: true, leaves: 0, frontier: Vec::new(), target: None }
+    }
+
+    /// A builder without the leaf prefix, which manifests recorded before it
+    /// was added were built as.
+    pub fn unprefixed(hasher: &'a dyn ChunkHasher) -> Self {
+        TreeBuilder { prefixed: false, ..TreeBuilder::new(hasher) }
+    }
+
+    /// A builder that also collects the path of the leaf at `index`.
+    pub fn proving(hasher: &'a dyn ChunkHasher, index: usize) -> Self {
+        TreeBuilder { target: Some((index, Vec::new())), ..TreeBuilder::new(hasher) }
+    }
+
+    /// Goes on with the tree whose first `leaves` leaves left `frontier`,
+    /// or `None` if it can't be theirs.
+    pub fn resume(hasher: &'a dyn ChunkHasher, leaves: usize, frontier: Vec<Vec<u8>>) -> Option<Self> {
+        (frontier.len() == leaves.count_ones() as usize).then(|| TreeBuilder { leaves, frontier, ..TreeBuilder::new(hasher) })
+    }
+
+    pub fn leaves(&self) -> usize {
+        self.leaves
+    }
+
+    /// Roots of the complete subtrees so far, to `resume` from.
+    pub fn frontier(&self) -> &[Vec<u8>] {
+        &self.frontier
+    }
+
+    pub fn push(&mut self, chunk_hash: &[u8]) {
+        let mut node = if self.prefixed { leaf(self.hasher, chunk_hash) } else { chunk_hash.to_vec() };
+        self.leaves += 1;
+        let mut size = 1;
+        while self.leaves.is_multiple_of(size * 2) {
+            let left = self.frontier.pop().expect("a subtree of every set bit of the leaf count");
+            let start = self.leaves - 2 * size;
+            self.record_step(start..start + size, &left, &node, start + 2 * size);
+            node = parent(self.hasher, &left, &node);
+            size *= 2;
+        }
+        self.frontier.push(node);
+    }
+
+    /// Adds the step of the target's path where the subtree over `left`
+    /// and the one after it, up to `end`, are joined.
+    fn record_step(&mut self, left: std::ops::Range<usize>, left_node: &[u8], right_node: &[u8], end: usize) {
+        if let Some((index, path)) = &mut self.target {
+            if left.contains(index) {
+                path.push(ProofStep { side: Side::Right, sibling: right_node.to_vec() });
+            } else if (left.end..end).contains(index) {
+                path.push(ProofStep { side: Side::Left, sibling: left_node.to_vec() });
+            }
+        }
+    }
+
+    /// Folds the frontier from the right into the root.
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        let mut root = self.frontier.pop()?;
+        let mut sizes = self.leaves;
+        let mut start = self.leaves - (sizes & sizes.wrapping_neg());
+        sizes &= sizes - 1;
+        while let Some(left) = self.frontier.pop() {
+            let size = sizes & sizes.wrapping_neg();
+            sizes &= sizes - 1;
+            self.record_step(start - size..start, &left, &root, self.leaves);
+            root = parent(self.hasher, &left, &root);
+            start -= size;
+        }
+        Some(root)
+    }
+
+    /// Root of the tree, or `None` if it has no leaves.
+    pub fn root(mut self) -> Option<Vec<u8>> {
+        self.finish()
+    }
+
+    /// Proof of the leaf given to `proving`, or `None` if there is no such
+    /// leaf.
+    pub fn proof(mut self) -> Option<Proof> {
+        match self.target {
+            Some((index, _)) if index < self.leaves => {}
+            _ => return None,
+        }
+        self.finish();
+        let (index, path) = self.target.take()?;
+        Some(Proof { index, leaves: self.leaves, path })
+    }
+}
+
+/// Root of the tree over `leaves`, or `None` if there are no leaves.
+pub fn root<T: AsRef<[u8]>>(hasher: &dyn ChunkHasher, leaves: impl IntoIterator<Item = T>) -> Option<Vec<u8>> {
+    let mut tree = TreeBuilder::new(hasher);
+    leaves.into_iter().for_each(|chunk_hash| tree.push(chunk_hash.as_ref()));
+    tree.root()
+}
+
+/// Root of the tree without the leaf prefix, which manifests recorded
+/// before it was added were built as.
+pub fn unprefixed_root<T: AsRef<[u8]>>(hasher: &dyn ChunkHasher, leaves: impl IntoIterator<Item = T>) -> Option<Vec<u8>> {
+    let mut tree = TreeBuilder::unprefixed(hasher);
+    leaves.into_iter().for_each(|chunk_hash| tree.push(chunk_hash.as_ref()));
+    tree.root()
+}
+
+/// Proof for the leaf at `index`, or `None` if there is no such leaf.
+pub fn prove<T: AsRef<[u8]>>(hasher: &dyn ChunkHasher, leaves: impl IntoIterator<Item = T>, index: usize) -> Option<Proof> {
+    let mut tree = TreeBuilder::proving(hasher, index);
+    leaves.into_iter().for_each(|chunk_hash| tree.push(chunk_hash.as_ref()));
+    tree.proof()
+}
+
+/// Checks that `chunk_hash` is leaf `proof.index` of a tree of
+/// `proof.leaves` leaves with the given `root`. The path must have exactly
+/// the steps, and sides, that the index and count call for.
+pub fn verify(hasher: &dyn ChunkHasher, chunk_hash: &[u8], proof: &Proof, root: &[u8]) -> bool {
+    if proof.index >= proof.leaves {
+        return false;
+    }
+    let mut steps = proof.path.iter();
+    let mut computed = leaf(hasher, chunk_hash);
+    let (mut position, mut width) = (proof.index, proof.leaves);
+    while width > 1 {
+        // The last node of a level without a sibling is carried up.
+        let side = if position % 2 == 1 {
+            Some(Side::Left)
+        } else {
+            (position + 1 < width).then_some(Side::Right)
+        };
+        if let Some(side) = side {
+            match steps.next() {
+                Some(step) if step.side == side => {
+                    computed = match side {
+                        Side::Left => parent(hasher, &step.sibling, &computed),
+                        Side::Right => parent(hasher, &computed, &step.sibling),
+                    };
+                }
+                _ => return false,
+            }
+        }
+        position /= 2;
+        width = width.div_ceil(2);
+    }
+    steps.next().is_none() && computed == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Sha256Hasher;
+
+    fn leaves(n: u8) -> Vec<Vec<u8>> {
+        (0..n).map(|i| Sha256Hasher.hash(&[i])).collect()
+    }
+
+    #[test]
+    fn proofs_verify_only_at_their_own_position() {
+        for n in 1..=9 {
+            let root = root(&Sha256Hasher, leaves(n)).unwrap();
+            for index in 0..n as usize {
+                let proof = prove(&Sha256Hasher, leaves(n), index).unwrap();
+                assert_eq!(Proof::parse(&proof.to_string()), Some(proof.clone()));
+                let chunk_hash = &leaves(n)[index];
+                assert!(verify(&Sha256Hasher, chunk_hash, &proof, &root), "{} of {}", index, n);
+
+                for wrong in [index ^ 1, index + 1, index.wrapping_sub(1)] {
+                    let moved = Proof { index: wrong, ..proof.clone() };
+                    assert!(!verify(&Sha256Hasher, chunk_hash, &moved, &root), "{} as {} of {}", index, wrong, n);
+                }
+                if let Some((_, shorter)) = proof.path.split_last() {
+                    let truncated = Proof { path: shorter.to_vec(), ..proof.clone() };
+                    assert!(!verify(&Sha256Hasher, chunk_hash, &truncated, &root));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frontiers_build_the_tree_of_carried_up_levels() {
+        // The tree as it was built before, one level at a time.
+        let levels_root = |leaves: Vec<Vec<u8>>| {
+            let mut level: Vec<_> = leaves.iter().map(|chunk_hash| leaf(&Sha256Hasher, chunk_hash)).collect();
+            while level.len() > 1 {
+                level = level.chunks(2)
+                    .map(|pair| match pair {
+                        [left, right] => parent(&Sha256Hasher, left, right),
+                        [single] => single.clone(),
+                        _ => unreachable!(),
+                    })
+                    .collect();
+            }
+            level.pop()
+        };
+        for n in 0..=40 {
+            let expected = levels_root(leaves(n));
+            assert_eq!(root(&Sha256Hasher, leaves(n)), expected, "{} leaves", n);
+            for split in 0..=n as usize {
+                let mut first = TreeBuilder::new(&Sha256Hasher);
+                leaves(n)[..split].iter().for_each(|chunk_hash| first.push(chunk_hash));
+                let mut resumed = TreeBuilder::resume(&Sha256Hasher, split, first.frontier().to_vec()).unwrap();
+                leaves(n)[split..].iter().for_each(|chunk_hash| resumed.push(chunk_hash));
+                assert_eq!(resumed.root(), expected, "{} leaves resumed after {}", n, split);
+            }
+        }
+        assert!(TreeBuilder::resume(&Sha256Hasher, 3, leaves(1)).is_none());
+    }
+
+    #[test]
+    fn inner_nodes_are_not_leaves() {
+        // The node over the first four of eight leaves, with the one over the
+        // other four as its path, doesn't pass as a leaf.
+        let proof = prove(&Sha256Hasher, leaves(8), 0).unwrap();
+        let root = root(&Sha256Hasher, leaves(8)).unwrap();
+        let inner = [&proof.path[0], &proof.path[1]].iter().fold(leaf(&Sha256Hasher, &leaves(8)[0]), |acc, step| {
+            parent(&Sha256Hasher, &acc, &step.sibling)
+        });
+        let top = vec![proof.path[2].clone()];
+        for (index, leaves) in [(0, 2), (0, 8), (1, 2)] {
+            assert!(!verify(&Sha256Hasher, &inner, &Proof { index, leaves, path: top.clone() }, &root));
+        }
+        // Without the leaf prefix the same forgery would hold.
+        let unprefixed = unprefixed_root(&Sha256Hasher, vec![inner.clone(), proof.path[2].sibling.clone()]).unwrap();
+        assert_eq!(unprefixed, root);
+    }
+}
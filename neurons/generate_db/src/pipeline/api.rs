@@ -0,0 +1,121 @@
+//! The library API of generation runs: `GenerateConfig` and `generate_blocking`.
+
+use super::*;
+
+/// Settings of a generation run for `generate`, given as the flags
+/// of the binary so that a service runs exactly what the command line would.
+#[derive(Clone)]
+pub struct GenerateConfig {
+    pub(super) args: Args,
+}
+
+impl GenerateConfig {
+    /// Parses `flags` as the command line of a generation run, with or
+    /// without the `generate-all` subcommand, e.g.
+    /// `["--path", "DB.db", "--seed", "7", "--n", "1000"]`. A `--config`
+    /// file is read as it is by the binary. `--max_runtime_secs` is refused:
+    /// its deadline would stop every later run of the process as well.
+    pub fn from_flags<I, T>(flags: I) -> Result<Self, GenError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString>,
+    {
+        let argv: Vec<std::ffi::OsString> = std::iter::once("storer_db_project".into())
+            .chain(flags.into_iter().map(Into::into))
+            .collect();
+        let joined = argv[1..].iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ");
+        let invalid = |reason: String| GenError::InvalidArg { name: "flags", value: joined.clone(), reason };
+        let matches = app()
+            .get_matches_from_safe(config::with_defaults(argv)?)
+            .map_err(|err| invalid(err.message.lines().next().unwrap_or_default().trim_start_matches("error: ").to_string()))?;
+        let args = match matches.subcommand() {
+            ("generate-all", Some(sub_matches)) => Args::for_generate_all(sub_matches)?,
+            ("", None) => Args::from_matches(&matches)?,
+            (other, _) => return Err(invalid(format!("{} is not a generation run", other))),
+        };
+        if args.max_runtime.is_some() {
+            return Err(invalid("--max_runtime_secs is only supported by the binary".to_string()));
+        }
+        Ok(GenerateConfig { args })
+    }
+}
+
+/// Stops the runs it is given to at their next batch boundary, as SIGINT
+/// stops the binary. The rows committed so far are kept, so running the same
+/// config again resumes where it stopped. Clones stop the same runs.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Outcome of one table of a run, as in its `--json` summary line.
+#[derive(Clone, Debug)]
+pub struct TableSummary {
+    pub table: String,
+    pub n_chunks: usize,
+    pub final_seed: Vec<u8>,
+    pub rows_written: usize,
+    pub rows_deleted: usize,
+    pub rows_filled: usize,
+    pub data_bytes: u64,
+    pub stored_bytes: u64,
+    pub manifest_root: Option<Vec<u8>>,
+    /// `written`, `truncated`, `filled`, `complete` or `interrupted`.
+    pub status: &'static str,
+}
+
+/// Outcome of a `generate` run.
+#[derive(Debug)]
+pub struct Summary {
+    /// The tables generated, in partition order.
+    pub tables: Vec<TableSummary>,
+    /// Tables that failed under `--continue_on_error`, with their errors.
+    /// Without it, the first failure is returned as the error of the run.
+    pub failed: Vec<(String, GenError)>,
+}
+
+/// Runs `config` on the calling thread, like the binary but without
+/// progress bars or output, and calls `on_progress` after every committed
+/// batch. Once `cancel` is cancelled, the run stops and its unfinished
+/// tables are reported as `interrupted`. `generate` runs this on tokio's
+/// blocking pool.
+pub fn generate_blocking(
+    config: &GenerateConfig,
+    cancel: &CancelToken,
+    on_progress: impl Fn(ProgressUpdate) + Send + Sync + 'static,
+) -> Result<Summary, GenError> {
+    let args = &Args { cancel: cancel.clone(), ..config.args.clone() };
+    if args.confirm_overwrite {
+        confirm_overwrite(args)?;
+    }
+    let (tables, failures) = generate_run(args, true, Some(Arc::new(on_progress)))?;
+    Ok(Summary {
+        tables: tables.iter()
+            .map(|table| TableSummary {
+                table: table.table.clone(),
+                n_chunks: table.n_chunks,
+                final_seed: table.final_seed.clone(),
+                rows_written: table.rows_written,
+                rows_deleted: table.rows_deleted,
+                rows_filled: table.rows_filled,
+                data_bytes: table.data_bytes,
+                stored_bytes: table.stored_bytes,
+                manifest_root: table.manifest_root.clone(),
+                status: table.status(),
+            })
+            .collect(),
+        failed: failures.into_iter().map(|failure| (failure.table, failure.error)).collect(),
+    })
+}
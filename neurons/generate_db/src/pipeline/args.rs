@@ -0,0 +1,872 @@
+//! Parsed arguments of generation and of every other subcommand.
+
+use super::*;
+
+/// Where generated chunks are kept. `sqlite` stores one row per chunk with
+/// its metadata and checkpoints; `rawfile` writes fixed-size records to a
+/// flat file with a sidecar hash index, trading those features for
+/// sequential write throughput (see the `rawfile` module).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Backend {
+    Sqlite,
+    RawFile,
+}
+
+impl Backend {
+    pub(super) const NAMES: [&'static str; 2] = ["sqlite", "rawfile"];
+
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sqlite" => Some(Backend::Sqlite),
+            "rawfile" => Some(Backend::RawFile),
+            _ => None,
+        }
+    }
+}
+
+/// Output of the `export` subcommand: the chunk bytes back to back, one hex
+/// line per chunk, or one JSON object per chunk like `fetch` prints.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum ExportFormat {
+    Raw,
+    Hex,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub(super) const NAMES: [&'static str; 3] = ["raw", "hex", "ndjson"];
+
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "raw" => Some(ExportFormat::Raw),
+            "hex" => Some(ExportFormat::Hex),
+            "ndjson" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// Command line arguments for a generation run.
+#[derive(Clone)]
+pub(super) struct Args {
+    pub(super) path: String,
+    /// `--key` of a build with the `sqlcipher` feature, which every
+    /// database file of the command is opened with.
+    pub(super) key: Option<String>,
+    pub(super) hash: bool,
+    pub(super) n_chunks: usize,
+    pub(super) chunk_size: usize,
+    pub(super) seed: String,
+    pub(super) delete: bool,
+    /// Ask before `--overwrite` deletes the tables.
+    pub(super) confirm_overwrite: bool,
+    pub(super) batch_size: usize,
+    pub(super) hash_algo: HashAlgo,
+    /// Bytes of each chunk hash kept in the `hash` column.
+    pub(super) hash_bytes: usize,
+    pub(super) hash_encoding: HashEncoding,
+    pub(super) threads: usize,
+    /// Give every thread its own range of the chain, found in a first pass.
+    pub(super) split_chain: bool,
+    pub(super) strict_resume: bool,
+    pub(super) json: bool,
+    /// No progress bars, by `--quiet` or `--progress`; every committed batch
+    /// is logged at info level instead.
+    pub(super) quiet: bool,
+    pub(super) synchronous: String,
+    pub(super) journal: String,
+    pub(super) auto_vacuum: String,
+    pub(super) cache_mb: Option<usize>,
+    /// Pages of write-ahead log after which a commit checkpoints it.
+    pub(super) wal_autocheckpoint: usize,
+    pub(super) genesis_seed: Option<Vec<u8>>,
+    pub(super) no_salt: bool,
+    /// Partition whose index is mixed into the genesis seed.
+    pub(super) partition: Option<PartitionId>,
+    /// One of the tables of `--partitions`.
+    pub(super) in_partitions: bool,
+    /// Fill in the data of hash-only rows before appending.
+    pub(super) fill_data: bool,
+    /// Ids the chunks of the chain are stored at.
+    pub(super) id_layout: IdLayout,
+    pub(super) verify_after: bool,
+    /// Checkpoint and fsync the database before reporting the run as done.
+    pub(super) fsync_on_exit: bool,
+    /// Create the directory of `--path`, or `--db_root_path`, if missing.
+    pub(super) mkdir: bool,
+    /// Go on with the other partitions when one fails.
+    pub(super) continue_on_error: bool,
+    /// Fail unless the database journals in WAL mode, so that other
+    /// processes can read the committed batches during the run.
+    pub(super) reader_safe: bool,
+    /// Time after which the run stops as if interrupted.
+    pub(super) max_runtime: Option<std::time::Duration>,
+    /// Stops the run at the next batch boundary; shared by its threads and
+    /// partitions.
+    pub(super) cancel: CancelToken,
+    /// Shared by the threads and partitions of the run.
+    pub(super) write_throttle: Option<Arc<WriteThrottle>>,
+    /// Latencies of the table's batches under `--timing_histogram`, shared
+    /// by its threads; each partition gets its own.
+    pub(super) timings: Option<Arc<BatchTimings>>,
+    /// Where to write the progress of the table after every batch commit.
+    pub(super) status_file: Option<String>,
+    pub(super) force: bool,
+    pub(super) db_root_path: Option<String>,
+    pub(super) partitions: usize,
+    /// Partitions generated concurrently, more than one only in `generate-all`.
+    pub(super) jobs: usize,
+    pub(super) checkpoint_interval: usize,
+    pub(super) compress: Codec,
+    pub(super) rng: RngAlgo,
+    pub(super) charset: Charset,
+    pub(super) start: Option<(usize, Vec<u8>)>,
+    pub(super) backend: Backend,
+    /// Raw export whose records `import` checks and inserts in place of
+    /// generated chunks.
+    pub(super) import_from: Option<String>,
+}
+
+impl Args {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        let hash_algo = HashAlgo::from_name(matches.value_of("hash_algo").unwrap_or_default())
+            .unwrap_or(HashAlgo::Sha256);
+        let genesis_seed = match matches.value_of("genesis_seed") {
+            Some(value) => {
+                let seed = parse_hex_arg(matches, "genesis_seed")?;
+                if seed.len() != hash_algo.output_len() {
+                    return Err(GenError::InvalidArg {
+                        name: "genesis_seed",
+                        value: value.to_string(),
+                        reason: format!("expected {} bytes, got {}", hash_algo.output_len(), seed.len()),
+                    });
+                }
+                Some(seed)
+            }
+            None => None,
+        };
+        let hash_bytes = match matches.value_of("hash_bytes") {
+            Some(value) => {
+                let hash_bytes = parse_arg(matches, "hash_bytes")?;
+                if !(1..=hash_algo.output_len()).contains(&hash_bytes) {
+                    return Err(GenError::InvalidArg {
+                        name: "hash_bytes",
+                        value: value.to_string(),
+                        reason: format!("must be between 1 and {}", hash_algo.output_len()),
+                    });
+                }
+                hash_bytes
+            }
+            None => hash_algo.output_len(),
+        };
+        let start = match matches.value_of("start_seed") {
+            Some(value) => {
+                let seed = parse_hex_arg(matches, "start_seed")?;
+                if seed.len() != hash_algo.output_len() {
+                    return Err(GenError::InvalidArg {
+                        name: "start_seed",
+                        value: value.to_string(),
+                        reason: format!("expected {} bytes, got {}", hash_algo.output_len(), seed.len()),
+                    });
+                }
+                Some((parse_arg(matches, "start_index")?, seed))
+            }
+            None => None,
+        };
+        let partitions = match matches.value_of("partitions") {
+            Some(_) => parse_count_arg(matches, "partitions")?,
+            None => 1,
+        };
+        let chunk_size = parse_count_arg(matches, "size")?;
+        let n_chunks = match matches.value_of("size_gb") {
+            Some(value) => {
+                let invalid = || GenError::InvalidArg {
+                    name: "size_gb",
+                    value: value.to_string(),
+                    reason: "expected a positive number of GiB".to_string(),
+                };
+                let size_gb: f64 = value.parse().map_err(|_| invalid())?;
+                if !(size_gb.is_finite() && size_gb > 0.0) {
+                    return Err(invalid());
+                }
+                (size_gb * (1u64 << 30) as f64 / chunk_size.max(1) as f64).ceil() as usize
+            }
+            None => parse_count_arg(matches, "n")?,
+        };
+        // Every byte count derived from the arguments (progress bar, disk
+        // estimate) is at most this, so checking it once here keeps the rest
+        // of the arithmetic in range.
+        let total_bytes = (chunk_size as u64)
+            .checked_add(3 * hash_algo.output_len() as u64 + ROW_OVERHEAD)
+            .and_then(|row| row.checked_mul(n_chunks as u64));
+        if total_bytes.is_none() {
+            return Err(GenError::InvalidArg {
+                name: "n",
+                value: n_chunks.to_string(),
+                reason: format!("{} chunks of {} bytes overflow a 64-bit byte count", n_chunks, chunk_size),
+            });
+        }
+        // clap restricts the value to the known backend names.
+        let backend = Backend::from_name(matches.value_of("backend").unwrap_or_default()).unwrap_or(Backend::Sqlite);
+        if backend == Backend::RawFile && matches.value_of("path") == Some(MEMORY_DB) {
+            return Err(GenError::InvalidArg {
+                name: "path",
+                value: MEMORY_DB.to_string(),
+                reason: "in-memory databases are only supported by the sqlite backend".to_string(),
+            });
+        }
+        let id_layout = IdLayout {
+            offset: match matches.value_of("id_offset") {
+                Some(_) => parse_arg(matches, "id_offset")?,
+                None => 0,
+            },
+            stride: match matches.value_of("id_stride") {
+                Some(_) => parse_count_arg(matches, "id_stride")?,
+                None => 1,
+            },
+        };
+        // Like `--id`, stored ids must stay in the sqlite rowid range.
+        if let Some(last) = n_chunks.checked_sub(1) {
+            let last_id = (last as u64).checked_mul(id_layout.stride as u64).and_then(|id| id.checked_add(id_layout.offset as u64));
+            if last_id.is_none_or(|id| id > i64::MAX as u64) {
+                return Err(GenError::InvalidArg {
+                    name: "id_stride",
+                    value: id_layout.stride.to_string(),
+                    reason: format!("the id of chunk {} lies beyond the maximum rowid {}", last, i64::MAX),
+                });
+            }
+        }
+        if backend == Backend::RawFile {
+            for arg in [
+                "hash", "hash_bytes", "hash_encoding", "compress", "start_index", "fill_data", "partition_index", "key",
+                "id_stride", "id_offset", "reader_safe", "timing_histogram",
+            ] {
+                if matches.occurrences_of(arg) > 0 {
+                    return Err(GenError::InvalidArg {
+                        name: "backend",
+                        value: "rawfile".to_string(),
+                        reason: format!("--{} is only supported by the sqlite backend", arg),
+                    });
+                }
+            }
+        }
+        if backend == Backend::Sqlite && chunk_size as i64 > PAGE_SIZE {
+            log::warn!(
+                "Chunks of {} bytes exceed the {} byte page size and will be stored in overflow pages",
+                chunk_size, PAGE_SIZE
+            );
+        }
+        if matches.is_present("reader_safe") && matches.value_of("journal").is_some_and(|mode| mode.eq_ignore_ascii_case("OFF")) {
+            return Err(GenError::InvalidArg {
+                name: "journal",
+                value: "OFF".to_string(),
+                reason: "--reader_safe needs WAL, without a journal readers may see half-written pages".to_string(),
+            });
+        }
+        let batch_size = parse_count_arg(matches, "batch_size")?;
+        // clap restricts the value to the known encoding names.
+        let hash_encoding = HashEncoding::from_name(matches.value_of("hash_encoding").unwrap_or_default())
+            .unwrap_or(HashEncoding::Hex);
+        let wal_autocheckpoint = match matches.value_of("wal_autocheckpoint") {
+            Some(_) => parse_count_arg(matches, "wal_autocheckpoint")?,
+            None => default_wal_autocheckpoint(
+                batch_size, chunk_size, matches.is_present("hash"), hash_algo, hash_bytes, hash_encoding,
+            ),
+        };
+        Ok(Args {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            hash: matches.is_present("hash"),
+            n_chunks,
+            chunk_size,
+            seed: parse_table_seed(matches)?,
+            delete: matches.is_present("delete") || matches.is_present("overwrite"),
+            confirm_overwrite: matches.is_present("overwrite") && !matches.is_present("yes"),
+            batch_size,
+            // clap restricts the value to the known algorithm names.
+            hash_algo,
+            hash_bytes,
+            hash_encoding,
+            threads: parse_count_arg(matches, "threads")?,
+            split_chain: matches.is_present("split_chain"),
+            strict_resume: matches.is_present("strict_resume"),
+            json: matches.is_present("json"),
+            quiet: progress_hidden(matches),
+            synchronous: matches.value_of("synchronous").unwrap_or("FULL").to_ascii_uppercase(),
+            journal: matches.value_of("journal").unwrap_or("WAL").to_ascii_uppercase(),
+            auto_vacuum: matches.value_of("auto_vacuum").unwrap_or("FULL").to_ascii_uppercase(),
+            cache_mb: match matches.value_of("cache_mb") {
+                Some(_) => Some(parse_count_arg(matches, "cache_mb")?),
+                None => None,
+            },
+            wal_autocheckpoint,
+            genesis_seed,
+            no_salt: matches.is_present("no_salt"),
+            partition: match matches.value_of("partition_index") {
+                Some(_) => Some(PartitionId(parse_arg(matches, "partition_index")? as u64)),
+                None => None,
+            },
+            in_partitions: false,
+            fill_data: matches.is_present("fill_data"),
+            id_layout,
+            verify_after: matches.is_present("verify_after"),
+            fsync_on_exit: !matches.is_present("no_fsync_on_exit"),
+            mkdir: !matches.is_present("no_mkdir"),
+            continue_on_error: matches.is_present("continue_on_error"),
+            reader_safe: matches.is_present("reader_safe"),
+            max_runtime: match matches.value_of("max_runtime_secs") {
+                Some(_) => Some(std::time::Duration::from_secs(parse_count_arg(matches, "max_runtime_secs")? as u64)),
+                None => None,
+            },
+            cancel: CancelToken::default(),
+            write_throttle: match matches.value_of("max_write_mbps") {
+                Some(_) => Some(Arc::new(WriteThrottle::new(parse_count_arg(matches, "max_write_mbps")? as f64 * 1e6))),
+                None => None,
+            },
+            timings: matches.is_present("timing_histogram").then(Arc::default),
+            status_file: matches.value_of("status_file").map(str::to_string),
+            force: matches.is_present("force"),
+            db_root_path: matches.value_of("db_root_path").map(str::to_string),
+            partitions,
+            jobs: 1,
+            checkpoint_interval: parse_arg(matches, "checkpoint_interval")?,
+            // clap restricts the value to the known codec names.
+            compress: Codec::from_name(matches.value_of("compress").unwrap_or_default()).unwrap_or(Codec::None),
+            rng: chunk_source(matches).0,
+            charset: chunk_source(matches).1,
+            start,
+            backend,
+            import_from: None,
+        })
+    }
+}
+
+impl Args {
+    /// Arguments of `generate-all`: those of a partitioned run, with `--n`
+    /// derived from `--size_gb` and up to `--jobs` partitions in flight.
+    pub(super) fn for_generate_all(matches: &ArgMatches) -> Result<Self, GenError> {
+        let mut args = Args::from_matches(matches)?;
+        args.jobs = match matches.value_of("jobs") {
+            Some(_) => parse_count_arg(matches, "jobs")?,
+            None => std::thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        };
+        Ok(args)
+    }
+}
+
+/// Command line arguments for the `verify` subcommand.
+pub(super) struct VerifyArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) chunk_size: Option<usize>,
+    pub(super) from: usize,
+    /// Workers checking ranges of the chain at once.
+    pub(super) threads: usize,
+    /// Check only a random sample of the rows.
+    pub(super) sample: Option<Sample>,
+    /// Compare only the hash chain, not the data; hash-only tables always are.
+    pub(super) only_hashes: bool,
+    pub(super) backend: Backend,
+}
+
+/// Rows picked by `--sample_verify`: `percent` of them, drawn by a ChaCha20
+/// rng seeded with `seed`, so the same seed picks the same rows again.
+pub(super) struct Sample {
+    pub(super) percent: f64,
+    pub(super) seed: u64,
+}
+
+impl VerifyArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(VerifyArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            chunk_size: match matches.value_of("size") {
+                Some(_) => Some(parse_count_arg(matches, "size")?),
+                None => None,
+            },
+            from: parse_arg(matches, "from")?,
+            threads: parse_count_arg(matches, "threads")?,
+            sample: match matches.value_of("sample_verify") {
+                Some(value) => {
+                    let percent = value.parse::<f64>().ok().filter(|percent| *percent > 0.0 && *percent <= 100.0);
+                    Some(Sample {
+                        percent: percent.ok_or_else(|| GenError::InvalidArg {
+                            name: "sample_verify",
+                            value: value.to_string(),
+                            reason: "expected a percentage above 0 and at most 100".to_string(),
+                        })?,
+                        seed: match matches.value_of("sample_seed") {
+                            Some(_) => parse_arg(matches, "sample_seed")? as u64,
+                            // Halved to stay within what --sample_seed takes back.
+                            None => rand::random::<u64>() >> 1,
+                        },
+                    })
+                }
+                None => None,
+            },
+            only_hashes: matches.is_present("verify_only_hashes"),
+            backend: Backend::from_name(matches.value_of("backend").unwrap_or_default()).unwrap_or(Backend::Sqlite),
+        })
+    }
+}
+
+/// Command line arguments for the `fetch` and `challenge` subcommands.
+pub(super) struct RowArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) id: usize,
+    pub(super) chunk_size: Option<usize>,
+    pub(super) backend: Backend,
+}
+
+impl RowArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(RowArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            id: parse_arg(matches, "id")?,
+            chunk_size: match matches.value_of("size") {
+                Some(_) => Some(parse_count_arg(matches, "size")?),
+                None => None,
+            },
+            backend: Backend::from_name(matches.value_of("backend").unwrap_or_default()).unwrap_or(Backend::Sqlite),
+        })
+    }
+}
+
+/// Command line arguments for the `prove-progress` subcommand.
+pub(super) struct ProgressProofArgs {
+    pub(super) row: RowArgs,
+    pub(super) nonce: Vec<u8>,
+}
+
+impl ProgressProofArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(ProgressProofArgs { row: RowArgs::from_matches(matches)?, nonce: parse_hex_arg(matches, "nonce")? })
+    }
+}
+
+/// Command line arguments for the `export` subcommand.
+pub(super) struct ExportArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) backend: Backend,
+    pub(super) format: ExportFormat,
+}
+
+impl ExportArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(ExportArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            backend: Backend::from_name(matches.value_of("backend").unwrap_or_default()).unwrap_or(Backend::Sqlite),
+            // clap restricts the value to the known format names.
+            format: ExportFormat::from_name(matches.value_of("format").unwrap_or_default()).unwrap_or(ExportFormat::Raw),
+        })
+    }
+}
+
+/// Command line arguments for the `repair` subcommand.
+pub(super) struct RepairArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) chunk_size: Option<usize>,
+}
+
+impl RepairArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(RepairArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            chunk_size: match matches.value_of("size") {
+                Some(_) => Some(parse_count_arg(matches, "size")?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// Command line arguments for the `regenerate` subcommand.
+pub(super) struct RegenerateArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) chunk_size: Option<usize>,
+    pub(super) from: usize,
+    pub(super) to: usize,
+    pub(super) write: bool,
+}
+
+impl RegenerateArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        let from = parse_arg(matches, "from")?;
+        let to = parse_arg(matches, "to")?;
+        if to <= from {
+            return Err(GenError::InvalidArg {
+                name: "to",
+                value: to.to_string(),
+                reason: format!("must be greater than --from {}", from),
+            });
+        }
+        Ok(RegenerateArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            chunk_size: match matches.value_of("size") {
+                Some(_) => Some(parse_count_arg(matches, "size")?),
+                None => None,
+            },
+            from,
+            to,
+            write: matches.is_present("write"),
+        })
+    }
+}
+
+/// Command line arguments for the `diff` subcommand.
+pub(super) struct DiffArgs {
+    pub(super) db_a: String,
+    pub(super) db_b: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+}
+
+impl DiffArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(DiffArgs {
+            db_a: matches.value_of("db_a").unwrap_or_default().to_string(),
+            db_b: matches.value_of("db_b").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+        })
+    }
+}
+
+/// Command line arguments for the `merge` subcommand.
+pub(super) struct MergeArgs {
+    pub(super) inputs: Vec<String>,
+    pub(super) output: String,
+    pub(super) key: Option<String>,
+    /// Table the inputs are merged into.
+    pub(super) seed: String,
+    pub(super) json: bool,
+}
+
+impl MergeArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(MergeArgs {
+            inputs: matches.value_of("inputs").unwrap_or_default().split(',').map(str::to_string).collect(),
+            output: matches.value_of("output").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            json: matches.is_present("json"),
+        })
+    }
+}
+
+/// Command line arguments for the `import` subcommand.
+pub(super) struct ImportArgs {
+    pub(super) from: String,
+    pub(super) db: String,
+    pub(super) key: Option<String>,
+    /// Table to import into, instead of the one named in the footer.
+    pub(super) seed: Option<String>,
+    pub(super) json: bool,
+}
+
+impl ImportArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(ImportArgs {
+            from: matches.value_of("from").unwrap_or_default().to_string(),
+            db: matches.value_of("db").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: match matches.value_of("seed") {
+                Some(_) => Some(parse_table_seed(matches)?),
+                None => None,
+            },
+            json: matches.is_present("json"),
+        })
+    }
+}
+
+/// Command line arguments for the `merkle-root` and `prove` subcommands.
+pub(super) struct MerkleArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) id: Option<usize>,
+}
+
+impl MerkleArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(MerkleArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            id: match matches.value_of("id") {
+                Some(_) => Some(parse_arg(matches, "id")?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// Command line arguments for the `verify-proof` subcommand.
+pub(super) struct VerifyProofArgs {
+    pub(super) leaf: Vec<u8>,
+    pub(super) proof: merkle::Proof,
+    pub(super) root: Vec<u8>,
+    pub(super) hash_algo: HashAlgo,
+}
+
+impl VerifyProofArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        let proof = matches.value_of("proof").unwrap_or_default();
+        Ok(VerifyProofArgs {
+            leaf: parse_hex_arg(matches, "leaf")?,
+            proof: merkle::Proof::parse(proof).ok_or_else(|| GenError::InvalidArg {
+                name: "proof",
+                value: proof.to_string(),
+                reason: "expected <index>/<leaves> followed by comma separated L:<hex> or R:<hex> steps".to_string(),
+            })?,
+            root: parse_hex_arg(matches, "root")?,
+            hash_algo: HashAlgo::from_name(matches.value_of("hash_algo").unwrap_or_default())
+                .unwrap_or(HashAlgo::Sha256),
+        })
+    }
+}
+
+/// Command line arguments for the `bench` subcommand.
+pub(super) struct BenchArgs {
+    pub(super) n_chunks: usize,
+    pub(super) chunk_size: usize,
+    pub(super) hash_algo: HashAlgo,
+    pub(super) rng: RngAlgo,
+    pub(super) charset: Charset,
+}
+
+impl BenchArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(BenchArgs {
+            n_chunks: parse_count_arg(matches, "n")?,
+            chunk_size: parse_count_arg(matches, "size")?,
+            hash_algo: HashAlgo::from_name(matches.value_of("hash_algo").unwrap_or_default())
+                .unwrap_or(HashAlgo::Sha256),
+            rng: RngAlgo::from_name(matches.value_of("rng").unwrap_or_default()).unwrap_or(RngAlgo::Std),
+            charset: Charset::from_name(matches.value_of("charset").unwrap_or_default()).unwrap_or(Charset::Alphanumeric),
+        })
+    }
+}
+
+/// Command line arguments for the `final-seed` and `testvectors` subcommands.
+pub(super) struct ChainArgs {
+    pub(super) n_chunks: usize,
+    pub(super) chunk_size: usize,
+    /// Table whose salted genesis seed starts the chain, `None` for the
+    /// unsalted one of `--no_salt` tables.
+    pub(super) seed: Option<String>,
+    /// Partition index mixed into the salted genesis seed.
+    pub(super) partition: Option<PartitionId>,
+    pub(super) hash_algo: HashAlgo,
+    pub(super) rng: RngAlgo,
+    pub(super) charset: Charset,
+}
+
+impl ChainArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(ChainArgs {
+            n_chunks: parse_arg(matches, "n")?,
+            chunk_size: parse_count_arg(matches, "size")?,
+            seed: match matches.value_of("seed") {
+                Some(_) => Some(parse_table_seed(matches)?),
+                None => None,
+            },
+            partition: match matches.value_of("partition_index") {
+                Some(_) => Some(PartitionId(parse_arg(matches, "partition_index")? as u64)),
+                None => None,
+            },
+            hash_algo: HashAlgo::from_name(matches.value_of("hash_algo").unwrap_or_default())
+                .unwrap_or(HashAlgo::Sha256),
+            rng: chunk_source(matches).0,
+            charset: chunk_source(matches).1,
+        })
+    }
+
+    /// The chain of the table, at its genesis seed.
+    pub(super) fn chunk_generator(&self) -> ChunkGenerator {
+        let hasher = self.hash_algo.hasher();
+        let seed = match (&self.seed, self.partition) {
+            (Some(table), Some(partition)) => partition.genesis_seed(hasher.as_ref(), &format!("DB{}", table), self.chunk_size),
+            (Some(table), None) => ChunkGenerator::salted_genesis_seed(hasher.as_ref(), &format!("DB{}", table), self.chunk_size),
+            (None, _) => ChunkGenerator::genesis_seed(hasher.as_ref(), self.chunk_size),
+        };
+        ChunkGenerator::new(seed, self.chunk_size, hasher)
+            .with_rng(self.rng)
+            .with_charset(self.charset)
+    }
+}
+
+/// Command line arguments for the `truncate` subcommand.
+pub(super) struct TruncateArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) to: usize,
+    /// auto_vacuum mode to switch a database without one to, if asked.
+    pub(super) auto_vacuum: Option<String>,
+}
+
+impl TruncateArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(TruncateArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            to: parse_arg(matches, "to")?,
+            auto_vacuum: matches.value_of("auto_vacuum").map(str::to_ascii_uppercase),
+        })
+    }
+}
+
+/// Command line arguments for the `info` and `rehash` subcommands.
+pub(super) struct InfoArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    pub(super) json: bool,
+}
+
+impl InfoArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(InfoArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            json: matches.is_present("json"),
+        })
+    }
+}
+
+/// Command line arguments for the `list` subcommand.
+pub(super) struct ListArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) json: bool,
+}
+
+impl ListArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(ListArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            json: matches.is_present("json"),
+        })
+    }
+}
+
+/// Command line arguments for the `doctor` subcommand.
+pub(super) struct DoctorArgs {
+    pub(super) path: String,
+    pub(super) key: Option<String>,
+    pub(super) seed: String,
+    /// Rows the table is meant to reach, to check the free space against.
+    pub(super) n_chunks: Option<usize>,
+    /// Trailing rows to regenerate and compare.
+    pub(super) rows: usize,
+    pub(super) json: bool,
+}
+
+impl DoctorArgs {
+    pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, GenError> {
+        Ok(DoctorArgs {
+            path: matches.value_of("path").unwrap_or_default().to_string(),
+            key: matches.value_of("key").map(str::to_string),
+            seed: parse_table_seed(matches)?,
+            n_chunks: match matches.value_of("n") {
+                Some(_) => Some(parse_count_arg(matches, "n")?),
+                None => None,
+            },
+            rows: parse_count_arg(matches, "rows")?,
+            json: matches.is_present("json"),
+        })
+    }
+}
+
+/// The PRNG and charset of the chunks, which `--incompressible` fixes to the
+/// ChaCha20 keystream. clap keeps it from being combined with either option.
+pub(super) fn chunk_source(matches: &ArgMatches) -> (RngAlgo, Charset) {
+    if matches.is_present("incompressible") {
+        return INCOMPRESSIBLE;
+    }
+    // clap restricts the values to the known names.
+    (
+        RngAlgo::from_name(matches.value_of("rng").unwrap_or_default()).unwrap_or(RngAlgo::Std),
+        Charset::from_name(matches.value_of("charset").unwrap_or_default()).unwrap_or(Charset::Alphanumeric),
+    )
+}
+
+/// Reads the table seed, which is interpolated into SQL as `DB{seed}` and
+/// so must be checked before any statement is built.
+pub(super) fn parse_table_seed(matches: &ArgMatches) -> Result<String, GenError> {
+    check_table_seed(matches.value_of("seed").unwrap_or_default())
+}
+
+/// The seed is spliced into SQL as part of the table name.
+pub(super) fn check_table_seed(value: &str) -> Result<String, GenError> {
+    if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(GenError::InvalidArg {
+            name: "seed",
+            value: value.to_string(),
+            reason: "only ASCII letters, digits and '_' are allowed".to_string(),
+        });
+    }
+    Ok(value.to_string())
+}
+
+pub(super) fn parse_hex_arg(matches: &ArgMatches, arg: &'static str) -> Result<Vec<u8>, GenError> {
+    let value = matches.value_of(arg).unwrap_or_default();
+    hex::decode(value).map_err(|err| GenError::InvalidArg { name: arg, value: value.to_string(), reason: err.to_string() })
+}
+
+pub(super) fn parse_arg(matches: &ArgMatches, arg: &'static str) -> Result<usize, GenError> {
+    let value = matches.value_of(arg).unwrap_or_default();
+    // `usize::from_str` takes a leading `+`, and a leading `-` would only
+    // be reported as an invalid digit.
+    if value.starts_with(['+', '-']) {
+        return Err(GenError::InvalidArg {
+            name: arg,
+            value: value.to_string(),
+            reason: "expected a non-negative integer without a sign".to_string(),
+        });
+    }
+    // Parsed as an i64 so that every count and id stays a valid (positive)
+    // SQLite rowid when it is cast for a query.
+    let parsed: i64 = value.parse().map_err(|source| GenError::Parse { name: arg, value: value.to_string(), source })?;
+    usize::try_from(parsed).map_err(|_| GenError::InvalidArg {
+        name: arg,
+        value: value.to_string(),
+        reason: format!("larger than the maximum {}", usize::MAX),
+    })
+}
+
+/// Like `parse_arg`, for counts and sizes where zero means nothing.
+pub(super) fn parse_count_arg(matches: &ArgMatches, arg: &'static str) -> Result<usize, GenError> {
+    let value = parse_arg(matches, arg)?;
+    if value == 0 {
+        return Err(GenError::InvalidArg {
+            name: arg,
+            value: "0".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    Ok(value)
+}
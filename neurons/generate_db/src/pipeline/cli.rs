@@ -0,0 +1,1015 @@
+//! The command line: `app` and the `run_cli` entry point of the binary.
+
+use super::*;
+
+/// Exit code of a generation run that found every table already at `--n`
+/// rows and neither wrote nor deleted anything.
+pub(super) const EXIT_ALREADY_COMPLETE: i32 = 3;
+
+pub(super) fn db_path_arg() -> Arg<'static, 'static> {
+    Arg::with_name("path")
+        .long("path")
+        .value_name("DB_PATH")
+        .help("Path to the SQLite database")
+        .required(true)
+        .takes_value(true)
+}
+
+pub(super) fn backend_arg() -> Arg<'static, 'static> {
+    Arg::with_name("backend")
+        .long("backend")
+        .value_name("BACKEND")
+        .help("Storage backend: an SQLite table, or a flat file of chunks at --path with \
+               {path}.idx and {path}.json alongside it")
+        .possible_values(&Backend::NAMES)
+        .default_value("sqlite")
+        .takes_value(true)
+}
+
+pub(super) fn incompressible_arg() -> Arg<'static, 'static> {
+    Arg::with_name("incompressible")
+        .long("incompressible")
+        .help("Fill the chunks with the ChaCha20 keystream of their seeds, high-entropy bytes that no \
+               compressor can shrink; the same as --rng chacha20 --charset bytes")
+        .conflicts_with_all(&["rng", "charset"])
+        .takes_value(false)
+}
+
+/// Only in builds with the `sqlcipher` feature, which link SQLCipher in place
+/// of plain SQLite.
+#[cfg(feature = "sqlcipher")]
+pub(super) fn key_arg() -> Arg<'static, 'static> {
+    Arg::with_name("key")
+        .long("key")
+        .value_name("KEY")
+        .env("STORER_DB_KEY")
+        .hide_env_values(true)
+        .help("Encrypt the database files with this SQLCipher key, for every command. There is no way \
+               to recover the data without it, a key given on the command line shows up in the process \
+               list (prefer the environment variable), and tools on plain SQLite, the miner's and \
+               validator's Python included, can't open the files")
+        .global(true)
+        .takes_value(true)
+}
+
+pub(super) fn table_seed_arg() -> Arg<'static, 'static> {
+    Arg::with_name("seed")
+        .long("seed")
+        .value_name("seed")
+        .help("Seed the table was generated with.")
+        .required(true)
+        .takes_value(true)
+}
+
+/// Arguments of the subcommands that walk a table's chain without a
+/// database: `final-seed` and `testvectors`.
+pub(super) fn chain_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("n")
+            .long("n")
+            .value_name("NUM_CHUNKS")
+            .help("Number of chunks")
+            .required(true)
+            .takes_value(true),
+        Arg::with_name("size")
+            .long("size")
+            .visible_alias("chunk_size")
+            .value_name("CHUNK_SIZE")
+            .help("Size of each chunk in bytes")
+            .required(true)
+            .takes_value(true),
+        Arg::with_name("seed")
+            .long("seed")
+            .value_name("seed")
+            .help("Seed of the table, which salts its genesis seed")
+            .required_unless("no_salt")
+            .takes_value(true),
+        Arg::with_name("no_salt")
+            .long("no_salt")
+            .help("Start from the unsalted genesis seed of tables generated with --no_salt")
+            .conflicts_with("seed")
+            .takes_value(false),
+        Arg::with_name("partition_index")
+            .long("partition_index")
+            .value_name("K")
+            .help("Start from the genesis seed of partition K of the table")
+            .requires("seed")
+            .takes_value(true),
+        Arg::with_name("hash_algo")
+            .long("hash_algo")
+            .value_name("HASH_ALGO")
+            .help("Hash used for chunk hashes and the seed chain")
+            .possible_values(&HashAlgo::NAMES)
+            .default_value("sha256")
+            .takes_value(true),
+        Arg::with_name("rng")
+            .long("rng")
+            .value_name("RNG")
+            .help("PRNG for the chunk characters")
+            .possible_values(&RngAlgo::NAMES)
+            .default_value("std")
+            .takes_value(true),
+        Arg::with_name("charset")
+            .long("charset")
+            .value_name("CHARSET")
+            .help("Characters of the chunks")
+            .possible_values(&Charset::NAMES)
+            .default_value("alphanumeric")
+            .takes_value(true),
+        incompressible_arg(),
+    ]
+}
+
+/// Whether the progress bars are replaced by log lines: with `--quiet`,
+/// `--progress never`, or by default when stderr is not a terminal, where
+/// the redrawn bars would only garble a log file. Subcommands without the
+/// options draw no bars to begin with.
+pub(super) fn progress_hidden(matches: &ArgMatches) -> bool {
+    match matches.value_of("progress") {
+        _ if matches.is_present("quiet") => true,
+        Some("always") | None => false,
+        Some("never") => true,
+        Some(_) => !io::stderr().is_terminal(),
+    }
+}
+
+/// Arguments shared by the generation command and `generate-all`.
+pub(super) fn generation_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("hash")
+            .long("hash")
+            .value_name("hash")
+            .help("Stores the hashes instead of the data itself.")
+            .required(false)
+            .takes_value(false),
+        Arg::with_name("size")
+            .long("size")
+            .value_name("CHUNK_SIZE")
+            .help("Size of each chunk in bytes")
+            .required(true)
+            .takes_value(true),
+        Arg::with_name("seed")
+            .long("seed")
+            .value_name("seed")
+            .help("Seed used to generate the data.")
+            .required(true)
+            .takes_value(true),
+        Arg::with_name("delete")
+            .long("delete")
+            .help("Delete the table if it exists.")
+            .required(false)
+            .takes_value(false),
+        Arg::with_name("overwrite")
+            .long("overwrite")
+            .help("Delete the table with its metadata and checkpoints, after asking on the terminal, \
+                   and generate it again from the genesis seed, e.g. after changing --hash_algo")
+            .takes_value(false),
+        Arg::with_name("yes")
+            .long("yes")
+            .help("Overwrite without asking, for --overwrite in runs without a terminal")
+            .requires("overwrite")
+            .takes_value(false),
+        Arg::with_name("batch_size")
+            .long("batch_size")
+            .value_name("BATCH_SIZE")
+            .help("Number of rows inserted per transaction")
+            .default_value("1000")
+            .takes_value(true),
+        Arg::with_name("hash_algo")
+            .long("hash_algo")
+            .value_name("HASH_ALGO")
+            .help("Hash used for chunk hashes and the seed chain")
+            .possible_values(&HashAlgo::NAMES)
+            .default_value("sha256")
+            .takes_value(true),
+        Arg::with_name("hash_bytes")
+            .long("hash_bytes")
+            .value_name("N")
+            .help("Keep only the first N bytes of each chunk hash in the hash column; the seed chain stays full width")
+            .takes_value(true),
+        Arg::with_name("hash_encoding")
+            .long("hash_encoding")
+            .value_name("ENCODING")
+            .help("Store the hash column as hex text or as raw bytes, half the size")
+            .possible_values(&HashEncoding::NAMES)
+            .default_value("hex")
+            .takes_value(true),
+        Arg::with_name("threads")
+            .long("threads")
+            .value_name("THREADS")
+            .help("Number of threads generating and inserting chunks: one walks the chain while \
+                   one connection inserts the batches before it, and the rest compress chunks")
+            .default_value("1")
+            .takes_value(true),
+        Arg::with_name("split_chain")
+            .long("split_chain")
+            .help("With --threads, find each thread's first seed in a hash-only pass over the chain, then \
+                   let every thread generate and insert its own range; the pass costs about as much as \
+                   generating, so this only pays off when inserting is much slower")
+            .takes_value(false),
+        Arg::with_name("strict_resume")
+            .long("strict_resume")
+            .help("Fail instead of deleting trailing rows that don't match the seed chain on resume.")
+            .takes_value(false),
+        Arg::with_name("fill_data")
+            .long("fill_data")
+            .help("Store the data of the rows of a table generated with --hash, checking every \
+                   regenerated chunk against its stored hash, before generating any new rows")
+            .conflicts_with("hash")
+            .takes_value(false),
+        Arg::with_name("verify_after")
+            .long("verify_after")
+            .help("Reopen each table once it is generated and verify every row, failing on any mismatch")
+            .takes_value(false),
+        Arg::with_name("continue_on_error")
+            .long("continue_on_error")
+            .help("With --db_root_path, log a partition that fails and go on with the others; the run \
+                   still exits nonzero and names the failed tables at the end")
+            .requires("db_root_path")
+            .takes_value(false),
+        Arg::with_name("reader_safe")
+            .long("reader_safe")
+            .help("Guarantee that fetch, challenge and the other read-only subcommands can run against \
+                   --path while it is generated: the run fails unless the database journals in WAL mode, \
+                   where readers see every committed batch and nothing of the one being written")
+            .takes_value(false),
+        Arg::with_name("max_runtime_secs")
+            .long("max_runtime_secs")
+            .value_name("SECS")
+            .help("Stop after SECS seconds as on Ctrl-C: finish and commit the current batch, print the \
+                   last committed id and exit 0, so a later run resumes from there")
+            .takes_value(true),
+        Arg::with_name("max_write_mbps")
+            .long("max_write_mbps")
+            .value_name("MBPS")
+            .help("Pace batch commits to write at most MBPS megabytes of rows per second across all \
+                   threads and partitions, leaving disk bandwidth to other processes")
+            .takes_value(true),
+        Arg::with_name("timing_histogram")
+            .long("timing_histogram")
+            .help("Record how long each batch took to generate and insert, which is mostly hashing, and \
+                   to commit, which is mostly the disk, and print their p50, p99 and max at the end")
+            .takes_value(false),
+        Arg::with_name("status_file")
+            .long("status_file")
+            .value_name("PATH")
+            .help("Atomically rewrite PATH with the table's {id, total, bytes, eta_s} as JSON after every \
+                   batch commit; partition i writes PATH.i")
+            .takes_value(true),
+        Arg::with_name("json")
+            .long("json")
+            .help("Hide the progress bar and print a JSON summary as the last line of stdout.")
+            .takes_value(false),
+        Arg::with_name("quiet")
+            .long("quiet")
+            .help("Draw no progress bars and log a plain line per committed batch instead, at the info level \
+                   that --quiet makes the default. Same as --progress never")
+            .conflicts_with("progress")
+            .takes_value(false),
+        Arg::with_name("progress")
+            .long("progress")
+            .value_name("WHEN")
+            .help("Draw the progress bars always, never (as --quiet), or only when stderr is a terminal, \
+                   so that logs captured by a process manager get plain lines")
+            .possible_values(&["always", "never", "auto"])
+            .default_value("auto")
+            .takes_value(true),
+        Arg::with_name("synchronous")
+            .long("synchronous")
+            .value_name("MODE")
+            .help("SQLite synchronous mode. FULL syncs every commit; NORMAL is faster and \
+                   can only lose the latest batches on power loss; OFF is fastest but a \
+                   crash or power loss may corrupt the database")
+            .possible_values(&["OFF", "NORMAL", "FULL"])
+            .case_insensitive(true)
+            .default_value("FULL")
+            .takes_value(true),
+        Arg::with_name("no_fsync_on_exit")
+            .long("no_fsync_on_exit")
+            .help("Skip the final checkpoint and fsync of the database file, which otherwise make sure \
+                   the reported final seed is on disk even with --synchronous OFF or --journal OFF")
+            .takes_value(false),
+        Arg::with_name("no_mkdir")
+            .long("no_mkdir")
+            .help("Fail if the directory of --path or --db_root_path doesn't exist, instead of creating it")
+            .takes_value(false),
+        Arg::with_name("journal")
+            .long("journal")
+            .value_name("MODE")
+            .help("SQLite journal mode. WAL survives a crash or power loss and lets other \
+                   processes read committed batches during the run, at roughly 10-15% lower \
+                   throughput; OFF is fastest but a crash or a full disk may corrupt the database, \
+                   and readers may see half-written pages")
+            .possible_values(&["WAL", "OFF"])
+            .case_insensitive(true)
+            .default_value("WAL")
+            .takes_value(true),
+        Arg::with_name("auto_vacuum")
+            .long("auto_vacuum")
+            .value_name("MODE")
+            .help("SQLite auto_vacuum mode of new databases. FULL shrinks the file as soon as \
+                   rows are deleted but pays for page bookkeeping on every commit, which is \
+                   wasted on the usual append-only run; NONE is fastest for those; \
+                   INCREMENTAL frees space only when a table is truncated")
+            .possible_values(&["NONE", "FULL", "INCREMENTAL"])
+            .case_insensitive(true)
+            .default_value("FULL")
+            .takes_value(true),
+        Arg::with_name("genesis_seed")
+            .long("genesis_seed")
+            .value_name("HEX")
+            .help("Seed of the first chunk, defaults to the hash of the table name followed by \
+                   an all-zero chunk")
+            .takes_value(true),
+        Arg::with_name("no_salt")
+            .long("no_salt")
+            .help("Start new tables from the hash of an all-zero chunk alone, like tables \
+                   generated before the table name was mixed into the genesis seed")
+            .conflicts_with("genesis_seed")
+            .takes_value(false),
+        Arg::with_name("partition_index")
+            .long("partition_index")
+            .value_name("K")
+            .help("Start a new table from the hash of K as 8 little-endian bytes, the table name and an \
+                   all-zero chunk, so that partitions handed out by index never share a chain. \
+                   The index is recorded and must match when the table is resumed")
+            .conflicts_with_all(&["genesis_seed", "no_salt", "partitions"])
+            .takes_value(true),
+        Arg::with_name("id_stride")
+            .long("id_stride")
+            .value_name("S")
+            .help("Store chunk i of the chain at id --id_offset + i * S instead of i, for layouts that interleave \
+                   the ids of several partitions. The stride and offset are recorded and must match when the \
+                   table is resumed")
+            .conflicts_with_all(&["partitions", "start_index"])
+            .takes_value(true),
+        Arg::with_name("id_offset")
+            .long("id_offset")
+            .value_name("O")
+            .help("Id of the first chunk of the table, see --id_stride")
+            .conflicts_with_all(&["partitions", "start_index"])
+            .takes_value(true),
+        Arg::with_name("force")
+            .long("force")
+            .help("Generate even if the estimated size exceeds the free disk space, take --start_index/--start_seed \
+                   without checking them against the table, and resume a table generated with other settings")
+            .takes_value(false),
+        Arg::with_name("start_index")
+            .long("start_index")
+            .value_name("ID")
+            .help("Id of the next row to write, used with --start_seed instead of reading the resume point from the table")
+            .requires("start_seed")
+            .conflicts_with("partitions")
+            .takes_value(true),
+        Arg::with_name("start_seed")
+            .long("start_seed")
+            .value_name("HEX")
+            .help("Seed that generates row --start_index")
+            .requires("start_index")
+            .takes_value(true),
+        Arg::with_name("checkpoint_interval")
+            .long("checkpoint_interval")
+            .value_name("K")
+            .help("Record the chain seed every K rows so `challenge` walks at most K chunks (0 disables)")
+            .default_value("10000")
+            .takes_value(true),
+        Arg::with_name("rng")
+            .long("rng")
+            .value_name("RNG")
+            .help("PRNG for the chunk characters. chacha20 is a fixed algorithm; std follows \
+                   whatever the rand crate ships as StdRng")
+            .possible_values(&RngAlgo::NAMES)
+            .default_value("std")
+            .takes_value(true),
+        Arg::with_name("charset")
+            .long("charset")
+            .value_name("CHARSET")
+            .help("Characters of the chunks: alphanumeric, printable ascii, or bytes over the full 0-255 range")
+            .possible_values(&Charset::NAMES)
+            .default_value("alphanumeric")
+            .takes_value(true),
+        incompressible_arg(),
+        backend_arg(),
+        Arg::with_name("compress")
+            .long("compress")
+            .value_name("CODEC")
+            .help("Compress each chunk before storing it. Saves disk but costs CPU on every \
+                   challenge response")
+            .possible_values(&Codec::NAMES)
+            .default_value("none")
+            .takes_value(true),
+        Arg::with_name("cache_mb")
+            .long("cache_mb")
+            .value_name("MB")
+            .help("SQLite page cache size per connection in MB (SQLite default if omitted)")
+            .takes_value(true),
+        Arg::with_name("wal_autocheckpoint")
+            .long("wal_autocheckpoint")
+            .value_name("PAGES")
+            .help("With --journal WAL, checkpoint the log into the database once it holds PAGES pages of \
+                   32 KiB; defaults to 1000 or the pages of one batch, whichever is more")
+            .takes_value(true),
+        Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("TOML file of defaults for the settings every table of a miner shares, e.g. \
+                   size = 4096 or hash_algo = \"blake3\"; flags on the command line override it")
+            .takes_value(true),
+    ]
+}
+
+/// Command line of the binary, which `GenerateConfig` parses as well.
+pub(super) fn app() -> App<'static, 'static> {
+    let app = App::new("SQLite Chunk Generator")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .arg(Arg::with_name("log_level")
+            .long("log_level")
+            .value_name("LEVEL")
+            .help("Log verbosity, overrides RUST_LOG. info logs the milestones of a run, debug \
+                   also every committed batch")
+            .possible_values(&["error", "warn", "info", "debug", "trace"])
+            .case_insensitive(true)
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("path")
+            .long("path")
+            .value_name("DB_PATH")
+            .help("Path to the SQLite database, or :memory: to generate into memory without touching the disk")
+            .required_unless("db_root_path")
+            .conflicts_with("db_root_path")
+            .takes_value(true))
+        .arg(Arg::with_name("db_root_path")
+            .long("db_root_path")
+            .value_name("DIR")
+            .help("Directory holding one database file per partition, used instead of --path")
+            .takes_value(true))
+        .arg(Arg::with_name("partitions")
+            .long("partitions")
+            .value_name("N")
+            .help("Split the --n chunks across N tables DB{seed}_0..DB{seed}_{N-1}, one file each. \
+                   Table DB{seed}_K is generated as if by --partition_index K")
+            .requires("db_root_path")
+            .conflicts_with_all(&["genesis_seed", "no_salt"])
+            .takes_value(true))
+        .arg(Arg::with_name("n")
+            .long("n")
+            .value_name("NUM_CHUNKS")
+            .help("Number of chunks to generate")
+            .required(true)
+            .takes_value(true))
+        .args(&generation_args())
+        .subcommand(SubCommand::with_name("generate-all")
+            .about("Generates all partitions under --db_root_path in one process, several at a time")
+            .arg(Arg::with_name("db_root_path")
+                .long("db_root_path")
+                .value_name("DIR")
+                .help("Directory holding one database file per partition")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("partitions")
+                .long("partitions")
+                .value_name("N")
+                .help("Number of tables DB{seed}_0..DB{seed}_{N-1}, one file each. \
+                       Table DB{seed}_K is generated as if by --partition_index K")
+                .required(true)
+                .conflicts_with_all(&["genesis_seed", "no_salt"])
+                .takes_value(true))
+            .arg(Arg::with_name("size_gb")
+                .long("size_gb")
+                .value_name("GIB")
+                .help("Total chunk data across all partitions in GiB, rounded up to whole chunks")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("jobs")
+                .long("jobs")
+                .value_name("N")
+                .help("Partitions generated at the same time, defaults to the number of CPUs")
+                .takes_value(true))
+            .args(&generation_args()))
+        .subcommand(SubCommand::with_name("bench")
+            .about("Generates chunks in memory and reports RNG and hashing throughput")
+            .arg(Arg::with_name("n")
+                .long("n")
+                .value_name("NUM_CHUNKS")
+                .help("Number of chunks to generate")
+                .default_value("10000")
+                .takes_value(true))
+            .arg(Arg::with_name("size")
+                .long("size")
+                .visible_alias("chunk_size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("hash_algo")
+                .long("hash_algo")
+                .value_name("HASH_ALGO")
+                .help("Hash used for chunk hashes and the seed chain")
+                .possible_values(&HashAlgo::NAMES)
+                .default_value("sha256")
+                .takes_value(true))
+            .arg(Arg::with_name("rng")
+                .long("rng")
+                .value_name("RNG")
+                .help("PRNG for the chunk characters")
+                .possible_values(&RngAlgo::NAMES)
+                .default_value("std")
+                .takes_value(true))
+            .arg(Arg::with_name("charset")
+                .long("charset")
+                .value_name("CHARSET")
+                .help("Characters of the chunks")
+                .possible_values(&Charset::NAMES)
+                .default_value("alphanumeric")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("final-seed")
+            .about("Prints the seed a table of N chunks ends on, hashing the chain without storing anything")
+            .args(&chain_args()))
+        .subcommand(SubCommand::with_name("testvectors")
+            .about("Prints the genesis seed and the first N chunks of a table as JSON, for checking \
+                    other implementations of the chain against this one")
+            .args(&chain_args()))
+        .subcommand(SubCommand::with_name("truncate")
+            .about("Deletes every row from an id on and returns the space to the filesystem")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("N")
+                .help("Number of rows to keep; rows with id >= N are deleted")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("auto_vacuum")
+                .long("auto_vacuum")
+                .value_name("MODE")
+                .help("auto_vacuum mode to switch a database created with NONE to, during the VACUUM that \
+                       reclaims its space; without it the database keeps its mode")
+                .possible_values(&["NONE", "FULL", "INCREMENTAL"])
+                .case_insensitive(true)
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("info")
+            .about("Prints the row count, id range, chunk size and file size of a table")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the report as JSON.")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("list")
+            .about("Lists the DB{seed} tables in a database with their row counts and chunk sizes")
+            .arg(db_path_arg())
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print one JSON object per table.")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("doctor")
+            .about("Checks a table for common problems: its schema and metadata, its last rows, the \
+                    pragmas of the database and the free disk space")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("n")
+                .long("n")
+                .value_name("N")
+                .help("Number of rows the table is meant to reach; checks that the disk has room for the rest")
+                .takes_value(true))
+            .arg(Arg::with_name("rows")
+                .long("rows")
+                .value_name("K")
+                .help("Number of trailing rows to regenerate and compare")
+                .default_value("100")
+                .takes_value(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print one JSON object per check.")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Regenerates every chunk and checks it against the stored rows")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(backend_arg())
+            .arg(Arg::with_name("size")
+                .long("size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes, defaults to the size recorded for the table")
+                .takes_value(true))
+            .arg(Arg::with_name("from")
+                .long("from")
+                .value_name("ID")
+                .help("First row to check, replaying the chain from the nearest seed checkpoint")
+                .default_value("0")
+                .takes_value(true))
+            .arg(Arg::with_name("threads")
+                .long("threads")
+                .value_name("THREADS")
+                .help("Number of workers, each checking the rows between two seed checkpoints at a time")
+                .default_value("1")
+                .takes_value(true))
+            .arg(Arg::with_name("verify_only_hashes")
+                .long("verify_only_hashes")
+                .help("Walk the chain without building the chunks and compare only the hash and rng_state \
+                       columns, leaving the data unchecked; always done for hash-only tables")
+                .conflicts_with("sample_verify")
+                .takes_value(false))
+            .arg(Arg::with_name("sample_verify")
+                .long("sample_verify")
+                .value_name("P")
+                .help("Check a random P% of the rows from --from on, each regenerated from the nearest \
+                       seed checkpoint, and report every failure")
+                .conflicts_with("threads")
+                .takes_value(true))
+            .arg(Arg::with_name("sample_seed")
+                .long("sample_seed")
+                .value_name("SEED")
+                .help("Seed picking the rows of --sample_verify, random if not given; the report names it \
+                       so a sample can be checked again")
+                .requires("sample_verify")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("repair")
+            .about("Regenerates the rows from the first corrupt one on, checking from the last seed checkpoint")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("size")
+                .long("size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes, defaults to the size recorded for the table")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("rehash")
+            .about("Recomputes the hash column from the data column, for a table whose hashes are corrupt but \
+                    whose data is trusted")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the counts as JSON.")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("regenerate")
+            .about("Regenerates the chunks of an id range from the nearest seed checkpoint and prints their hashes")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("size")
+                .long("size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes, defaults to the size recorded for the table")
+                .takes_value(true))
+            .arg(Arg::with_name("from")
+                .long("from")
+                .value_name("ID")
+                .help("First id of the range")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("ID")
+                .help("Id after the last one of the range")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("write")
+                .long("write")
+                .help("Rewrite the stored rows of the range instead of printing the hashes; \
+                       miner-stored rows and ids past the end of the table are left alone")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("fetch")
+            .about("Prints the stored data and hash of one row as JSON")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(backend_arg())
+            .arg(Arg::with_name("id")
+                .long("id")
+                .value_name("ID")
+                .help("Id of the row to print")
+                .required(true)
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Compares a table in two databases row by row and reports the first difference")
+            .arg(Arg::with_name("db_a")
+                .long("db_a")
+                .value_name("DB_PATH")
+                .help("Path to the first SQLite database, e.g. the miner's")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("db_b")
+                .long("db_b")
+                .value_name("DB_PATH")
+                .help("Path to the second SQLite database, e.g. a regenerated reference")
+                .required(true)
+                .takes_value(true))
+            .arg(table_seed_arg()))
+        .subcommand(SubCommand::with_name("merge")
+            .about("Merges the tables of several databases into one table as long as all of them; the \
+                    chunks are regenerated from the merged table's chain, as every seed depends on the \
+                    position of its row, and only miner-stored rows are copied")
+            .arg(Arg::with_name("inputs")
+                .long("inputs")
+                .value_name("DB_PATHS")
+                .help("Comma-separated databases holding one table each, merged in this order")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("output")
+                .long("output")
+                .value_name("DB_PATH")
+                .help("Database the merged table is written to")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("seed")
+                .long("table")
+                .value_name("seed")
+                .help("Seed of the merged table, which is named DB{seed}")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the summary of the generation as JSON")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("import")
+            .about("Rebuilds a table from a file written by `export --format raw`, checking every chunk \
+                    against the chain described by the file's footer before writing its batch")
+            .arg(Arg::with_name("from")
+                .long("from")
+                .value_name("FILE")
+                .help("Raw export to import")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("db")
+                .long("db")
+                .value_name("DB_PATH")
+                .help("Database the table is written to")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("seed")
+                .long("table")
+                .value_name("seed")
+                .help("Seed of the imported table, instead of the one recorded in the footer")
+                .takes_value(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the summary of the generation as JSON")
+                .takes_value(false)))
+        .subcommand(SubCommand::with_name("export")
+            .about("Streams every stored chunk to stdout in id order")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(backend_arg())
+            .arg(Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("raw writes the chunk bytes back to back followed by a footer of the table settings \
+                       that `import` reads, hex one hex line per chunk, ndjson one {\"id\", \"hash\", \"data\"} \
+                       object per chunk")
+                .possible_values(&ExportFormat::NAMES)
+                .default_value("raw")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("challenge")
+            .about("Regenerates one chunk from the nearest seed checkpoint and checks the stored row")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(backend_arg())
+            .arg(Arg::with_name("id")
+                .long("id")
+                .value_name("ID")
+                .help("Id of the chunk to check")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("size")
+                .long("size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes, defaults to the size recorded for the table")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("prove-progress")
+            .about("Prints hash(seed || nonce) for the seed the chain reaches after chunk --id, replayed \
+                    from the nearest seed checkpoint, to answer a challenge that the table was generated that far")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("id")
+                .long("id")
+                .value_name("ID")
+                .help("Id of the last chunk the table must hold")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("nonce")
+                .long("nonce")
+                .value_name("HEX")
+                .help("Nonce picked by the challenger, in hex")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("size")
+                .long("size")
+                .value_name("CHUNK_SIZE")
+                .help("Size of each chunk in bytes, defaults to the size recorded for the table")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("merkle-root")
+            .about("Prints the Merkle root over the stored chunk hashes")
+            .arg(db_path_arg())
+            .arg(table_seed_arg()))
+        .subcommand(SubCommand::with_name("verify-manifest")
+            .about("Recomputes the Merkle root over the stored chunk hashes and compares it with the one \
+                    recorded after generation, without regenerating any chunk")
+            .arg(db_path_arg())
+            .arg(table_seed_arg()))
+        .subcommand(SubCommand::with_name("prove")
+            .about("Prints the Merkle proof for one chunk: its index, the number of chunks and the \
+                    authentication path")
+            .arg(db_path_arg())
+            .arg(table_seed_arg())
+            .arg(Arg::with_name("id")
+                .long("id")
+                .value_name("ID")
+                .help("Id of the chunk to prove")
+                .required(true)
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("verify-proof")
+            .about("Checks a Merkle authentication path against a known root")
+            .arg(Arg::with_name("leaf")
+                .long("leaf")
+                .value_name("HASH")
+                .help("Hex hash of the proven chunk")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("proof")
+                .long("proof")
+                .value_name("PROOF")
+                .help("Proof printed by `prove`")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("root")
+                .long("root")
+                .value_name("ROOT")
+                .help("Hex Merkle root")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("hash_algo")
+                .long("hash_algo")
+                .value_name("HASH_ALGO")
+                .help("Hash the tree was built with")
+                .possible_values(&HashAlgo::NAMES)
+                .default_value("sha256")
+                .takes_value(true)))
+;
+    #[cfg(feature = "sqlcipher")]
+    let app = app.arg(key_arg());
+    app
+}
+
+/// Runs the command line of the process and exits with its status.
+pub fn run_cli() {
+    let app = app();
+    let argv = config::with_defaults(std::env::args_os().collect()).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    let matches = app.get_matches_from(argv);
+
+    // --log_level takes precedence over RUST_LOG; without either, only
+    // warnings and errors are shown, or without progress bars also the info
+    // lines that replace them. Logs always go to stderr so --json output on
+    // stdout stays parseable.
+    let quiet = progress_hidden(matches.subcommand().1.unwrap_or(&matches));
+    let log_filter = matches.value_of("log_level").map(str::to_string)
+        .or_else(|| std::env::var("RUST_LOG").ok())
+        .unwrap_or_else(|| if quiet { "info" } else { "warn" }.to_string());
+    env_logger::Builder::new()
+        .parse_filters(&log_filter)
+        .target(env_logger::Target::Stderr)
+        .init();
+
+    let result = match matches.subcommand() {
+        ("verify", Some(sub_matches)) => VerifyArgs::from_matches(sub_matches).and_then(verify),
+        ("repair", Some(sub_matches)) => RepairArgs::from_matches(sub_matches).and_then(repair),
+        ("rehash", Some(sub_matches)) => InfoArgs::from_matches(sub_matches).and_then(rehash),
+        ("regenerate", Some(sub_matches)) => RegenerateArgs::from_matches(sub_matches).and_then(regenerate),
+        ("fetch", Some(sub_matches)) => RowArgs::from_matches(sub_matches).and_then(fetch),
+        ("challenge", Some(sub_matches)) => RowArgs::from_matches(sub_matches).and_then(challenge),
+        ("prove-progress", Some(sub_matches)) => ProgressProofArgs::from_matches(sub_matches).and_then(prove_progress),
+        ("diff", Some(sub_matches)) => DiffArgs::from_matches(sub_matches).and_then(diff),
+        ("merge", Some(sub_matches)) => MergeArgs::from_matches(sub_matches).and_then(merge),
+        ("export", Some(sub_matches)) => ExportArgs::from_matches(sub_matches).and_then(export),
+        ("import", Some(sub_matches)) => ImportArgs::from_matches(sub_matches).and_then(import),
+        ("merkle-root", Some(sub_matches)) => MerkleArgs::from_matches(sub_matches).and_then(merkle_root),
+        ("prove", Some(sub_matches)) => MerkleArgs::from_matches(sub_matches).and_then(prove),
+        ("verify-manifest", Some(sub_matches)) => MerkleArgs::from_matches(sub_matches).and_then(verify_manifest),
+        ("bench", Some(sub_matches)) => BenchArgs::from_matches(sub_matches).and_then(bench),
+        ("final-seed", Some(sub_matches)) => ChainArgs::from_matches(sub_matches).and_then(final_seed),
+        ("testvectors", Some(sub_matches)) => ChainArgs::from_matches(sub_matches).and_then(testvectors),
+        ("truncate", Some(sub_matches)) => TruncateArgs::from_matches(sub_matches).and_then(truncate),
+        ("info", Some(sub_matches)) => InfoArgs::from_matches(sub_matches).and_then(info),
+        ("list", Some(sub_matches)) => ListArgs::from_matches(sub_matches).and_then(list),
+        ("doctor", Some(sub_matches)) => DoctorArgs::from_matches(sub_matches).and_then(doctor),
+        ("verify-proof", Some(sub_matches)) => VerifyProofArgs::from_matches(sub_matches).and_then(verify_proof),
+        ("generate-all", Some(sub_matches)) => exit_if_complete(Args::for_generate_all(sub_matches).and_then(run)),
+        _ => exit_if_complete(Args::from_matches(&matches).and_then(run)),
+    };
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        if matches.subcommand().1.unwrap_or(&matches).is_present("json") {
+            println!("{}", err.to_json());
+        }
+        process::exit(err.exit_code());
+    }
+}
+
+/// Exits with `EXIT_ALREADY_COMPLETE` if a generation run had nothing to do.
+pub(super) fn exit_if_complete(result: Result<bool, GenError>) -> Result<(), GenError> {
+    match result {
+        Ok(true) => process::exit(EXIT_ALREADY_COMPLETE),
+        other => other.map(drop),
+    }
+}
+
+/// Generates the requested tables and returns whether all of them were
+/// already complete, so there was nothing to do.
+pub(super) fn run(args: Args) -> Result<bool, GenError> {
+    let started = std::time::Instant::now();
+    if args.confirm_overwrite {
+        confirm_overwrite(&args)?;
+    }
+
+    let cancel = args.cancel.clone();
+    ctrlc::set_handler(move || cancel.cancel())
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    if let Some(budget) = args.max_runtime {
+        let cancel = args.cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(budget);
+            OUT_OF_TIME.store(true, Ordering::SeqCst);
+            cancel.cancel();
+        });
+    }
+
+    let (tables, failures) = generate_run(&args, args.json || args.quiet, None)?;
+
+    if args.json {
+        for table in &tables {
+            let mut summary = serde_json::json!({
+                "table": table.table,
+                "n_chunks": table.n_chunks,
+                "chunk_size": args.chunk_size,
+                "final_seed": hex::encode(&table.final_seed),
+                "rows_written": table.rows_written,
+                "rows_deleted": table.rows_deleted,
+                "rows_filled": table.rows_filled,
+                "status": table.status(),
+                "data_bytes": table.data_bytes,
+                "stored_bytes": table.stored_bytes,
+                "manifest_root": table.manifest_root.as_ref().map(hex::encode),
+                "interrupted": args.cancel.is_cancelled(),
+                "out_of_time": OUT_OF_TIME.load(Ordering::SeqCst),
+                "elapsed_ms": started.elapsed().as_millis() as u64,
+            });
+            if let Some((verified, elapsed)) = &table.verified {
+                summary["verified_rows"] = verified.rows.into();
+                summary["verify_ms"] = (elapsed.as_millis() as u64).into();
+                summary["verify_mb_per_s"] = verify_throughput(verified, *elapsed, args.chunk_size).into();
+            }
+            if let Some(timings) = &table.timings {
+                summary["batch_timing"] = timings.to_json();
+            }
+            println!("{}", summary);
+        }
+    } else if args.cancel.is_cancelled() {
+        let reason = match args.max_runtime {
+            Some(budget) if OUT_OF_TIME.load(Ordering::SeqCst) => format!("Stopped after {}s", budget.as_secs()),
+            _ => "Interrupted".to_string(),
+        };
+        match tables.last().and_then(|table| table.next_id.checked_sub(1)) {
+            Some(last) => println!("{}, last committed id: {}", reason, args.id_layout.id(last)),
+            None => println!("{} before any rows were committed", reason),
+        }
+    }
+    if !args.json {
+        for table in &tables {
+            if table.rows_filled > 0 {
+                println!("Filled in the data of {} hash-only rows of {}", table.rows_filled, table.table);
+            }
+            if let Some((verified, elapsed)) = &table.verified {
+                println!(
+                    "Verified {} rows of {} in {:.2}s ({:.1} MB/s)",
+                    verified.rows, table.table, elapsed.as_secs_f64(),
+                    verify_throughput(verified, *elapsed, args.chunk_size)
+                );
+            }
+            if let Some(timings) = &table.timings {
+                timings.print(&table.table);
+            }
+        }
+    }
+    for failure in &failures {
+        if args.json {
+            let mut summary = failure.error.to_json();
+            summary["table"] = failure.table.clone().into();
+            summary["status"] = "failed".into();
+            println!("{}", summary);
+        } else {
+            println!("Failed {}: {}", failure.table, failure.error);
+        }
+    }
+    if !failures.is_empty() {
+        return Err(GenError::TablesFailed {
+            failed: failures.into_iter().map(|failure| failure.table).collect(),
+            of: args.partitions,
+        });
+    }
+    let already_complete = !tables.is_empty() && tables.iter().all(|table| table.status() == "complete");
+    if already_complete && !args.json {
+        println!("Nothing to do, already at {} rows", args.n_chunks);
+    }
+    Ok(already_complete)
+}
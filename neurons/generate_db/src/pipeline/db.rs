@@ -0,0 +1,588 @@
+//! Opening databases and reading and writing their metadata, checkpoints
+//! and rows.
+
+use super::*;
+
+/// `--path` of a database kept in memory for the length of the process, so
+/// tests can run the whole pipeline without touching the disk.
+pub(super) const MEMORY_DB: &str = ":memory:";
+
+/// Every connection to `MEMORY_DB` opens this shared-cache database instead,
+/// so that, like a file, it is seen by all of them. It lives as long as one
+/// connection to it is open, which `MEMORY_DB_KEEPER` holds until exit.
+pub(super) const MEMORY_DB_URI: &str = "file:storer_db_project?mode=memory&cache=shared";
+
+pub(super) static MEMORY_DB_KEEPER: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+pub(super) fn open_memory_db() -> Result<Connection, rusqlite::Error> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI;
+    if MEMORY_DB_KEEPER.get().is_none() {
+        let _ = MEMORY_DB_KEEPER.set(Mutex::new(Connection::open_with_flags(MEMORY_DB_URI, flags)?));
+    }
+    Connection::open_with_flags(MEMORY_DB_URI, flags)
+}
+
+/// Keys a new connection to a database file with the `--key` of the command,
+/// if one was given. SQLCipher accepts any key and only fails on the first
+/// read, so the schema is read right away to report a wrong key before
+/// anything else runs.
+#[cfg(feature = "sqlcipher")]
+pub(super) fn apply_key(conn: &Connection, key: Option<&str>) -> Result<(), rusqlite::Error> {
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", &key)?;
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", params![], |row| row.get::<_, i64>(0))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+pub(super) fn apply_key(_conn: &Connection, _key: Option<&str>) -> Result<(), rusqlite::Error> {
+    Ok(())
+}
+
+pub(super) fn open_db(path: &str, key: Option<&str>) -> Result<Connection, rusqlite::Error> {
+    let conn = if path == MEMORY_DB {
+        open_memory_db()?
+    } else {
+        let conn = Connection::open(path)?;
+        apply_key(&conn, key)?;
+        conn
+    };
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+pub(super) fn open_db_read_only(path: &str, key: Option<&str>) -> Result<Connection, rusqlite::Error> {
+    let conn = if path == MEMORY_DB {
+        open_memory_db()?
+    } else {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        apply_key(&conn, key)?;
+        conn
+    };
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Runs `op` again while the database is locked, backing off for up to
+/// `BUSY_TIMEOUT` in total. The busy timeout covers most waits, but SQLite
+/// returns SQLITE_BUSY at once where waiting could deadlock, e.g. when
+/// switching the journal mode while another connection reads.
+pub(super) fn retry_busy<T>(mut op: impl FnMut() -> Result<T, GenError>) -> Result<T, GenError> {
+    let started = std::time::Instant::now();
+    let mut backoff = std::time::Duration::from_millis(50);
+    loop {
+        match op() {
+            Err(err) if err.is_busy() && started.elapsed() + backoff < BUSY_TIMEOUT => {
+                log::warn!("Database is locked by another process, retrying in {:?}", backoff);
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(std::time::Duration::from_secs(2));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Reads a per-table metadata value, or `None` if it was never recorded.
+pub(super) fn read_metadata(conn: &Connection, table: &str, key: &str) -> Result<Option<String>, rusqlite::Error> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'metadata'",
+        params![],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Ok(None);
+    }
+    let mut stmt = conn.prepare("SELECT value FROM metadata WHERE table_name = ? AND key = ?")?;
+    let mut rows = stmt.query(params![table, key])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn create_metadata_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS metadata (
+            table_name TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (table_name, key)
+        )",
+        params![],
+    )?;
+    Ok(())
+}
+
+/// Records a per-table metadata value, keeping any value already stored.
+pub(super) fn write_metadata(conn: &Connection, table: &str, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+    create_metadata_table(conn)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO metadata (table_name, key, value) VALUES (?, ?, ?)",
+        params![table, key, value],
+    )?;
+    Ok(())
+}
+
+pub(super) fn remove_metadata(conn: &Connection, table: &str, key: &str) -> Result<(), rusqlite::Error> {
+    create_metadata_table(conn)?;
+    conn.execute("DELETE FROM metadata WHERE table_name = ? AND key = ?", params![table, key])?;
+    Ok(())
+}
+
+pub(super) fn clear_metadata(conn: &Connection, table: &str) -> Result<(), rusqlite::Error> {
+    create_metadata_table(conn)?;
+    conn.execute("DELETE FROM metadata WHERE table_name = ?", params![table])?;
+    Ok(())
+}
+
+pub(super) fn create_checkpoint_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS seed_checkpoints (
+            table_name TEXT NOT NULL,
+            id INTEGER NOT NULL,
+            seed BLOB NOT NULL,
+            PRIMARY KEY (table_name, id)
+        )",
+        params![],
+    )?;
+    Ok(())
+}
+
+/// The checkpoint closest to `id` from below: a chain step, which is the row
+/// id unless the table has an `IdLayout`, and the seed that generated it, or
+/// `None` if the table has no checkpoint at or before `id`.
+pub(super) fn nearest_checkpoint(conn: &Connection, table: &str, id: usize) -> Result<Option<(usize, Vec<u8>)>, rusqlite::Error> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'seed_checkpoints'",
+        params![],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Ok(None);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, seed FROM seed_checkpoints WHERE table_name = ? AND id <= ? ORDER BY id DESC LIMIT 1",
+    )?;
+    let mut rows = stmt.query(params![table, id as i64])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get::<_, i64>(0)? as usize, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+/// Chain steps in `steps` the table has a seed checkpoint at, in order.
+pub(super) fn checkpoint_steps(conn: &Connection, table: &str, steps: std::ops::Range<usize>) -> Result<Vec<usize>, rusqlite::Error> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'seed_checkpoints'",
+        params![],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id FROM seed_checkpoints WHERE table_name = ? AND id >= ? AND id < ? ORDER BY id",
+    )?;
+    let steps = stmt
+        .query_map(params![table, steps.start as i64, steps.end as i64], |row| row.get::<_, i64>(0))?
+        .map(|step| step.map(|step| step as usize))
+        .collect();
+    steps
+}
+
+pub(super) fn clear_checkpoints(conn: &Connection, table: &str) -> Result<(), rusqlite::Error> {
+    create_checkpoint_table(conn)?;
+    conn.execute("DELETE FROM seed_checkpoints WHERE table_name = ?", params![table])?;
+    Ok(())
+}
+
+/// Genesis seed recorded for the table. Tables without one predate salted
+/// genesis seeds and start from `ChunkGenerator::genesis_seed`.
+pub(super) fn stored_genesis_seed(conn: &Connection, table: &str, hash_algo: HashAlgo) -> Result<Option<Vec<u8>>, GenError> {
+    match read_metadata(conn, table, "genesis_seed")? {
+        Some(value) => match hex::decode(&value) {
+            Ok(seed) if seed.len() == hash_algo.output_len() => Ok(Some(seed)),
+            _ => Err(GenError::InvalidMetadata { key: "genesis_seed", value }),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Hash algorithm recorded for a table, defaulting to SHA-256 for tables
+/// generated before the algorithm was stored.
+pub(super) fn stored_hash_algo(conn: &Connection, table: &str) -> Result<HashAlgo, GenError> {
+    match read_metadata(conn, table, "hash_algo")? {
+        Some(name) => HashAlgo::from_name(&name)
+            .ok_or(GenError::InvalidMetadata { key: "hash_algo", value: name }),
+        None => Ok(HashAlgo::Sha256),
+    }
+}
+
+/// Bytes of each chunk hash kept in the `hash` column, the full digest for
+/// tables generated before hashes could be truncated.
+pub(super) fn stored_hash_bytes(conn: &Connection, table: &str, hash_algo: HashAlgo) -> Result<usize, GenError> {
+    match read_metadata(conn, table, "hash_bytes")? {
+        Some(value) => match value.parse() {
+            Ok(hash_bytes) if (1..=hash_algo.output_len()).contains(&hash_bytes) => Ok(hash_bytes),
+            _ => Err(GenError::InvalidMetadata { key: "hash_bytes", value }),
+        },
+        None => Ok(hash_algo.output_len()),
+    }
+}
+
+pub(super) fn stored_hash_encoding(conn: &Connection, table: &str) -> Result<HashEncoding, GenError> {
+    match read_metadata(conn, table, "hash_encoding")? {
+        Some(name) => HashEncoding::from_name(&name)
+            .ok_or(GenError::InvalidMetadata { key: "hash_encoding", value: name }),
+        None => Ok(HashEncoding::Hex),
+    }
+}
+
+pub(super) fn stored_codec(conn: &Connection, table: &str) -> Result<Codec, GenError> {
+    match read_metadata(conn, table, "compression")? {
+        Some(name) => Codec::from_name(&name)
+            .ok_or(GenError::InvalidMetadata { key: "compression", value: name }),
+        None => Ok(Codec::None),
+    }
+}
+
+pub(super) fn stored_rng(conn: &Connection, table: &str) -> Result<RngAlgo, GenError> {
+    match read_metadata(conn, table, "rng")? {
+        Some(name) => RngAlgo::from_name(&name)
+            .ok_or(GenError::InvalidMetadata { key: "rng", value: name }),
+        None => Ok(RngAlgo::Std),
+    }
+}
+
+pub(super) fn stored_charset(conn: &Connection, table: &str) -> Result<Charset, GenError> {
+    match read_metadata(conn, table, "charset")? {
+        Some(name) => Charset::from_name(&name)
+            .ok_or(GenError::InvalidMetadata { key: "charset", value: name }),
+        None => Ok(Charset::Alphanumeric),
+    }
+}
+
+/// Chunk size recorded for the table. Tables generated before it was stored
+/// fall back to the size of their first generated row, which is unknown for
+/// empty and hash-only tables.
+pub(super) fn stored_chunk_size(conn: &Connection, table: &str) -> Result<Option<usize>, GenError> {
+    if let Some(value) = read_metadata(conn, table, "chunk_size")? {
+        return match value.parse() {
+            Ok(chunk_size) => Ok(Some(chunk_size)),
+            Err(_) => Err(GenError::InvalidMetadata { key: "chunk_size", value }),
+        };
+    }
+    match first_generated_row(conn, table, stored_codec(conn, table)?)? {
+        Some(sample) if !sample.data.is_empty() => {
+            Ok(Some(sample.data.len().saturating_sub(2 * stored_hash_algo(conn, table)?.output_len())))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// First row of `table` that holds a generated chunk. Miner-stored rows hold
+/// arbitrary data, so they say nothing about the table's settings.
+pub(super) fn first_generated_row(conn: &Connection, table: &str, codec: Codec) -> Result<Option<StoredRow>, GenError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM DB{} WHERE flag != 'T' ORDER BY id LIMIT 1",
+        StoredRow::COLUMNS, table
+    ))?;
+    let mut rows = stmt.query(params![])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(StoredRow::from_row(row)?.decoded(codec)?)),
+        None => Ok(None),
+    }
+}
+
+/// Chunk size to regenerate `table` with: the one recorded for it, which
+/// `--size` must agree with if it is given as well. Hash-only tables from
+/// before the size was recorded need `--size`.
+pub(super) fn table_chunk_size(conn: &Connection, table: &str, requested: Option<usize>) -> Result<usize, GenError> {
+    match (requested, stored_chunk_size(conn, table)?) {
+        (Some(requested), Some(stored)) if requested != stored => Err(GenError::ParamMismatch {
+            field: "chunk_size",
+            stored: stored.to_string(),
+            requested: requested.to_string(),
+        }),
+        (_, Some(stored)) => Ok(stored),
+        (Some(requested), None) => Ok(requested),
+        (None, None) => Err(GenError::InvalidArg {
+            name: "size",
+            value: "none".to_string(),
+            reason: "the table records no chunk size, so it has to be given".to_string(),
+        }),
+    }
+}
+
+pub(super) fn table_has_rows(conn: &Connection, table: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(&format!("SELECT EXISTS (SELECT 1 FROM DB{})", table), params![], |row| row.get(0))
+}
+
+/// Encoding of the `data` column, recorded in metadata as `compression`.
+///
+/// Compressed chunks take a fraction of the space but have to be decompressed
+/// for every challenge response, so `none` stays the default.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Codec {
+    None,
+    Zstd,
+}
+
+impl Codec {
+    pub(super) const NAMES: [&'static str; 2] = ["none", "zstd"];
+
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Codec::None),
+            "zstd" => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Codec::None => "none",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// The stored form of `data`, borrowed as is when uncompressed.
+    pub(super) fn encode(self, data: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+        match self {
+            Codec::None => Ok(Cow::Borrowed(data)),
+            Codec::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL).map(Cow::Owned),
+        }
+    }
+
+    pub(super) fn decode(self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::None => Ok(data),
+            Codec::Zstd => zstd::decode_all(data.as_slice()),
+        }
+    }
+}
+
+/// Encoding of the `hash` column, recorded in metadata as `hash_encoding`.
+///
+/// `blob` stores the raw hash in half the bytes of its hex text. Readers go by
+/// the type of each value rather than the metadata, so a table switched over
+/// with `--force` keeps verifying its older hex rows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum HashEncoding {
+    Hex,
+    Blob,
+}
+
+impl HashEncoding {
+    pub(super) const NAMES: [&'static str; 2] = ["hex", "blob"];
+
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hex" => Some(HashEncoding::Hex),
+            "blob" => Some(HashEncoding::Blob),
+            _ => None,
+        }
+    }
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            HashEncoding::Hex => "hex",
+            HashEncoding::Blob => "blob",
+        }
+    }
+
+    pub(super) fn encode(self, hash: &[u8]) -> Value {
+        match self {
+            HashEncoding::Hex => Value::Text(hex::encode(hash)),
+            HashEncoding::Blob => Value::Blob(hash.to_vec()),
+        }
+    }
+
+    /// Bytes a hash of `hash_bytes` bytes takes in the column.
+    pub(super) fn stored_len(self, hash_bytes: usize) -> usize {
+        match self {
+            HashEncoding::Hex => 2 * hash_bytes,
+            HashEncoding::Blob => hash_bytes,
+        }
+    }
+}
+
+/// Reads a column as raw bytes. Generated chunks are BLOBs, but rows written
+/// by the miner (and the empty data of older hash-only tables) are TEXT.
+pub(super) fn column_bytes(row: &Row, idx: usize) -> Result<Vec<u8>, rusqlite::Error> {
+    match row.get_raw_checked(idx)? {
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => Ok(bytes.to_vec()),
+        ValueRef::Null => Ok(Vec::new()),
+        other => Err(rusqlite::Error::InvalidColumnType(idx, String::new(), other.data_type())),
+    }
+}
+
+/// Reads the `hash` column as hex, whichever encoding the row was written with.
+pub(super) fn column_hash_hex(row: &Row, idx: usize) -> Result<String, rusqlite::Error> {
+    match row.get_raw_checked(idx)? {
+        ValueRef::Blob(bytes) => Ok(hex::encode(bytes)),
+        _ => row.get(idx),
+    }
+}
+
+/// A row of a generated `DB{seed}` table.
+pub(super) struct StoredRow {
+    pub(super) id: i64,
+    pub(super) data: Vec<u8>,
+    pub(super) hash: String,
+    pub(super) flag: String,
+    pub(super) rng_state: Vec<u8>,
+}
+
+impl StoredRow {
+    pub(super) const COLUMNS: &'static str = "id, data, hash, flag, rng_state";
+
+    pub(super) fn from_row(row: &Row) -> Result<Self, rusqlite::Error> {
+        Ok(StoredRow {
+            id: row.get(0)?,
+            data: column_bytes(row, 1)?,
+            hash: column_hash_hex(row, 2)?,
+            flag: row.get(3)?,
+            rng_state: row.get(4)?,
+        })
+    }
+
+    /// Undoes the table's `--compress` codec on the data column. User data is
+    /// stored by the miner as is.
+    pub(super) fn decoded(mut self, codec: Codec) -> Result<Self, GenError> {
+        if !self.data.is_empty() && !self.is_user_data() {
+            self.data = codec.decode(self.data).map_err(|err| GenError::CorruptRow {
+                id: self.id as usize,
+                reason: format!("cannot decompress data: {}", err),
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Miner-stored rows (flag `T`) hold user data in place of the chunk.
+    pub(super) fn is_user_data(&self) -> bool {
+        self.flag == "T"
+    }
+
+    /// First column that differs from the regenerated chunk, if any. The
+    /// `hash` column holds the first `hash_bytes` bytes of the chunk hash.
+    pub(super) fn mismatch(&self, chunk_data: &[u8], chunk_hash: &[u8], hash_bytes: usize) -> Option<&'static str> {
+        if self.rng_state != chunk_hash {
+            // The seed chain is kept even where the miner replaced the data.
+            Some("rng_state")
+        } else if self.is_user_data() {
+            None
+        } else if !self.data.is_empty() && self.data != chunk_data {
+            Some("data")
+        } else if self.hash != hex::encode(&chunk_hash[..hash_bytes]) {
+            Some("hash")
+        } else {
+            None
+        }
+    }
+
+    /// `mismatch` for rows read without their data, against the hash-only
+    /// chain.
+    pub(super) fn hash_mismatch(&self, chunk_hash: &[u8], hash_bytes: usize) -> Option<&'static str> {
+        debug_assert!(self.data.is_empty());
+        self.mismatch(&[], chunk_hash, hash_bytes)
+    }
+}
+
+/// Where the chunks of a table are stored: chunk `step` of the seed chain
+/// goes to id `offset + step * stride`. The chain itself, and the seed
+/// checkpoints that index it, only know steps, so a table of `n` chunks holds
+/// ids `0..n` unless it was generated with `--id_stride`/`--id_offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct IdLayout {
+    pub(super) offset: usize,
+    pub(super) stride: usize,
+}
+
+impl IdLayout {
+    pub(super) const SEQUENTIAL: IdLayout = IdLayout { offset: 0, stride: 1 };
+
+    /// Recorded in metadata as `id_offset` and `id_stride`; tables without
+    /// them store their chunks at sequential ids.
+    pub(super) fn stored(conn: &Connection, table: &str) -> Result<Self, GenError> {
+        let read = |key: &'static str, default: usize| -> Result<usize, GenError> {
+            match read_metadata(conn, table, key)? {
+                Some(value) => match value.parse() {
+                    Ok(parsed) if key != "id_stride" || parsed > 0 => Ok(parsed),
+                    _ => Err(GenError::InvalidMetadata { key, value }),
+                },
+                None => Ok(default),
+            }
+        };
+        Ok(IdLayout { offset: read("id_offset", 0)?, stride: read("id_stride", 1)? })
+    }
+
+    /// Id of chunk `step`. `Args` checks that the ids of its table fit.
+    pub(super) fn id(self, step: usize) -> i64 {
+        (self.offset + step * self.stride) as i64
+    }
+
+    /// Step of the chunk stored at `id`, or `None` if no chunk belongs there.
+    pub(super) fn step(self, id: i64) -> Option<usize> {
+        let from_offset = usize::try_from(id).ok()?.checked_sub(self.offset)?;
+        (from_offset % self.stride == 0).then_some(from_offset / self.stride)
+    }
+
+    /// First step whose chunk is stored at or after `id`.
+    pub(super) fn step_from(self, id: usize) -> usize {
+        id.saturating_sub(self.offset).div_ceil(self.stride)
+    }
+}
+
+/// Seed used to generate chunk `step`: the `rng_state` of the row before it,
+/// or the genesis seed for the first row.
+pub(super) fn seed_for_id(
+    conn: &Connection,
+    table: &str,
+    step: usize,
+    genesis: &[u8],
+    hash_algo: HashAlgo,
+    layout: IdLayout,
+) -> Result<Vec<u8>, GenError> {
+    match step.checked_sub(1) {
+        Some(prev) => match read_row(conn, table, layout.id(prev))? {
+            Some(row) => row_seed(row.id as usize, row.rng_state, hash_algo),
+            None => Ok(Vec::new()),
+        },
+        None => Ok(genesis.to_vec()),
+    }
+}
+
+/// The `rng_state` of row `id` as a seed. Only a torn or corrupt row holds
+/// one that isn't a full digest, and the chain can't go on from it.
+pub(super) fn row_seed(id: usize, rng_state: Vec<u8>, hash_algo: HashAlgo) -> Result<Vec<u8>, GenError> {
+    if rng_state.len() != hash_algo.output_len() {
+        log::debug!("Row {} holds a {} byte rng_state, not {}", id, rng_state.len(), hash_algo.output_len());
+        return Err(GenError::CorruptSeedChain { id });
+    }
+    Ok(rng_state)
+}
+
+/// First step whose chunk is not present in the table, i.e. the length of
+/// its contiguous prefix.
+pub(super) fn first_missing_id(conn: &Connection, table: &str, layout: IdLayout) -> Result<usize, rusqlite::Error> {
+    let sql = format!(
+        "SELECT CASE WHEN NOT EXISTS (SELECT 1 FROM DB{0} WHERE id = ?1) THEN ?1 ELSE
+            (SELECT MIN(a.id) + ?2 FROM DB{0} a
+             WHERE a.id >= ?1 AND NOT EXISTS (SELECT 1 FROM DB{0} b WHERE b.id = a.id + ?2))
+        END",
+        table
+    );
+    let id: i64 = conn.query_row(&sql, params![layout.offset as i64, layout.stride as i64], |row| row.get(0))?;
+    Ok(layout.step_from(id as usize))
+}
+
+pub(super) fn read_row(conn: &Connection, table: &str, id: i64) -> Result<Option<StoredRow>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM DB{} WHERE id = ?", StoredRow::COLUMNS, table))?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(StoredRow::from_row(row)?)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,1687 @@
+//! Generating tables, into SQLite or a raw file.
+
+use super::*;
+
+/// Fails with `err`, unless `--force` is set, in which case it is only logged.
+pub(super) fn refuse_unless_forced(args: &Args, err: GenError) -> Result<(), GenError> {
+    if !args.force {
+        return Err(err);
+    }
+    log::warn!("{}, continuing anyway because of --force", err);
+    Ok(())
+}
+
+/// Records `requested` as the table's `key` setting. Stored rows were written
+/// with the previous value, so it can only change while the table is empty
+/// or with `--force`.
+pub(super) fn pin_setting(conn: &Connection, args: &Args, key: &'static str, stored: &str, requested: &str) -> Result<(), GenError> {
+    let table = args.seed.as_str();
+    if stored != requested {
+        if table_has_rows(conn, table)? {
+            refuse_unless_forced(args, GenError::ParamMismatch {
+                field: key,
+                stored: stored.to_string(),
+                requested: requested.to_string(),
+            })?;
+        }
+        remove_metadata(conn, table, key)?;
+        // The next manifest is built afresh rather than on a frontier of
+        // rows made with the old setting.
+        for key in ["manifest_frontier", "manifest_last_id"] {
+            remove_metadata(conn, table, key)?;
+        }
+    }
+    write_metadata(conn, table, key, requested)?;
+    Ok(())
+}
+
+/// Checks the requested generation settings against the ones recorded for
+/// the table, and records them if it has none yet. Appending rows made with
+/// another hash, rng or chunk size would leave a chain no verifier can replay.
+pub(super) fn pin_table_settings(conn: &Connection, args: &Args) -> Result<(), GenError> {
+    let table = args.seed.as_str();
+    let chunk_size = args.chunk_size.to_string();
+    let stored_chunk_size = stored_chunk_size(conn, table)?.map(|size| size.to_string());
+    let stored_hash_algo = stored_hash_algo(conn, table)?;
+    pin_setting(conn, args, "hash_algo", stored_hash_algo.name(), args.hash_algo.name())?;
+    // Read with the stored algorithm, whose digest bounds the stored width.
+    let hash_bytes = args.hash_bytes.to_string();
+    let stored_hash_bytes = stored_hash_bytes(conn, table, stored_hash_algo)?.to_string();
+    pin_setting(conn, args, "hash_bytes", &stored_hash_bytes, &hash_bytes)?;
+    pin_setting(conn, args, "hash_encoding", stored_hash_encoding(conn, table)?.name(), args.hash_encoding.name())?;
+    pin_setting(conn, args, "chunk_size", stored_chunk_size.as_deref().unwrap_or(&chunk_size), &chunk_size)?;
+    pin_setting(conn, args, "compression", stored_codec(conn, table)?.name(), args.compress.name())?;
+    pin_setting(conn, args, "rng", stored_rng(conn, table)?.name(), args.rng.name())?;
+    pin_setting(conn, args, "charset", stored_charset(conn, table)?.name(), args.charset.name())?;
+    if let Some(partition) = args.partition {
+        let stored = read_metadata(conn, table, "partition_index")?;
+        pin_setting(conn, args, "partition_index", stored.as_deref().unwrap_or("none"), &partition.0.to_string())?;
+    }
+    let stored_layout = IdLayout::stored(conn, table)?;
+    if args.id_layout != IdLayout::SEQUENTIAL || stored_layout != IdLayout::SEQUENTIAL {
+        let (stride, offset) = (args.id_layout.stride.to_string(), args.id_layout.offset.to_string());
+        pin_setting(conn, args, "id_stride", &stored_layout.stride.to_string(), &stride)?;
+        pin_setting(conn, args, "id_offset", &stored_layout.offset.to_string(), &offset)?;
+    }
+
+    // Informational only: the format of a table does not depend on the version.
+    let version = env!("CARGO_PKG_VERSION");
+    match read_metadata(conn, table, "generator_version")? {
+        Some(created_by) if created_by != version => {
+            log::info!("Table DB{} was created by version {}, this is {}", table, created_by, version);
+        }
+        Some(_) => {}
+        None => write_metadata(conn, table, "generator_version", version)?,
+    }
+    Ok(())
+}
+
+/// Upper bound on the chunk bytes a batch buffers before it is written, by
+/// the rawfile backend and by `--threads`, so large chunks don't multiply
+/// `--batch_size` into gigabytes.
+pub(super) const BATCH_BYTES: u64 = 64 << 20;
+
+/// Page size requested for new databases, large enough to keep typical
+/// chunks out of overflow pages.
+pub(super) const PAGE_SIZE: i64 = 32768;
+
+/// Rough per-row cost on top of the data, hash and rng_state columns: the
+/// record header, the id and flag, and b-tree cell bookkeeping.
+pub(super) const ROW_OVERHEAD: u64 = 16;
+
+/// `SQLITE_CONSTRAINT_PRIMARYKEY`, which the bundled bindings keep private.
+pub(super) const PRIMARY_KEY_CONFLICT: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (6 << 8);
+
+/// `err` of inserting row `id` into `DB{table}`, naming the id if the row
+/// was already there.
+pub(super) fn insert_error(err: rusqlite::Error, table: &str, id: i64) -> GenError {
+    match &err {
+        rusqlite::Error::SqliteFailure(failure, _) if failure.extended_code == PRIMARY_KEY_CONFLICT => {
+            GenError::DuplicateId { table: table.to_string(), id }
+        }
+        _ => err.into(),
+    }
+}
+
+/// Generates the tables of `args` behind progress bars, drawn unless
+/// `hidden`, and returns those done and those that failed under
+/// `--continue_on_error`.
+pub(super) fn generate_run(
+    args: &Args,
+    hidden: bool,
+    listener: Option<ProgressListener>,
+) -> Result<(Vec<TableRun>, Vec<TableFailure>), GenError> {
+    let multi = MultiProgress::new();
+    if hidden {
+        multi.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    let row_bytes = if args.hash { 0 } else { args.chunk_size as u64 } + args.hash_encoding.stored_len(args.hash_bytes) as u64;
+    let pb = Progress { listener, ..Progress::new(&multi, args.n_chunks as u64, row_bytes) };
+    let jobs = match args.db_root_path {
+        Some(_) => args.jobs.clamp(1, args.partitions),
+        None => 1,
+    };
+    let job_bars: Vec<ProgressBar> = if jobs > 1 {
+        (0..jobs).map(|_| Progress::job_bar(&multi)).collect()
+    } else {
+        Vec::new()
+    };
+
+    // The bars are drawn by a thread of their own until all of them are
+    // finished, so finish them on every path, errors included, and only then
+    // join it.
+    let progress_thread = std::thread::spawn(move || multi.join());
+    let outcomes = generate_tables(args, jobs, &pb, &job_bars);
+    for bar in &job_bars {
+        bar.finish_and_clear();
+    }
+    pb.finish();
+    progress_thread.join()
+        .map_err(|_| io::Error::other("progress bar thread panicked"))??;
+    let (mut tables, mut failures) = (Vec::new(), Vec::new());
+    for outcome in outcomes? {
+        match outcome {
+            Ok(table) => tables.push(table),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    Ok((tables, failures))
+}
+
+/// Asks on the terminal whether `--overwrite` may delete the tables of the
+/// run. Nothing is asked for databases that don't exist yet; without a
+/// terminal to ask on, only `--yes` lets the run go ahead.
+pub(super) fn confirm_overwrite(args: &Args) -> Result<(), GenError> {
+    let (target, exists) = match &args.db_root_path {
+        Some(root) => (
+            format!("the {} partitions of DB{} under {}", args.partitions, args.seed, root),
+            std::path::Path::new(root).exists(),
+        ),
+        None => (format!("DB{} in {}", args.seed, args.path), std::path::Path::new(&args.path).exists()),
+    };
+    if !exists {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other("--overwrite needs a terminal to confirm on, pass --yes to overwrite without asking").into());
+    }
+    eprint!("Delete {} and generate from scratch? [y/N] ", target);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        return Err(io::Error::other(format!("not overwriting {}", target)).into());
+    }
+    Ok(())
+}
+
+/// Creates `dir`, the directory `--{arg} value` points into, unless it
+/// exists or `--no_mkdir` asks to fail instead.
+pub(super) fn ensure_dir(dir: &std::path::Path, arg: &'static str, value: &str, mkdir: bool) -> Result<(), GenError> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    if !mkdir {
+        return Err(GenError::InvalidArg {
+            name: arg,
+            value: value.to_string(),
+            reason: format!("directory {} does not exist (omit --no_mkdir to create it)", dir.display()),
+        });
+    }
+    std::fs::create_dir_all(dir).map_err(|err| {
+        io::Error::new(err.kind(), format!("creating directory {} for --{}: {}", dir.display(), arg, err)).into()
+    })
+}
+
+/// Generates the table at `--path`, or every partition under `--db_root_path`.
+pub(super) fn generate_tables(args: &Args, jobs: usize, pb: &Progress, job_bars: &[ProgressBar]) -> Result<Vec<TableOutcome>, GenError> {
+    let root = match args.db_root_path.as_deref() {
+        None => {
+            if args.path != MEMORY_DB {
+                let parent = std::path::Path::new(&args.path).parent().filter(|dir| !dir.as_os_str().is_empty());
+                if let Some(parent) = parent {
+                    ensure_dir(parent, "path", &args.path, args.mkdir)?;
+                }
+            }
+            return Ok(vec![Ok(generate(args, pb)?)]);
+        }
+        Some(root) => root,
+    };
+    ensure_dir(std::path::Path::new(root), "db_root_path", root, args.mkdir)?;
+    if jobs > 1 {
+        return generate_concurrently(&partition_args(args, root), jobs, pb, job_bars);
+    }
+    let mut tables = Vec::new();
+    for partition in partition_args(args, root) {
+        if args.cancel.is_cancelled() {
+            break;
+        }
+        tables.push(partition_outcome(&partition, generate(&partition, pb))?);
+    }
+    Ok(tables)
+}
+
+/// A table of the run, or the error it failed with under `--continue_on_error`.
+pub(super) type TableOutcome = Result<TableRun, TableFailure>;
+
+pub(super) struct TableFailure {
+    pub(super) table: String,
+    pub(super) error: GenError,
+}
+
+/// `result` of generating `partition`, with a failure kept for the summary
+/// rather than ending the run if `--continue_on_error` was given.
+pub(super) fn partition_outcome(partition: &Args, result: Result<TableRun, GenError>) -> Result<TableOutcome, GenError> {
+    match result {
+        Ok(table) => Ok(Ok(table)),
+        Err(error) if partition.continue_on_error => {
+            let table = format!("DB{}", partition.seed);
+            log::error!("event=table_failed table={} path={} error={}", table, partition.path, error);
+            Ok(Err(TableFailure { table, error }))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Generates `partitions` on a pool of `jobs` threads. Each worker shows the
+/// table it is working on in its own bar of `job_bars`. Tables are returned in
+/// partition order; those not started before a shutdown are left out.
+pub(super) fn generate_concurrently(
+    partitions: &[Args],
+    jobs: usize,
+    pb: &Progress,
+    job_bars: &[ProgressBar],
+) -> Result<Vec<TableOutcome>, GenError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    let tables: Vec<Option<TableOutcome>> = pool.install(|| {
+        partitions.par_iter()
+            .map(|partition| {
+                if partition.cancel.is_cancelled() {
+                    return Ok(None);
+                }
+                let bar = &job_bars[rayon::current_thread_index().unwrap_or(0) % job_bars.len()];
+                let table_pb = pb.for_table(bar, &format!("DB{}", partition.seed), partition.n_chunks as u64);
+                partition_outcome(partition, generate(partition, &table_pb)).map(Some)
+            })
+            .collect::<Result<_, GenError>>()
+    })?;
+    Ok(tables.into_iter().flatten().collect())
+}
+
+/// Regenerated chunk bytes per second of a verification pass, in MB/s.
+pub(super) fn verify_throughput(verified: &Verified, elapsed: std::time::Duration, chunk_size: usize) -> f64 {
+    (verified.rows + verified.skipped) as f64 * chunk_size as f64 / elapsed.as_secs_f64().max(1e-9) / 1e6
+}
+
+/// Outcome of generating one table, reported once all tables are done.
+pub(super) struct TableRun {
+    pub(super) table: String,
+    pub(super) n_chunks: usize,
+    pub(super) final_seed: Vec<u8>,
+    pub(super) rows_written: usize,
+    pub(super) rows_deleted: usize,
+    /// Hash-only rows given their data by `--fill_data`.
+    pub(super) rows_filled: usize,
+    /// Size of the chunk data written, before and after compression.
+    pub(super) data_bytes: u64,
+    pub(super) stored_bytes: u64,
+    pub(super) next_id: usize,
+    /// Root recorded by `record_manifest`; the rawfile backend has none.
+    pub(super) manifest_root: Option<Vec<u8>>,
+    /// Result and duration of the `--verify_after` pass.
+    pub(super) verified: Option<(Verified, std::time::Duration)>,
+    pub(super) timings: Option<Arc<BatchTimings>>,
+    /// Whether the run was cancelled by the time the table was done.
+    pub(super) interrupted: bool,
+}
+
+impl TableRun {
+    /// What the run did to the table: `truncated` it down to `--n` rows,
+    /// `written` new rows, only `filled` in the data of existing ones, found
+    /// it already `complete`, or was `interrupted` before committing anything.
+    pub(super) fn status(&self) -> &'static str {
+        if self.rows_deleted > 0 {
+            "truncated"
+        } else if self.rows_written > 0 {
+            "written"
+        } else if self.rows_filled > 0 {
+            "filled"
+        } else if self.next_id >= self.n_chunks && !self.interrupted {
+            "complete"
+        } else {
+            "interrupted"
+        }
+    }
+}
+
+/// Arguments for each of the `--partitions` tables under `--db_root_path`.
+/// Partition `i` is table `DB{seed}_{i}` in a file of the same name, with
+/// `--n` split evenly across partitions. Each one is generated as if by
+/// `--partition_index i`, so its chain and recorded index are those of the
+/// same table generated on its own.
+pub(super) fn partition_args(args: &Args, root: &str) -> Vec<Args> {
+    (0..args.partitions)
+        .map(|index| {
+            let seed = format!("{}_{}", args.seed, index);
+            Args {
+                path: std::path::Path::new(root).join(format!("DB{}", seed)).display().to_string(),
+                n_chunks: args.n_chunks / args.partitions + usize::from(index < args.n_chunks % args.partitions),
+                seed,
+                partition: Some(PartitionId(index as u64)),
+                in_partitions: true,
+                status_file: args.status_file.as_ref().map(|path| format!("{}.{}", path, index)),
+                timings: args.timings.as_ref().map(|_| Arc::default()),
+                ..args.clone()
+            }
+        })
+        .collect()
+}
+
+pub(super) fn generate(args: &Args, pb: &Progress) -> Result<TableRun, GenError> {
+    let name = format!("DB{}", args.seed);
+    let pb = &match &args.status_file {
+        Some(path) => pb.with_status(StatusFile::new(path, &name, args.n_chunks as u64, pb.row_bytes)),
+        None => pb.clone(),
+    }
+    .listening(&name, args.n_chunks as u64);
+    let mut table = match args.backend {
+        Backend::Sqlite => generate_table(args, pb)?,
+        Backend::RawFile => generate_rawfile(args, pb)?,
+    };
+    if args.verify_after && !args.cancel.is_cancelled() {
+        let verify_args = VerifyArgs {
+            path: args.path.clone(),
+            key: args.key.clone(),
+            seed: args.seed.clone(),
+            chunk_size: Some(args.chunk_size),
+            from: 0,
+            threads: args.threads,
+            sample: None,
+            only_hashes: false,
+            backend: args.backend,
+        };
+        let started = std::time::Instant::now();
+        let verified = match args.backend {
+            Backend::Sqlite => verify_table(&verify_args)?,
+            Backend::RawFile => verify_rawfile(&verify_args)?,
+        };
+        let elapsed = started.elapsed();
+        log::info!(
+            "event=verified table={} rows={} skipped={} elapsed_ms={}",
+            table.table, verified.rows, verified.skipped, elapsed.as_millis()
+        );
+        table.verified = Some((verified, elapsed));
+    }
+    Ok(table)
+}
+
+/// Arguments that resume a `--partitions` table generated before partitions
+/// were generated as their partition index, which hashed the index onto the
+/// base genesis seed. Such a table records its genesis seed but no
+/// `partition_index`, and goes on with the seed it was started from.
+pub(super) fn legacy_partition_args(conn: &Connection, args: &Args) -> Result<Option<Args>, GenError> {
+    let table = args.seed.as_str();
+    if !args.in_partitions || read_metadata(conn, table, "partition_index")?.is_some() {
+        return Ok(None);
+    }
+    Ok(stored_genesis_seed(conn, table, args.hash_algo)?.map(|genesis| {
+        log::info!("Table DB{} was generated before partition indices, continuing its recorded chain", table);
+        Args { partition: None, genesis_seed: Some(genesis), ..args.clone() }
+    }))
+}
+
+/// Genesis seed of a new table without `--genesis_seed`: salted with the
+/// table name, and with the index of `--partition_index` if given, unless
+/// `--no_salt` asks for the legacy chain.
+pub(super) fn default_genesis(args: &Args) -> Vec<u8> {
+    let hasher = args.hash_algo.hasher();
+    if let Some(partition) = args.partition {
+        partition.genesis_seed(hasher.as_ref(), &format!("DB{}", args.seed), args.chunk_size)
+    } else if args.no_salt {
+        ChunkGenerator::genesis_seed(hasher.as_ref(), args.chunk_size)
+    } else {
+        ChunkGenerator::salted_genesis_seed(hasher.as_ref(), &format!("DB{}", args.seed), args.chunk_size)
+    }
+}
+
+/// Refuses to start a run that is bound to hit DiskFull halfway, unless
+/// `--force` is set.
+pub(super) fn check_free_space(args: &Args, needed: u64) -> Result<(), GenError> {
+    if args.path == MEMORY_DB {
+        return Ok(());
+    }
+    let available = fs2::available_space(&args.path)?;
+    if needed > available {
+        let err = GenError::InsufficientSpace {
+            needed,
+            available,
+            path: match std::path::Path::new(&args.path).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+                _ => ".".to_string(),
+            },
+        };
+        if !args.force {
+            return Err(err);
+        }
+        log::warn!("{}", err);
+    }
+    Ok(())
+}
+
+/// Makes every committed row durable before the run reports its final seed.
+/// With `--synchronous OFF` or `--journal OFF` commits leave the pages in the
+/// OS cache, so the log is folded into the database file and the file is
+/// flushed explicitly.
+pub(super) fn sync_database(conn: &Connection, args: &Args) -> Result<(), GenError> {
+    if args.journal == "WAL" {
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", params![], |_| Ok(()))?;
+    }
+    std::fs::File::open(&args.path)?.sync_all()?;
+    Ok(())
+}
+
+/// Bytes of the SQLite varint encoding of `value`.
+pub(super) fn varint_len(value: u64) -> u64 {
+    match value {
+        0..=0x7f => 1,
+        _ if value >> 56 != 0 => 9,
+        _ => (64 - value.leading_zeros() as u64).div_ceil(7),
+    }
+}
+
+/// Estimated size of a database holding `n_chunks` generated rows, following
+/// the SQLite file format: the record of each row (the `data`, `hash`, `flag`
+/// and `rng_state` columns behind a header of serial types; `id` aliases the
+/// rowid and takes no space in the record), its cell in a b-tree leaf page,
+/// any overflow pages, the interior pages above the leaves and the pointer
+/// map pages of auto_vacuum. Rows appended in id order leave the leaf pages
+/// full; the interleaved ranges of `--threads` split some of them, which costs
+/// a few percent more. Compressed data is counted at its uncompressed size.
+pub(super) fn estimate_db_bytes(
+    n_chunks: u64,
+    chunk_size: usize,
+    only_hash: bool,
+    hash_algo: HashAlgo,
+    hash_bytes: usize,
+    hash_encoding: HashEncoding,
+) -> u64 {
+    let usable = PAGE_SIZE as u64;
+    let seed_len = hash_algo.output_len() as u64;
+    // Generated chunks carry the hex hash of their characters.
+    let data_len = if only_hash { 0 } else { chunk_size as u64 + 2 * seed_len };
+    let hash_len = hash_encoding.stored_len(hash_bytes) as u64;
+    // Serial types are 12 + 2n for an n-byte blob and 13 + 2n for text.
+    let hash_type = match hash_encoding {
+        HashEncoding::Hex => 13 + 2 * hash_len,
+        HashEncoding::Blob => 12 + 2 * hash_len,
+    };
+    let header = 1 + 1 + varint_len(12 + 2 * data_len) + varint_len(hash_type) + 1 + varint_len(12 + 2 * seed_len);
+    let payload = header + data_len + hash_len + 1 + seed_len;
+
+    // Payloads past the local limit of a leaf cell spill into overflow pages.
+    let max_local = usable - 35;
+    let (local, overflow_pages) = if payload <= max_local {
+        (payload, 0)
+    } else {
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let fit = min_local + (payload - min_local) % (usable - 4);
+        let local = if fit <= max_local { fit } else { min_local };
+        (local + 4, (payload - local).div_ceil(usable - 4))
+    };
+    let cell = varint_len(payload) + varint_len(n_chunks) + local;
+    // Each cell also takes a 2-byte pointer; leaf pages have an 8-byte header.
+    let per_leaf = ((usable - 8) / (cell + 2)).max(1);
+    let mut pages = n_chunks.div_ceil(per_leaf) + n_chunks * overflow_pages;
+
+    // Interior cells are a child page number and a rowid.
+    let fanout = (usable - 12) / (4 + varint_len(n_chunks) + 2);
+    let mut level = n_chunks.div_ceil(per_leaf);
+    while level > 1 {
+        level = level.div_ceil(fanout);
+        pages += level;
+    }
+    // The schema, the metadata and checkpoint tables with their primary key
+    // indexes, and one pointer map page per `usable / 5` pages.
+    pages += 5;
+    pages += pages.div_ceil(usable / 5);
+    pages.saturating_mul(usable)
+}
+
+/// Sets up the database for a generation run: file-level pragmas, the
+/// connection settings and the table itself. Returns the journal mode in use.
+pub(super) fn prepare_db(conn: &Connection, args: &Args) -> Result<String, GenError> {
+    // The page size and auto_vacuum mode are fixed once the file is written,
+    // so they must be set before switching the journal mode or creating any
+    // table. On an existing database without auto_vacuum the mode is only
+    // pending, and is applied by the VACUUM that reclaims space on a truncate.
+    conn.pragma_update(None, "page_size", &PAGE_SIZE)?;
+    conn.pragma_update(None, "auto_vacuum", &args.auto_vacuum)?;
+    let auto_vacuum: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+    if ["NONE", "FULL", "INCREMENTAL"][auto_vacuum.clamp(0, 2) as usize] != args.auto_vacuum {
+        log::warn!(
+            "Requested auto_vacuum={} but the database was created with {}, which only a VACUUM can change",
+            args.auto_vacuum, auto_vacuum
+        );
+    }
+    let journal_mode: String = conn.pragma_update_and_check(None, "journal_mode", &args.journal, |row| row.get(0))?;
+    // An in-memory database only ever journals in memory.
+    if args.reader_safe && !journal_mode.eq_ignore_ascii_case("WAL") {
+        return Err(GenError::InvalidArg {
+            name: "reader_safe",
+            value: args.path.clone(),
+            reason: format!("the database can't leave journal_mode={}, so readers aren't safe", journal_mode),
+        });
+    }
+    if !journal_mode.eq_ignore_ascii_case(&args.journal) && args.path != MEMORY_DB {
+        log::warn!("Requested journal_mode={} but the database is using {}", args.journal, journal_mode);
+    }
+    configure_connection(conn, args)?;
+    let seed_value = args.seed.as_str();
+    
+    if args.delete {
+        let mut delete_table = format!(
+            "DROP TABLE IF EXISTS DB{}", 
+            seed_value
+        );
+        conn.execute(&delete_table, params![])?;
+        delete_table = "DROP TABLE IF EXISTS latest_rng_state".to_string();
+        conn.execute(&delete_table, params![])?;
+        clear_metadata(conn, seed_value)?;
+        clear_checkpoints(conn, seed_value)?;
+    }
+
+    // `id` aliases the rowid, so the rows are stored in id order without a
+    // separate index. WITHOUT ROWID saves nothing on hash-only tables: their
+    // rows are already keyed by the id alone, and at 32 KiB pages the
+    // clustered index came out about 14% larger.
+    let create_table_sql = format!(
+        "CREATE TABLE IF NOT EXISTS DB{} (
+            id INTEGER PRIMARY KEY, 
+            data BLOB NOT NULL, 
+            hash TEXT NOT NULL,
+            flag TEXT NOT NULL,
+            rng_state BLOB NOT NULL
+        )", seed_value);
+    //log::info!("create_table_sql: {}", create_table_sql);
+    conn.execute(&create_table_sql, params![])?;
+    // The CREATE is a no-op on an existing table, whatever its columns.
+    check_table_schema(conn, seed_value)?;
+    create_checkpoint_table(conn)?;
+    Ok(journal_mode)
+}
+
+/// Columns of a generated table and the types they may be declared with.
+/// Tables created before the data column was declared BLOB have it as TEXT,
+/// which holds the same values.
+pub(super) const TABLE_COLUMNS: [(&str, &[&str]); 5] = [
+    ("id", &["INTEGER"]),
+    ("data", &["BLOB", "TEXT"]),
+    ("hash", &["TEXT"]),
+    ("flag", &["TEXT"]),
+    ("rng_state", &["BLOB"]),
+];
+
+/// Fails with `SchemaMismatch` unless `DB{table}` has the columns of
+/// `TABLE_COLUMNS`, in that order. A missing table is left to the queries
+/// on it to report.
+pub(super) fn check_table_schema(conn: &Connection, table: &str) -> Result<(), GenError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(DB{})", table))?;
+    let columns: Vec<(String, String)> = stmt
+        .query_map(params![], |row| Ok((row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+    let matches = columns.is_empty()
+        || columns.len() == TABLE_COLUMNS.len()
+        && columns.iter().zip(TABLE_COLUMNS).all(|((name, declared), (expected, types))| {
+            name == expected && types.iter().any(|ty| declared.eq_ignore_ascii_case(ty))
+        });
+    if matches {
+        return Ok(());
+    }
+    Err(GenError::SchemaMismatch {
+        table: table.to_string(),
+        expected: TABLE_COLUMNS.iter().map(|(name, types)| format!("{} {}", name, types[0])).collect(),
+        found: columns.into_iter().map(|(name, declared)| format!("{} {}", name, declared)).collect(),
+    })
+}
+
+/// Creates or resumes the table for `args.seed` in `args.path` and fills it
+/// up to `args.n_chunks` rows.
+///
+/// A table of `--n N` holds exactly the ids `0..N`, so its last row is
+/// `N - 1`. Resuming with the next id at `N` leaves nothing to do; past `N`
+/// the rows from id `N` on are deleted; short of it the missing ids are
+/// appended.
+pub(super) fn generate_table(args: &Args, pb: &Progress) -> Result<TableRun, GenError> {
+    let num_chunks = args.n_chunks;
+    let chunk_size = args.chunk_size;
+
+    // Create a new SQLite connection
+    let mut conn = open_db(&args.path, args.key.as_deref())?;
+    let journal_mode = retry_busy(|| prepare_db(&conn, args))?;
+    let seed_value = args.seed.as_str();
+    log::info!(
+        "event=db_opened path={} table=DB{} journal_mode={} synchronous={} wal_autocheckpoint={}",
+        args.path, seed_value, journal_mode, args.synchronous, args.wal_autocheckpoint
+    );
+
+    let page_size: i64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+    if page_size != PAGE_SIZE {
+        log::warn!(
+            "Database uses {} byte pages instead of {}; the page size only applies to new files",
+            page_size, PAGE_SIZE
+        );
+    }
+
+    // Get current state
+    //log::info!("Preparing statement to fetch the latest RNG state from the database.");
+    
+    // A `--split_chain` fill writes ranges out of order, so an interrupted
+    // one can leave holes. Drop everything past the first missing id before resuming.
+    if read_metadata(&conn, seed_value, "fill_in_progress")?.is_some() {
+        let layout = IdLayout::stored(&conn, seed_value)?;
+        let first_gap = layout.id(first_missing_id(&conn, seed_value, layout)?);
+        log::warn!("Previous parallel fill was interrupted, truncating from id {}", first_gap);
+        conn.execute(&format!("DELETE FROM DB{} WHERE id >= ?", seed_value), params![first_gap])?;
+        remove_metadata(&conn, seed_value, "fill_in_progress")?;
+    }
+
+    let args = match legacy_partition_args(&conn, args)? {
+        Some(legacy) => Cow::Owned(legacy),
+        None => Cow::Borrowed(args),
+    };
+    let args = args.as_ref();
+    pin_table_settings(&conn, args)?;
+
+    let hasher = args.hash_algo.hasher();
+    let unsalted = ChunkGenerator::genesis_seed(hasher.as_ref(), chunk_size);
+    // `--no_salt` and `--partition_index` ask for their chain as explicitly
+    // as `--genesis_seed`.
+    let requested_genesis = args.genesis_seed.clone()
+        .or_else(|| (args.no_salt || args.partition.is_some()).then(|| default_genesis(args)));
+    let stored_genesis = stored_genesis_seed(&conn, seed_value, args.hash_algo)?;
+    let genesis = match (requested_genesis, stored_genesis) {
+        (Some(requested), Some(stored)) if requested != stored => {
+            refuse_unless_forced(args, GenError::ParamMismatch {
+                field: "genesis_seed",
+                stored: hex::encode(stored),
+                requested: hex::encode(&requested),
+            })?;
+            remove_metadata(&conn, seed_value, "genesis_seed")?;
+            write_metadata(&conn, seed_value, "genesis_seed", &hex::encode(&requested))?;
+            requested
+        }
+        (Some(requested), None) => {
+            // Rows without a recorded genesis seed continue the unsalted chain.
+            if requested != unsalted && table_has_rows(&conn, seed_value)? {
+                refuse_unless_forced(args, GenError::ParamMismatch {
+                    field: "genesis_seed",
+                    stored: "derived from the zero chunk".to_string(),
+                    requested: hex::encode(&requested),
+                })?;
+            }
+            write_metadata(&conn, seed_value, "genesis_seed", &hex::encode(&requested))?;
+            requested
+        }
+        (_, Some(stored)) => stored,
+        (None, None) if table_has_rows(&conn, seed_value)? => {
+            log::info!("Table DB{} has no recorded genesis seed, continuing its unsalted chain", seed_value);
+            unsalted.clone()
+        }
+        (None, None) => {
+            let genesis = default_genesis(args);
+            write_metadata(&conn, seed_value, "genesis_seed", &hex::encode(&genesis))?;
+            genesis
+        }
+    };
+    let partition = read_metadata(&conn, seed_value, "partition_index")?
+        .and_then(|index| index.parse().ok())
+        .map(PartitionId);
+    let salt = if genesis == unsalted {
+        "none"
+    } else if genesis == ChunkGenerator::salted_genesis_seed(hasher.as_ref(), &format!("DB{}", seed_value), chunk_size) {
+        "table_name"
+    } else if partition.is_some_and(|partition| {
+        genesis == partition.genesis_seed(hasher.as_ref(), &format!("DB{}", seed_value), chunk_size)
+    }) {
+        "partition_index"
+    } else {
+        "custom"
+    };
+    remove_metadata(&conn, seed_value, "salt")?;
+    write_metadata(&conn, seed_value, "salt", salt)?;
+
+    let (start_index, current_seed) = match &args.start {
+        Some((index, seed)) => explicit_start(&conn, args, &genesis, *index, seed)?,
+        None => resume_point(&conn, args, &genesis)?,
+    };
+    log::info!(
+        "event=resume_point table=DB{} next_id={} seed={}",
+        seed_value, start_index, hex::encode(&current_seed)
+    );
+    
+    // Initialize ChunkGenerator with the provided seed and chunk size
+    let mut chunk_gen = ChunkGenerator::new(current_seed, chunk_size, hasher)
+        .with_rng(args.rng)
+        .with_charset(args.charset);
+
+    let stored_bytes = AtomicU64::new(0);
+    let rows_filled = if args.fill_data {
+        let (rows, bytes) = fill_data(&mut conn, args, start_index.min(num_chunks), pb)?;
+        stored_bytes.fetch_add(bytes, Ordering::Relaxed);
+        rows
+    } else {
+        if !args.hash && first_generated_row(&conn, seed_value, args.compress)?.is_some_and(|row| row.data.is_empty()) {
+            log::warn!("DB{} holds hash-only rows, rerun with --fill_data to store their data", seed_value);
+        }
+        0
+    };
+
+    // Delete the rows past id `num_chunks - 1`.
+    let next_id;
+    let mut rows_deleted = 0;
+    if start_index > num_chunks {
+        let delete_rows = format!(
+            "DELETE FROM DB{} WHERE id >= ?", 
+            seed_value
+        );
+        let size_before = database_size(&args.path)?;
+        rows_deleted = conn.execute(&delete_rows, params![args.id_layout.id(num_chunks)])?;
+        reclaim_space(&conn, Some(&args.auto_vacuum))?;
+        log::info!(
+            "event=truncated table=DB{} from_id={} rows_deleted={} bytes_before={} bytes_after={}",
+            seed_value, args.id_layout.id(num_chunks), rows_deleted, size_before, database_size(&args.path)?
+        );
+        next_id = num_chunks;
+        pb.resume_at(num_chunks as u64);
+
+        // The chain now ends at the last kept row.
+        chunk_gen.set_seed(seed_for_id(&conn, seed_value, num_chunks, &genesis, args.hash_algo, args.id_layout)?);
+    } else {
+        check_free_space(args, estimate_db_bytes(
+            (num_chunks - start_index) as u64,
+            args.chunk_size,
+            args.hash,
+            args.hash_algo,
+            args.hash_bytes,
+            args.hash_encoding,
+        ))?;
+
+        // Generate and store chunks
+        pb.resume_at(start_index as u64);
+
+        // Store the id, data, hash, and rng_state
+        // ABORT is SQLite's default, spelled out because a conflict here
+        // is a resume bug that must fail the batch rather than be skipped.
+        let insert_sql = format!(
+            "INSERT OR ABORT INTO DB{} (id, data, hash, flag, rng_state) VALUES (?, ?, ?, ?, ?)", 
+            seed_value
+        );
+
+        let threads = args.threads.clamp(1, (num_chunks - start_index).max(1));
+        let mut split_chain = args.split_chain && threads > 1;
+        if split_chain && args.path == MEMORY_DB {
+            // Shared-cache connections fail with SQLITE_LOCKED instead of
+            // waiting for each other.
+            log::warn!("An in-memory database is written by a single connection, ignoring --split_chain");
+            split_chain = false;
+        }
+        next_id = if let Some(from) = &args.import_from {
+            import_range(&mut conn, &insert_sql, &mut chunk_gen, start_index..num_chunks, from, args, pb, &stored_bytes)?
+        } else if threads == 1 {
+            write_range(&mut conn, &insert_sql, &mut chunk_gen, start_index..num_chunks, args, pb, &stored_bytes)?
+        } else if !split_chain {
+            fill_pipelined(&mut conn, &insert_sql, &mut chunk_gen, start_index..num_chunks, threads, args, pb, &stored_bytes)?
+        } else {
+            write_metadata(&conn, seed_value, "fill_in_progress", "1")?;
+            fill_parallel(args, &insert_sql, &mut chunk_gen, start_index, threads, pb, &stored_bytes)?;
+            if args.cancel.is_cancelled() {
+                // Leave the marker so the next run drops rows past the first gap.
+                let next_id = first_missing_id(&conn, seed_value, args.id_layout)?;
+                chunk_gen.set_seed(seed_for_id(&conn, seed_value, next_id, &genesis, args.hash_algo, args.id_layout)?);
+                next_id
+            } else {
+                remove_metadata(&conn, seed_value, "fill_in_progress")?;
+                num_chunks
+            }
+        };
+    }
+    let rows_written = next_id.saturating_sub(start_index);
+    let manifest_root = match read_metadata(&conn, seed_value, "manifest_root")? {
+        Some(root) if rows_written == 0 && rows_deleted == 0 && manifest_prefixed(&conn, seed_value)? => {
+            hex::decode(root).ok()
+        }
+        _ => {
+            let appended_from = (rows_deleted == 0).then(|| args.id_layout.id(start_index));
+            record_manifest(&conn, seed_value, args.hash_algo, args.hash_bytes, appended_from)?
+        }
+    };
+    if args.fsync_on_exit && args.path != MEMORY_DB {
+        sync_database(&conn, args)?;
+    }
+    if let Err((_conn, err)) = conn.close() {
+        return Err(err.into());
+    }
+
+    log::info!(
+        "event=table_done table=DB{} rows_written={} next_id={} final_seed={}",
+        seed_value, rows_written, next_id, hex::encode(chunk_gen.seed())
+    );
+    Ok(TableRun {
+        table: format!("DB{}", seed_value),
+        n_chunks: num_chunks,
+        final_seed: chunk_gen.seed().to_vec(),
+        rows_written,
+        rows_deleted,
+        rows_filled,
+        data_bytes: if args.hash { 0 } else { rows_written as u64 * pb.row_bytes },
+        stored_bytes: stored_bytes.into_inner(),
+        next_id,
+        manifest_root,
+        verified: None,
+        timings: args.timings.clone(),
+        interrupted: args.cancel.is_cancelled(),
+    })
+}
+
+/// Creates or resumes the raw store at `args.path` and fills it up to
+/// `args.n_chunks` chunks. Works like `generate_table`, except that the index
+/// holds the seed of every chunk, so checking the head on resume and
+/// truncating need no replay and no row lookups.
+pub(super) fn generate_rawfile(args: &Args, pb: &Progress) -> Result<TableRun, GenError> {
+    let num_chunks = args.n_chunks;
+    if args.delete {
+        rawfile::remove(&args.path)?;
+    }
+    let meta = match RawMeta::load(&args.path)? {
+        Some(meta) => {
+            let settings = [
+                ("table", meta.table.clone(), args.seed.clone()),
+                ("hash_algo", meta.hash_algo.name().to_string(), args.hash_algo.name().to_string()),
+                ("rng", meta.rng.name().to_string(), args.rng.name().to_string()),
+                ("charset", meta.charset.name().to_string(), args.charset.name().to_string()),
+                ("chunk_size", meta.chunk_size.to_string(), args.chunk_size.to_string()),
+                (
+                    "genesis_seed",
+                    hex::encode(&meta.genesis_seed),
+                    args.genesis_seed.as_ref().map_or_else(|| hex::encode(&meta.genesis_seed), hex::encode),
+                ),
+            ];
+            for (key, stored, requested) in settings {
+                if stored != requested {
+                    return Err(GenError::ParamMismatch { field: key, stored, requested });
+                }
+            }
+            meta
+        }
+        None => {
+            let meta = RawMeta {
+                table: args.seed.clone(),
+                hash_algo: args.hash_algo,
+                rng: args.rng,
+                charset: args.charset,
+                chunk_size: args.chunk_size,
+                genesis_seed: args.genesis_seed.clone().unwrap_or_else(|| default_genesis(args)),
+            };
+            meta.save(&args.path)?;
+            meta
+        }
+    };
+    let mut store = RawStore::open(&args.path, &meta, true)?;
+    log::info!(
+        "event=db_opened path={} table=DB{} backend=rawfile synchronous={}",
+        args.path, args.seed, args.synchronous
+    );
+
+    // Like `resume_point`, drop chunks off the end until the last one
+    // regenerates.
+    while let Some(head) = store.len().checked_sub(1) {
+        match raw_mismatch(&store, &meta, head as usize)? {
+            None => break,
+            Some(column) if args.strict_resume => return Err(GenError::Mismatch { id: head as usize, column }),
+            Some(column) => {
+                log::warn!("Chunk {} failed verification ({} differs), deleting it before resuming", head, column);
+                store.truncate(head)?;
+            }
+        }
+    }
+    let start_index = store.len() as usize;
+    log::info!("event=resume_point table=DB{} next_id={}", args.seed, start_index);
+
+    let mut rows_deleted = 0;
+    if start_index > num_chunks {
+        rows_deleted = start_index - num_chunks;
+        store.truncate(num_chunks as u64)?;
+        log::info!(
+            "event=truncated table=DB{} from_id={} rows_deleted={}",
+            args.seed, num_chunks, rows_deleted
+        );
+        pb.resume_at(num_chunks as u64);
+    } else {
+        check_free_space(args, (num_chunks - start_index) as u64 * (meta.record_len() + meta.hash_len()))?;
+        if args.threads > 1 {
+            log::warn!("The rawfile backend writes with a single thread, ignoring --threads {}", args.threads);
+        }
+        pb.resume_at(start_index as u64);
+
+        let mut chunk_gen = ChunkGenerator::new(raw_seed(&store, &meta, start_index)?, args.chunk_size, args.hash_algo.hasher())
+            .with_rng(args.rng)
+            .with_charset(args.charset);
+        let batch_size = args.batch_size.clamp(1, (BATCH_BYTES / meta.record_len()).max(1) as usize);
+        let sync = args.synchronous != "OFF";
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut batch_start = start_index;
+        while batch_start < num_chunks && !args.cancel.is_cancelled() {
+            let batch_end = num_chunks.min(batch_start + batch_size);
+            let batch_started = std::time::Instant::now();
+            batch.clear();
+            batch.extend(chunk_gen.by_ref().take(batch_end - batch_start));
+            pb.inc((batch_end - batch_start) as u64);
+            store.append(&batch, sync)?;
+            pb.committed((batch_end - batch_start) as u64);
+            throttle_writes(args, (batch_end - batch_start) as u64 * (meta.record_len() + meta.hash_len()));
+            log_batch(args, batch_start, batch_end, batch_started);
+            batch_start = batch_end;
+        }
+        if args.fsync_on_exit && !sync {
+            store.sync()?;
+        }
+    }
+
+    let next_id = store.len() as usize;
+    let rows_written = next_id.saturating_sub(start_index);
+    let final_seed = raw_seed(&store, &meta, next_id)?;
+    log::info!(
+        "event=table_done table=DB{} rows_written={} next_id={} final_seed={}",
+        args.seed, rows_written, next_id, hex::encode(&final_seed)
+    );
+    Ok(TableRun {
+        table: format!("DB{}", args.seed),
+        n_chunks: num_chunks,
+        final_seed,
+        rows_written,
+        rows_deleted,
+        rows_filled: 0,
+        data_bytes: rows_written as u64 * pb.row_bytes,
+        stored_bytes: rows_written as u64 * meta.record_len(),
+        next_id,
+        manifest_root: None,
+        verified: None,
+        timings: None,
+        interrupted: args.cancel.is_cancelled(),
+    })
+}
+
+/// Applies the per-connection pragmas requested on the command line.
+pub(super) fn configure_connection(conn: &Connection, args: &Args) -> Result<(), rusqlite::Error> {
+    conn.pragma_update(None, "synchronous", &args.synchronous)?;
+    let synchronous: i64 = conn.pragma_query_value(None, "synchronous", |row| row.get(0))?;
+    let expected = match args.synchronous.as_str() {
+        "OFF" => 0,
+        "NORMAL" => 1,
+        _ => 2,
+    };
+    if synchronous != expected {
+        log::warn!("Requested synchronous={} but the connection is using {}", args.synchronous, synchronous);
+    }
+
+    if let Some(cache_mb) = args.cache_mb {
+        // A negative cache_size is a size in KiB rather than a page count.
+        conn.pragma_update(None, "cache_size", &-((cache_mb * 1024) as i64))?;
+    }
+    conn.pragma_update(None, "wal_autocheckpoint", &(args.wal_autocheckpoint as i64))?;
+    Ok(())
+}
+
+/// `--wal_autocheckpoint` without the flag. Checkpointing after every batch
+/// costs 15-20% of the throughput of 1 KiB chunks in batches of 1000, which
+/// fill only about 40 pages; letting the log grow to 1000 pages (32 MiB)
+/// recovers it, and 4000 or 16000 pages gained nothing more. Batches larger
+/// than that are still checkpointed once per commit.
+pub(super) fn default_wal_autocheckpoint(
+    batch_size: usize,
+    chunk_size: usize,
+    only_hash: bool,
+    hash_algo: HashAlgo,
+    hash_bytes: usize,
+    hash_encoding: HashEncoding,
+) -> usize {
+    let batch_bytes = estimate_db_bytes(batch_size as u64, chunk_size, only_hash, hash_algo, hash_bytes, hash_encoding);
+    (batch_bytes.div_ceil(PAGE_SIZE as u64) as usize).max(1000)
+}
+
+/// Generates the chunks for `range` and inserts them, committing every
+/// `args.batch_size` rows so an interrupted run can always resume from the
+/// last fully written batch. In WAL mode the commit that takes the log past
+/// `args.wal_autocheckpoint` pages also checkpoints it, and a checkpoint that
+/// is busy or finds the disk full is left to a later commit; the committed
+/// rows are safe in the log.
+///
+/// A batch that runs out of disk space is rolled back and retried in halves,
+/// so every row that still fits is committed before the disk full error is
+/// returned. Each commit after that doubles the batch again, up to
+/// `args.batch_size`. Without a journal there is nothing to roll back with,
+/// so the error is returned at once.
+///
+/// Returns the id after the last committed row, which is short of
+/// `range.end` if a shutdown was requested.
+pub(super) fn write_range(
+    conn: &mut Connection,
+    insert_sql: &str,
+    chunk_gen: &mut ChunkGenerator,
+    range: std::ops::Range<usize>,
+    args: &Args,
+    pb: &Progress,
+    stored_bytes: &AtomicU64,
+) -> Result<usize, GenError> {
+    let mut batch_size = args.batch_size.max(1);
+    let journaled = args.journal != "OFF";
+    let mut batch_start = range.start;
+    while batch_start < range.end && !args.cancel.is_cancelled() {
+        let batch_end = range.end.min(batch_start + batch_size);
+        let batch_started = std::time::Instant::now();
+        let batch_seed = chunk_gen.seed().to_vec();
+        let batch_bytes = match write_batch(conn, insert_sql, chunk_gen, batch_start..batch_end, args) {
+            Ok(batch_bytes) => batch_bytes,
+            Err(GenError::DiskFull(_)) if journaled && batch_size > 1 => {
+                chunk_gen.set_seed(batch_seed);
+                batch_size = (batch_end - batch_start).div_ceil(2);
+                log::warn!(
+                    "Disk full writing DB{} ids {}..{}, retrying in batches of {} rows",
+                    args.seed, batch_start, batch_end, batch_size
+                );
+                continue;
+            }
+            Err(err @ GenError::DiskFull(_)) if journaled => {
+                log::error!(
+                    "Disk full at id {} of DB{}, ids {}..{} were committed",
+                    batch_start, args.seed, range.start, batch_start
+                );
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+        stored_bytes.fetch_add(batch_bytes, Ordering::Relaxed);
+        pb.inc((batch_end - batch_start) as u64);
+        pb.committed((batch_end - batch_start) as u64);
+        let row_overhead = args.hash_encoding.stored_len(args.hash_bytes) + args.hash_algo.output_len();
+        throttle_writes(args, batch_bytes + (batch_end - batch_start) as u64 * row_overhead as u64);
+        log_batch(args, batch_start, batch_end, batch_started);
+        batch_start = batch_end;
+        // A disk that was full for a moment needn't slow down the rest of the run.
+        batch_size = (batch_size * 2).min(args.batch_size.max(1));
+    }
+    Ok(batch_start)
+}
+
+/// Token bucket behind `--max_write_mbps`. Every committed batch takes its
+/// bytes from the bucket, and a writer that overdraws it sleeps until the
+/// deficit has refilled, so the rate holds on average over a few batches.
+/// After an idle spell up to a second's worth of writes go through at once.
+pub(super) struct WriteThrottle {
+    pub(super) bytes_per_sec: f64,
+    /// Tokens in the bucket, which go negative on an overdraft, and when it
+    /// was last refilled.
+    pub(super) state: Mutex<(f64, std::time::Instant)>,
+}
+
+impl WriteThrottle {
+    pub(super) fn new(bytes_per_sec: f64) -> Self {
+        WriteThrottle { bytes_per_sec, state: Mutex::new((bytes_per_sec, std::time::Instant::now())) }
+    }
+
+    /// Takes `bytes` from the bucket and waits out any deficit, returning
+    /// early on a shutdown.
+    pub(super) fn take(&self, bytes: u64, cancel: &CancelToken) {
+        let deficit = {
+            let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+            let (tokens, refilled) = &mut *state;
+            let now = std::time::Instant::now();
+            *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.bytes_per_sec).min(self.bytes_per_sec);
+            *refilled = now;
+            *tokens -= bytes as f64;
+            -*tokens
+        };
+        if deficit <= 0.0 {
+            return;
+        }
+        let until = std::time::Instant::now() + std::time::Duration::from_secs_f64(deficit / self.bytes_per_sec);
+        while !cancel.is_cancelled() {
+            let left = until.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(std::time::Duration::from_millis(100)));
+        }
+    }
+}
+
+/// Latencies of the batches of one table for `--timing_histogram`, in
+/// microseconds. Generating and inserting a batch is mostly hashing, so a
+/// slow `generate` points at the CPU; its commit is mostly the fsync, so
+/// stalls in `commit` point at the disk.
+#[derive(Default)]
+pub(super) struct BatchTimings(Mutex<Option<(Histogram<u64>, Histogram<u64>)>>);
+
+impl BatchTimings {
+    pub(super) fn record(&self, generate: std::time::Duration, commit: std::time::Duration) {
+        let mut histograms = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let (generated, committed) = histograms.get_or_insert_with(|| {
+            // Slower batches than an hour are counted as an hour.
+            let histogram = || Histogram::new_with_bounds(1, 3_600_000_000, 3).expect("valid histogram bounds");
+            (histogram(), histogram())
+        });
+        generated.saturating_record(generate.as_micros() as u64);
+        committed.saturating_record(commit.as_micros() as u64);
+    }
+
+    /// `(name, p50, p99, max)` of both histograms, empty before the first batch.
+    pub(super) fn percentiles(&self) -> Vec<(&'static str, u64, u64, u64)> {
+        let histograms = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let Some((generated, committed)) = &*histograms else {
+            return Vec::new();
+        };
+        [("generate", generated), ("commit", committed)]
+            .into_iter()
+            .map(|(name, histogram)| {
+                (name, histogram.value_at_quantile(0.5), histogram.value_at_quantile(0.99), histogram.max())
+            })
+            .collect()
+    }
+
+    pub(super) fn batches(&self) -> u64 {
+        let histograms = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        histograms.as_ref().map_or(0, |(generated, _)| generated.len())
+    }
+
+    pub(super) fn to_json(&self) -> serde_json::Value {
+        let mut timing = serde_json::json!({ "batches": self.batches() });
+        for (name, p50, p99, max) in self.percentiles() {
+            timing[format!("{}_us", name)] = serde_json::json!({ "p50": p50, "p99": p99, "max": max });
+        }
+        timing
+    }
+
+    pub(super) fn print(&self, table: &str) {
+        println!("Batch timing of {} over {} batches:", table, self.batches());
+        let ms = |us: u64| us as f64 / 1000.0;
+        for (name, p50, p99, max) in self.percentiles() {
+            println!("  {:<8} p50 {:.1} ms  p99 {:.1} ms  max {:.1} ms", name, ms(p50), ms(p99), ms(max));
+        }
+    }
+}
+
+/// Paces a committed batch of `bytes` under `--max_write_mbps`.
+pub(super) fn throttle_writes(args: &Args, bytes: u64) {
+    if let Some(throttle) = &args.write_throttle {
+        throttle.take(bytes, &args.cancel);
+    }
+}
+
+/// Logs a committed batch, at info level under `--quiet` where it stands in
+/// for the progress bars.
+pub(super) fn log_batch(args: &Args, batch_start: usize, batch_end: usize, started: std::time::Instant) {
+    if args.quiet {
+        log::info!("wrote ids {}..{} of DB{}", batch_start, batch_end, args.seed);
+    }
+    log::debug!(
+        "event=batch_committed table=DB{} ids={}..{} elapsed_ms={}",
+        args.seed, batch_start, batch_end, started.elapsed().as_millis()
+    );
+}
+
+/// Inserts the chunks of `ids` in one transaction and returns the bytes of
+/// data stored. On an error the transaction is rolled back and `chunk_gen`
+/// is left partway through the batch.
+pub(super) fn write_batch(
+    conn: &mut Connection,
+    insert_sql: &str,
+    chunk_gen: &mut ChunkGenerator,
+    ids: std::ops::Range<usize>,
+    args: &Args,
+) -> Result<u64, GenError> {
+    let mut batch_bytes = 0;
+    let timer = args.timings.as_ref().map(|timings| (timings, std::time::Instant::now()));
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare_cached(insert_sql)?;
+        let mut save_seed = tx.prepare_cached(
+            "INSERT OR REPLACE INTO seed_checkpoints (table_name, id, seed) VALUES (?, ?, ?)",
+        )?;
+        for i in ids {
+            if args.checkpoint_interval != 0 && i % args.checkpoint_interval == 0 {
+                save_seed.execute(params![args.seed, i as i64, chunk_gen.seed()])?;
+            }
+            // Optionally only store the data hash
+            // log::info!("Set in DB id: {} seed: {:?}", i, chunk_gen.seed.to_vec());
+
+            let id = args.id_layout.id(i);
+            let inserted = if args.hash {
+                // Store only the hash.
+                let hash = args.hash_encoding.encode(&chunk_gen.next_hash_only()[..args.hash_bytes]);
+                insert.execute(params![id, &[] as &[u8], hash, "F", chunk_gen.seed()])
+            } else {
+                // Store all the data, straight from the generator's buffer.
+                // The chunk hash is the seed of the next row. Inserting a
+                // zeroblob and streaming the chunk in with `blob_open` skips
+                // SQLite's copy of the bound value, but measured no faster:
+                // 3-8% slower for chunks of 4 KiB to 128 KiB and 3-5% faster
+                // only at 1 MiB, next to the cost of generating the chunk.
+                let (chunk_data, chunk_hash) = chunk_gen.next_chunk();
+                let hash = args.hash_encoding.encode(&chunk_hash[..args.hash_bytes]);
+                let chunk_data = args.compress.encode(chunk_data)?;
+                batch_bytes += chunk_data.len() as u64;
+                insert.execute(params![id, chunk_data.as_ref(), hash, "F", chunk_hash])
+            };
+            inserted.map_err(|err| insert_error(err, &args.seed, id))?;
+        }
+    }
+    let generated = timer.map(|(timings, started)| (timings, started.elapsed(), std::time::Instant::now()));
+    tx.commit()?;
+    if let Some((timings, generate, committing)) = generated {
+        timings.record(generate, committing.elapsed());
+    }
+    Ok(batch_bytes)
+}
+
+/// A chunk generated ahead of the transaction that inserts it.
+pub(super) struct GeneratedRow {
+    pub(super) step: usize,
+    /// The data as stored, compressed under `--compress`; empty for `--hash`.
+    pub(super) data: Vec<u8>,
+    pub(super) hash: Value,
+    pub(super) rng_state: Vec<u8>,
+    /// Seed the chunk was generated from, if it is kept as a seed checkpoint.
+    pub(super) checkpoint: Option<Vec<u8>>,
+}
+
+/// Rows of one batch, with how long generating them took.
+pub(super) struct GeneratedBatch {
+    pub(super) rows: Vec<GeneratedRow>,
+    pub(super) generating: std::time::Duration,
+}
+
+/// Rows of a batch run by `--threads`: `--batch_size`, but no more than
+/// `BATCH_BYTES` of chunks, since the whole batch is held in memory.
+pub(super) fn generated_batch_size(args: &Args) -> usize {
+    args.batch_size.clamp(1, (BATCH_BYTES / args.chunk_size.max(1) as u64).max(1) as usize)
+}
+
+/// Walks the chain over `steps` and keeps every chunk, the counterpart of
+/// `write_batch` for writers that must not hold the write lock while the
+/// chunks are generated. Compression is left to `compress_rows`.
+pub(super) fn generate_batch(chunk_gen: &mut ChunkGenerator, steps: std::ops::Range<usize>, args: &Args) -> GeneratedBatch {
+    let started = std::time::Instant::now();
+    let rows = steps
+        .map(|step| {
+            let checkpoint = (args.checkpoint_interval != 0 && step % args.checkpoint_interval == 0)
+                .then(|| chunk_gen.seed().to_vec());
+            let (data, rng_state) = if args.hash {
+                (Vec::new(), chunk_gen.next_hash_only())
+            } else {
+                chunk_gen.next()
+            };
+            let hash = args.hash_encoding.encode(&rng_state[..args.hash_bytes]);
+            GeneratedRow { step, data, hash, rng_state, checkpoint }
+        })
+        .collect();
+    GeneratedBatch { rows, generating: started.elapsed() }
+}
+
+/// Compresses the data of `rows` under `--compress`, on the current rayon
+/// pool.
+pub(super) fn compress_rows(rows: &mut [GeneratedRow], args: &Args) -> Result<(), GenError> {
+    if args.compress == Codec::None {
+        return Ok(());
+    }
+    rows.par_iter_mut().try_for_each(|row| {
+        row.data = args.compress.encode(&row.data)?.into_owned();
+        Ok(())
+    })
+}
+
+/// Inserts `rows` in one transaction, like `write_batch` but from chunks
+/// generated beforehand, so the write lock is only held for the inserts.
+/// `generating` is what the rows took to generate, for `--timing_histogram`.
+pub(super) fn insert_rows(
+    conn: &mut Connection,
+    insert_sql: &str,
+    rows: &[GeneratedRow],
+    args: &Args,
+    generating: std::time::Duration,
+) -> Result<(), GenError> {
+    let started = std::time::Instant::now();
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare_cached(insert_sql)?;
+        let mut save_seed = tx.prepare_cached(
+            "INSERT OR REPLACE INTO seed_checkpoints (table_name, id, seed) VALUES (?, ?, ?)",
+        )?;
+        for row in rows {
+            if let Some(seed) = &row.checkpoint {
+                save_seed.execute(params![args.seed, row.step as i64, seed])?;
+            }
+            let id = args.id_layout.id(row.step);
+            insert
+                .execute(params![id, row.data, row.hash, "F", row.rng_state])
+                .map_err(|err| insert_error(err, &args.seed, id))?;
+        }
+    }
+    let inserted = started.elapsed();
+    tx.commit()?;
+    if let Some(timings) = &args.timings {
+        timings.record(generating + inserted, started.elapsed() - inserted);
+    }
+    Ok(())
+}
+
+/// Inserts the generated `batches` of the chain from step `start` on, in
+/// order, through `conn`. Committing and retrying on a full disk work as in
+/// `write_range`, except that a retried batch is split from the rows at hand
+/// instead of being generated again.
+///
+/// Returns the step after the last committed row and that row's `rng_state`,
+/// short of the end of `batches` if a shutdown was requested.
+pub(super) fn write_generated(
+    conn: &mut Connection,
+    insert_sql: &str,
+    batches: impl IntoIterator<Item = Result<GeneratedBatch, GenError>>,
+    start: usize,
+    args: &Args,
+    pb: &Progress,
+    stored_bytes: &AtomicU64,
+) -> Result<(usize, Option<Vec<u8>>), GenError> {
+    let mut batch_size = args.batch_size.max(1);
+    let journaled = args.journal != "OFF";
+    let (mut next, mut last_seed) = (start, None);
+    for batch in batches {
+        if args.cancel.is_cancelled() {
+            break;
+        }
+        let GeneratedBatch { rows, mut generating } = batch?;
+        let mut rows = rows.as_slice();
+        while !rows.is_empty() && !args.cancel.is_cancelled() {
+            let len = rows.len().min(batch_size);
+            let batch_started = std::time::Instant::now();
+            match insert_rows(conn, insert_sql, &rows[..len], args, generating) {
+                Ok(()) => {}
+                Err(GenError::DiskFull(_)) if journaled && len > 1 => {
+                    batch_size = len.div_ceil(2);
+                    log::warn!(
+                        "Disk full writing DB{} ids {}..{}, retrying in batches of {} rows",
+                        args.seed, next, next + len, batch_size
+                    );
+                    continue;
+                }
+                Err(err @ GenError::DiskFull(_)) if journaled => {
+                    log::error!("Disk full at id {} of DB{}, ids {}..{} were committed", next, args.seed, start, next);
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            }
+            generating = std::time::Duration::ZERO;
+            let batch_bytes: u64 = rows[..len].iter().map(|row| row.data.len() as u64).sum();
+            stored_bytes.fetch_add(batch_bytes, Ordering::Relaxed);
+            pb.inc(len as u64);
+            pb.committed(len as u64);
+            let row_overhead = args.hash_encoding.stored_len(args.hash_bytes) + args.hash_algo.output_len();
+            throttle_writes(args, batch_bytes + len as u64 * row_overhead as u64);
+            log_batch(args, next, next + len, batch_started);
+            last_seed = Some(rows[len - 1].rng_state.clone());
+            next += len;
+            rows = &rows[len..];
+            batch_size = (batch_size * 2).min(args.batch_size.max(1));
+        }
+    }
+    Ok((next, last_seed))
+}
+
+/// `--threads` without `--split_chain`: the calling thread walks the chain,
+/// which is sequential, and hands whole batches to a writer thread that
+/// inserts them through `conn` while the next batch is generated. The other threads
+/// compress the chunks of `--compress`. Rows are written in order, so an
+/// interrupted run leaves no gaps and resumes like a single-threaded one.
+///
+/// Returns the step after the last committed row, with `chunk_gen` at the
+/// seed of that step.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn fill_pipelined(
+    conn: &mut Connection,
+    insert_sql: &str,
+    chunk_gen: &mut ChunkGenerator,
+    range: std::ops::Range<usize>,
+    threads: usize,
+    args: &Args,
+    pb: &Progress,
+    stored_bytes: &AtomicU64,
+) -> Result<usize, GenError> {
+    let compressors = rayon::ThreadPoolBuilder::new()
+        .num_threads((threads - 1).max(1))
+        .build()
+        .map_err(io::Error::other)?;
+    let start_seed = chunk_gen.seed().to_vec();
+    let batch_size = generated_batch_size(args);
+    // One batch waits while the next is generated and another is inserted.
+    let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+    let (next, last_seed) = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || write_generated(conn, insert_sql, receiver, range.start, args, pb, stored_bytes));
+        let mut batch_start = range.start;
+        while batch_start < range.end && !args.cancel.is_cancelled() {
+            let batch_end = range.end.min(batch_start + batch_size);
+            let mut batch = generate_batch(chunk_gen, batch_start..batch_end, args);
+            let batch = compressors.install(|| compress_rows(&mut batch.rows, args)).map(|()| batch);
+            let failed = batch.is_err();
+            // The writer hung up after a failure of its own.
+            if sender.send(batch).is_err() || failed {
+                break;
+            }
+            batch_start = batch_end;
+        }
+        drop(sender);
+        writer.join().unwrap_or_else(|_| Err(io::Error::other("writer thread panicked").into()))
+    })?;
+    chunk_gen.set_seed(last_seed.unwrap_or(start_seed));
+    Ok(next)
+}
+
+/// `--threads` with `--split_chain`: splits `start_index..n_chunks` into one
+/// contiguous range per thread.
+///
+/// A first pass walks the chain once, without keeping the chunks, to find
+/// the starting seed of every range. Each worker then regenerates its own
+/// range and inserts it through a separate connection, generating every
+/// batch before it takes the write lock, which produces the same rows as a
+/// single-threaded run. The first pass costs about as much as generating the
+/// rows, so this only beats `fill_pipelined` when inserting is much slower
+/// than generating. `chunk_gen` is left at the end of the chain.
+pub(super) fn fill_parallel(
+    args: &Args,
+    insert_sql: &str,
+    chunk_gen: &mut ChunkGenerator,
+    start_index: usize,
+    threads: usize,
+    pb: &Progress,
+    stored_bytes: &AtomicU64,
+) -> Result<(), GenError> {
+    let total = args.n_chunks - start_index;
+    let mut ranges = Vec::with_capacity(threads);
+    let mut range_start = start_index;
+    for t in 0..threads {
+        let range_end = start_index + (total as u64 * (t + 1) as u64 / threads as u64) as usize;
+        ranges.push((range_start..range_end, chunk_gen.seed().to_vec()));
+        for _ in range_start..range_end {
+            if args.cancel.is_cancelled() {
+                return Ok(());
+            }
+            chunk_gen.next_hash_only();
+        }
+        range_start = range_end;
+    }
+
+    let batch_size = generated_batch_size(args);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = ranges.into_iter().map(|(range, seed)| {
+            scope.spawn(move || -> Result<(), GenError> {
+                let mut conn = open_db(&args.path, args.key.as_deref())?;
+                // Workers take turns holding the write lock.
+                conn.busy_timeout(std::time::Duration::from_secs(600))?;
+                configure_connection(&conn, args)?;
+                let mut chunk_gen = ChunkGenerator::new(seed, args.chunk_size, args.hash_algo.hasher())
+                    .with_rng(args.rng)
+                    .with_charset(args.charset);
+                let batches = range.clone().step_by(batch_size).map(|batch_start| {
+                    let mut batch = generate_batch(&mut chunk_gen, batch_start..range.end.min(batch_start + batch_size), args);
+                    compress_rows(&mut batch.rows, args).map(|()| batch)
+                });
+                write_generated(&mut conn, insert_sql, batches, range.start, args, pb, stored_bytes)?;
+                conn.close().map_err(|(_conn, err)| err)?;
+                Ok(())
+            })
+        }).collect();
+
+        workers.into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|_| Err(io::Error::other("worker thread panicked").into())))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<Result<(), GenError>>()
+    })
+}
+
+/// Finds where generation should continue: the id after the last row and the
+/// seed for that id.
+///
+/// The last row is regenerated from the previous row's seed before it is
+/// trusted. Rows that fail the check are deleted one by one until the head of
+/// the table verifies, unless `--strict_resume` is set, in which case the
+/// first failure is returned as an error.
+pub(super) fn resume_point(conn: &Connection, args: &Args, genesis: &[u8]) -> Result<(usize, Vec<u8>), GenError> {
+    let table = args.seed.as_str();
+    // `id` aliases the rowid, so MAX(id) is a single b-tree lookup no matter
+    // how large the table is.
+    let query_latest = format!(
+        "SELECT {} FROM DB{1} WHERE id = (SELECT MAX(id) FROM DB{1})",
+        StoredRow::COLUMNS, table
+    );
+
+    loop {
+        //log::info!("Executing query to fetch the latest RNG state.");
+        let head = {
+            let mut stmt = conn.prepare_cached(&query_latest)?;
+            let mut rows = stmt.query(params![])?;
+            match rows.next()? {
+                Some(row) => StoredRow::from_row(row)?.decoded(args.compress)?,
+                //log::warn!("No RNG state found in the database. Using default seed.");
+                None => return Ok((0, genesis.to_vec())),
+            }
+        };
+
+        // Only a row written by something else lies off the table's ids.
+        let step = args.id_layout.step(head.id).ok_or(GenError::Mismatch { id: head.id as usize, column: "id" })?;
+        let prev_seed = if step == 0 {
+            Ok(Some(genesis.to_vec()))
+        } else {
+            let prev = read_row(conn, table, args.id_layout.id(step - 1))?;
+            prev.map(|prev| row_seed(prev.id as usize, prev.rng_state, args.hash_algo)).transpose()
+        };
+        let column = match prev_seed {
+            Ok(Some(seed)) => {
+                let (chunk_data, chunk_hash) = ChunkGenerator::new(seed, args.chunk_size, args.hash_algo.hasher())
+                    .with_rng(args.rng)
+                    .with_charset(args.charset)
+                    .next();
+                head.mismatch(&chunk_data, &chunk_hash, args.hash_bytes)
+            }
+            Err(err) if args.strict_resume => return Err(err),
+            // Deleting the head brings the torn row before it up for the
+            // same check.
+            Err(err) => {
+                log::warn!("{}", err);
+                Some("rng_state")
+            }
+            Ok(None) => Some("id"),
+        };
+
+        match column {
+            // +1 because we want to start from the next index
+            None => return Ok((step + 1, head.rng_state)),
+            Some(column) if args.strict_resume => return Err(GenError::Mismatch { id: head.id as usize, column }),
+            Some(column) => {
+                log::warn!("Row {} failed verification ({} differs), deleting it before resuming", head.id, column);
+                conn.execute(&format!("DELETE FROM DB{} WHERE id = ?", table), params![head.id])?;
+            }
+        }
+    }
+}
+
+/// Stores the data of the hash-only rows of the table below `end`, e.g. of
+/// one generated with `--hash`, and returns how many rows were filled and the
+/// bytes of data stored. The chain is replayed from the seed checkpoint at or
+/// before the first empty row, and every chunk must match the stored hash and
+/// rng_state before its data is written; the hashes are left as they are.
+/// Each batch is one transaction, so an interrupted fill picks up at the
+/// first row that is still empty.
+pub(super) fn fill_data(conn: &mut Connection, args: &Args, end: usize, pb: &Progress) -> Result<(usize, u64), GenError> {
+    let table = args.seed.as_str();
+    let first_empty: Option<i64> = conn.query_row(
+        &format!("SELECT MIN(id) FROM DB{} WHERE length(data) = 0 AND flag != 'T' AND id < ?", table),
+        params![args.id_layout.id(end)],
+        |row| row.get(0),
+    )?;
+    let layout = args.id_layout;
+    let from = match first_empty {
+        Some(id) => layout.step(id).ok_or(GenError::Mismatch { id: id as usize, column: "id" })?,
+        None => return Ok((0, 0)),
+    };
+    log::info!("event=fill_data table=DB{} from_id={} to_id={}", table, from, end);
+    let mut chunk_gen = generator_at(conn, table, args.hash_algo, args.rng, args.charset, args.chunk_size, from)?;
+    let select = format!("SELECT {} FROM DB{} WHERE id >= ? AND id < ? ORDER BY id", StoredRow::COLUMNS, table);
+    let update = format!("UPDATE DB{} SET data = ? WHERE id = ?", table);
+    let (mut rows_filled, mut bytes) = (0, 0);
+    let mut batch_start = from;
+    while batch_start < end && !args.cancel.is_cancelled() {
+        let batch_end = (batch_start + args.batch_size).min(end);
+        pb.set_message(format!("filling in data from id {}", batch_start));
+        let batch_bytes_before = bytes;
+        let tx = conn.transaction()?;
+        {
+            let rows: Vec<StoredRow> = tx.prepare_cached(&select)?
+                .query_map(params![layout.id(batch_start), layout.id(batch_end)], StoredRow::from_row)?
+                .collect::<Result<_, _>>()?;
+            let mut update = tx.prepare_cached(&update)?;
+            let mut expected_id = batch_start;
+            for stored in rows {
+                if stored.id != layout.id(expected_id) {
+                    break;
+                }
+                let stored = stored.decoded(args.compress)?;
+                let (chunk_data, chunk_hash) = chunk_gen.next();
+                if let Some(column) = stored.mismatch(&chunk_data, &chunk_hash, args.hash_bytes) {
+                    return Err(GenError::Mismatch { id: stored.id as usize, column });
+                }
+                if stored.data.is_empty() && !stored.is_user_data() {
+                    let chunk_data = args.compress.encode(&chunk_data)?;
+                    bytes += chunk_data.len() as u64;
+                    update.execute(params![chunk_data.as_ref(), stored.id])?;
+                    rows_filled += 1;
+                }
+                expected_id += 1;
+            }
+            if expected_id < batch_end {
+                return Err(GenError::Mismatch { id: layout.id(expected_id) as usize, column: "id" });
+            }
+        }
+        tx.commit()?;
+        throttle_writes(args, bytes - batch_bytes_before);
+        log::debug!("event=batch_filled table=DB{} ids={}..{}", table, batch_start, batch_end);
+        batch_start = batch_end;
+    }
+    pb.set_message(String::new());
+    log::info!("event=data_filled table=DB{} rows={} next_id={}", table, rows_filled, batch_start);
+    Ok((rows_filled, bytes))
+}
+
+/// Resume point given by `--start_index`/`--start_seed`.
+///
+/// Unlike `resume_point` nothing is regenerated: the index must be the id
+/// after the last row and the seed that row's `rng_state`, which takes two
+/// primary key lookups. With `--force` the table is not read at all and rows
+/// from `index` on are dropped so the new ones can take their place.
+pub(super) fn explicit_start(
+    conn: &Connection,
+    args: &Args,
+    genesis: &[u8],
+    index: usize,
+    seed: &[u8],
+) -> Result<(usize, Vec<u8>), GenError> {
+    let table = args.seed.as_str();
+    if args.force {
+        conn.execute(&format!("DELETE FROM DB{} WHERE id >= ?", table), params![index as i64])?;
+        conn.execute(
+            "DELETE FROM seed_checkpoints WHERE table_name = ? AND id >= ?",
+            params![table, index as i64],
+        )?;
+        return Ok((index, seed.to_vec()));
+    }
+    let next_id: i64 = conn.query_row(
+        &format!("SELECT COALESCE(MAX(id) + 1, 0) FROM DB{}", table),
+        params![],
+        |row| row.get(0),
+    )?;
+    if next_id as usize != index {
+        return Err(GenError::InvalidArg {
+            name: "start_index",
+            value: index.to_string(),
+            reason: format!("the table continues at id {} (pass --force to use it anyway)", next_id),
+        });
+    }
+    let expected = seed_for_id(conn, table, index, genesis, args.hash_algo, args.id_layout)?;
+    if expected != seed {
+        return Err(GenError::InvalidArg {
+            name: "start_seed",
+            value: hex::encode(seed),
+            reason: format!("row {} is generated from {} (pass --force to use it anyway)", index, hex::encode(expected)),
+        });
+    }
+    Ok((index, seed.to_vec()))
+}
@@ -0,0 +1,212 @@
+//! Merkle manifests over the hash column, and proofs against them.
+
+use super::*;
+
+/// Metadata of the manifest of a table: its root, the rows it covers, the
+/// `MANIFEST_VERSION` it was built with, and the frontier of its tree with
+/// the last id in it, which the next run adds its rows to.
+pub(super) const MANIFEST_KEYS: [&str; 5] =
+    ["manifest_root", "manifest_rows", "manifest_version", "manifest_frontier", "manifest_last_id"];
+
+/// Streams the leaves of `DB{table}` with an id above `after`, in id order,
+/// into `push`, and returns the last id read. Miner-stored rows hold the
+/// hash of the user data instead, so their leaf is the chunk hash that
+/// `rng_state` keeps, and storing user data leaves the tree as it was.
+pub(super) fn stream_leaves(
+    conn: &Connection,
+    table: &str,
+    hash_bytes: usize,
+    after: Option<i64>,
+    mut push: impl FnMut(&[u8]),
+) -> Result<Option<i64>, GenError> {
+    let mut stmt = conn.prepare(&format!("SELECT id, hash, flag, rng_state FROM DB{} WHERE id > ? ORDER BY id", table))?;
+    let mut rows = stmt.query(params![after.unwrap_or(i64::MIN)])?;
+    let mut last_id = None;
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let flag: String = row.get(2)?;
+        if flag == "T" {
+            let rng_state: Vec<u8> = row.get(3)?;
+            push(rng_state.get(..hash_bytes).ok_or_else(|| GenError::CorruptRow {
+                id: id as usize,
+                reason: format!("its rng_state is {} bytes, shorter than a {} byte hash", rng_state.len(), hash_bytes),
+            })?);
+        } else {
+            push(&hex::decode(column_hash_hex(row, 1)?).map_err(|err| GenError::CorruptRow {
+                id: id as usize,
+                reason: format!("hash is not valid hex: {}", err),
+            })?);
+        }
+        last_id = Some(id);
+    }
+    Ok(last_id)
+}
+
+/// Version of the manifests `record_manifest` writes, recorded as
+/// `manifest_version`. Manifests without one were built from unprefixed
+/// leaves and are still checked as such.
+pub(super) const MANIFEST_VERSION: &str = "2";
+
+/// Whether the manifest of `DB{table}` was built with the leaf prefix.
+pub(super) fn manifest_prefixed(conn: &Connection, table: &str) -> Result<bool, GenError> {
+    match read_metadata(conn, table, "manifest_version")? {
+        Some(version) if version == MANIFEST_VERSION => Ok(true),
+        Some(version) => Err(GenError::InvalidMetadata { key: "manifest_version", value: version }),
+        None => Ok(false),
+    }
+}
+
+/// Merkle root over the leaves of `DB{table}` in `id` order, and the rows
+/// it covers, or `None` for an empty table. `prefixed` is false only to
+/// check a manifest recorded before the leaf prefix.
+pub(super) fn manifest_root(
+    conn: &Connection,
+    table: &str,
+    hash_algo: HashAlgo,
+    hash_bytes: usize,
+    prefixed: bool,
+) -> Result<Option<(Vec<u8>, usize)>, GenError> {
+    let hasher = hash_algo.hasher();
+    let mut tree = if prefixed { merkle::TreeBuilder::new(hasher.as_ref()) } else { merkle::TreeBuilder::unprefixed(hasher.as_ref()) };
+    stream_leaves(conn, table, hash_bytes, None, |leaf| tree.push(leaf))?;
+    let rows = tree.leaves();
+    Ok(tree.root().map(|root| (root, rows)))
+}
+
+/// The tree of the recorded manifest of `DB{table}` and the last id in it,
+/// if it has a frontier to go on from.
+pub(super) fn recorded_tree<'a>(
+    conn: &Connection,
+    table: &str,
+    hasher: &'a dyn crate::ChunkHasher,
+) -> Result<Option<(merkle::TreeBuilder<'a>, i64)>, GenError> {
+    let (Some(frontier), Some(last_id), Some(rows)) = (
+        read_metadata(conn, table, "manifest_frontier")?,
+        read_metadata(conn, table, "manifest_last_id")?,
+        read_metadata(conn, table, "manifest_rows")?,
+    ) else {
+        return Ok(None);
+    };
+    if !manifest_prefixed(conn, table)? {
+        return Ok(None);
+    }
+    let last_id = last_id.parse().map_err(|_| GenError::InvalidMetadata { key: "manifest_last_id", value: last_id })?;
+    let rows = rows.parse().map_err(|_| GenError::InvalidMetadata { key: "manifest_rows", value: rows })?;
+    let nodes = frontier.split(',').filter(|node| !node.is_empty()).map(hex::decode).collect::<Result<Vec<_>, _>>();
+    match nodes.ok().and_then(|nodes| merkle::TreeBuilder::resume(hasher, rows, nodes)) {
+        Some(tree) => Ok(Some((tree, last_id))),
+        None => Err(GenError::InvalidMetadata { key: "manifest_frontier", value: frontier }),
+    }
+}
+
+/// Records the manifest of `DB{table}` under `MANIFEST_KEYS`, replacing any
+/// older one, and returns the root. `appended_from` is the first id a run
+/// wrote if it only added rows at the end of the table: the rows after the
+/// older manifest are then added to its frontier, so only they are read.
+pub(super) fn record_manifest(
+    conn: &Connection,
+    table: &str,
+    hash_algo: HashAlgo,
+    hash_bytes: usize,
+    appended_from: Option<i64>,
+) -> Result<Option<Vec<u8>>, GenError> {
+    let hasher = hash_algo.hasher();
+    let recorded = match appended_from {
+        Some(first_id) => recorded_tree(conn, table, hasher.as_ref())?.filter(|(_, last_id)| *last_id < first_id),
+        None => None,
+    };
+    let (mut tree, after) = match recorded {
+        Some((tree, last_id)) => (tree, Some(last_id)),
+        None => (merkle::TreeBuilder::new(hasher.as_ref()), None),
+    };
+    let resumed_rows = tree.leaves();
+    let last_id = stream_leaves(conn, table, hash_bytes, after, |leaf| tree.push(leaf))?.or(after);
+    for key in MANIFEST_KEYS {
+        remove_metadata(conn, table, key)?;
+    }
+    let Some(last_id) = last_id else {
+        return Ok(None);
+    };
+    let rows = tree.leaves();
+    let frontier = tree.frontier().iter().map(hex::encode).collect::<Vec<_>>().join(",");
+    let root = tree.root().expect("a tree with rows has a root");
+    write_metadata(conn, table, "manifest_root", &hex::encode(&root))?;
+    write_metadata(conn, table, "manifest_rows", &rows.to_string())?;
+    write_metadata(conn, table, "manifest_version", MANIFEST_VERSION)?;
+    write_metadata(conn, table, "manifest_frontier", &frontier)?;
+    write_metadata(conn, table, "manifest_last_id", &last_id.to_string())?;
+    log::info!(
+        "event=manifest_recorded table=DB{} rows={} root={} rows_read={}",
+        table, rows, hex::encode(&root), rows - resumed_rows
+    );
+    Ok(Some(root))
+}
+
+/// Recomputes the manifest of a table from its hash column and compares it
+/// with the one recorded after generation. Reads every row once but
+/// regenerates nothing, so it is much cheaper than `verify`; it catches a
+/// damaged hash column, not damaged data.
+pub(super) fn verify_manifest(args: MerkleArgs) -> Result<(), GenError> {
+    let conn = open_db(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    check_table_schema(&conn, table)?;
+    let hash_algo = stored_hash_algo(&conn, table)?;
+    let hash_bytes = stored_hash_bytes(&conn, table, hash_algo)?;
+    let recorded_root = read_metadata(&conn, table, "manifest_root")?
+        .ok_or_else(|| io::Error::other(format!("no manifest recorded for DB{}, a generation run records one", table)))?;
+    let recorded_rows = match read_metadata(&conn, table, "manifest_rows")? {
+        Some(rows) => rows.parse().map_err(|_| GenError::InvalidMetadata { key: "manifest_rows", value: rows })?,
+        None => return Err(GenError::InvalidMetadata { key: "manifest_rows", value: String::new() }),
+    };
+    let prefixed = manifest_prefixed(&conn, table)?;
+    let (computed_root, computed_rows) = manifest_root(&conn, table, hash_algo, hash_bytes, prefixed)?
+        .map_or((String::new(), 0), |(root, rows)| (hex::encode(root), rows));
+    if (recorded_rows, &recorded_root) != (computed_rows, &computed_root) {
+        return Err(GenError::ManifestMismatch {
+            table: table.to_string(),
+            recorded: (recorded_rows, recorded_root),
+            computed: (computed_rows, computed_root),
+        });
+    }
+    println!("Manifest of DB{} matches: root {} over {} rows", table, computed_root, computed_rows);
+    Ok(())
+}
+
+pub(super) fn merkle_root(args: MerkleArgs) -> Result<(), GenError> {
+    let conn = open_db(&args.path, args.key.as_deref())?;
+    let hash_algo = stored_hash_algo(&conn, &args.seed)?;
+    let hash_bytes = stored_hash_bytes(&conn, &args.seed, hash_algo)?;
+    let hasher = hash_algo.hasher();
+    let mut tree = merkle::TreeBuilder::new(hasher.as_ref());
+    stream_leaves(&conn, &args.seed, hash_bytes, None, |leaf| tree.push(leaf))?;
+    let count = tree.leaves();
+    match tree.root() {
+        Some(root) => println!("{}", hex::encode(root)),
+        None => log::warn!("Table DB{} is empty", args.seed),
+    }
+    log::info!("Computed Merkle root over {} chunks", count);
+    Ok(())
+}
+
+pub(super) fn prove(args: MerkleArgs) -> Result<(), GenError> {
+    let id = args.id.unwrap_or_default();
+    let conn = open_db(&args.path, args.key.as_deref())?;
+    let hash_algo = stored_hash_algo(&conn, &args.seed)?;
+    let hash_bytes = stored_hash_bytes(&conn, &args.seed, hash_algo)?;
+    let hasher = hash_algo.hasher();
+    let mut tree = merkle::TreeBuilder::proving(hasher.as_ref(), id);
+    stream_leaves(&conn, &args.seed, hash_bytes, None, |leaf| tree.push(leaf))?;
+    let proof = tree.proof()
+        .ok_or_else(|| GenError::InvalidArg { name: "id", value: id.to_string(), reason: "no such row".to_string() })?;
+    println!("{}", proof);
+    Ok(())
+}
+
+pub(super) fn verify_proof(args: VerifyProofArgs) -> Result<(), GenError> {
+    let hasher = args.hash_algo.hasher();
+    if !merkle::verify(hasher.as_ref(), &args.leaf, &args.proof, &args.root) {
+        return Err(GenError::InvalidProof);
+    }
+    println!("Proof is valid");
+    Ok(())
+}
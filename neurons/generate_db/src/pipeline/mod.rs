@@ -0,0 +1,45 @@
+//! Generation runs and the other subcommands of the `storer_db_project`
+//! binary, which is a thin wrapper around `run_cli`.
+
+use std::{io, process};
+use std::io::IsTerminal;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use rusqlite::{Connection, OpenFlags, Row, params};
+use rusqlite::types::{Value, ValueRef};
+use hdrhistogram::Histogram;
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use rayon::prelude::*;
+use crate::{BUSY_TIMEOUT, Charset, ChunkGenerator, GenError, HashAlgo, INCOMPRESSIBLE, PartitionId, RngAlgo, merkle};
+
+use crate::config;
+use crate::rawfile::{self, FOOTER_LEN, RawMeta, RawStore};
+
+mod api;
+mod args;
+mod cli;
+mod db;
+mod generate;
+mod manifest;
+mod progress;
+mod subcommands;
+mod verify;
+
+use args::*;
+use cli::*;
+use db::*;
+use generate::*;
+use manifest::*;
+use progress::*;
+use subcommands::*;
+use verify::*;
+
+pub use api::{CancelToken, GenerateConfig, Summary, TableSummary, generate_blocking};
+pub use cli::run_cli;
+pub use progress::ProgressUpdate;
+
+/// Set along with cancelling the run once `--max_runtime_secs` has elapsed.
+static OUT_OF_TIME: AtomicBool = AtomicBool::new(false);
@@ -0,0 +1,340 @@
+//! Progress bars, `--status_file` and the listeners of library runs.
+
+use super::*;
+
+/// Row and byte progress of a generation run.
+#[derive(Clone)]
+pub(super) struct Progress {
+    pub(super) rows: Arc<EtaBar>,
+    pub(super) bytes: ProgressBar,
+    pub(super) row_bytes: u64,
+    /// Rows of the single table being generated, under `generate-all`.
+    pub(super) table: Option<Arc<EtaBar>>,
+    pub(super) status: Option<Arc<StatusFile>>,
+    /// Told of every committed batch, by a `generate_blocking` caller.
+    pub(super) listener: Option<ProgressListener>,
+    /// Rows of the table being generated, as reported to `listener`.
+    pub(super) listened: Option<Arc<ListenedTable>>,
+}
+
+pub(super) type ProgressListener = Arc<dyn Fn(ProgressUpdate) + Send + Sync>;
+
+/// A table of a run whose progress goes to a `ProgressListener`.
+pub(super) struct ListenedTable {
+    pub(super) table: String,
+    pub(super) total: u64,
+    pub(super) rows: AtomicU64,
+}
+
+/// Rows committed to one table of a `generate` run so far, sent after every
+/// batch and once when a resumed table is opened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressUpdate {
+    /// `DB{seed}`, which tells the partitions of a run apart.
+    pub table: String,
+    pub rows: u64,
+    /// Rows the table will hold when done, its share of `--n`.
+    pub total: u64,
+}
+
+impl Progress {
+    /// Adds a row count bar and a bar of the bytes stored per row (the chunk,
+    /// if data is stored, plus its hex hash) to `multi`.
+    pub(super) fn new(multi: &MultiProgress, n_chunks: u64, row_bytes: u64) -> Self {
+        let rows = multi.add(ProgressBar::new(n_chunks));
+        rows.set_style(ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .progress_chars("#>-"));
+        let bytes = multi.add(ProgressBar::new(n_chunks * row_bytes));
+        bytes.set_style(ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})")
+            .progress_chars("#>-"));
+        Progress { rows: Arc::new(EtaBar::new(rows)), bytes, row_bytes, table: None, status: None, listener: None, listened: None }
+    }
+
+    /// Adds an idle bar for one `generate-all` job to `multi`.
+    pub(super) fn job_bar(multi: &MultiProgress) -> ProgressBar {
+        let bar = multi.add(ProgressBar::new(0));
+        bar.set_style(ProgressStyle::default_bar()
+            .template("  {prefix:>12} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .progress_chars("#>-"));
+        bar
+    }
+
+    /// Progress of `table`, shown on `bar` as well as in the totals.
+    pub(super) fn for_table(&self, bar: &ProgressBar, table: &str, n_chunks: u64) -> Progress {
+        bar.reset();
+        bar.set_message("");
+        bar.set_length(n_chunks);
+        bar.set_prefix(table.to_string());
+        Progress {
+            rows: self.rows.clone(),
+            bytes: self.bytes.clone(),
+            row_bytes: self.row_bytes,
+            table: Some(Arc::new(EtaBar::new(bar.clone()))),
+            status: None,
+            listener: self.listener.clone(),
+            listened: None,
+        }
+    }
+
+    /// This progress, also reported to `status` as batches are committed.
+    pub(super) fn with_status(&self, status: StatusFile) -> Progress {
+        Progress { status: Some(Arc::new(status)), ..self.clone() }
+    }
+
+    /// This progress, with the rows of `table` reported to the listener if
+    /// there is one.
+    pub(super) fn listening(&self, table: &str, total: u64) -> Progress {
+        let listened = self.listener.as_ref().map(|_| {
+            Arc::new(ListenedTable { table: table.to_string(), total, rows: AtomicU64::new(0) })
+        });
+        Progress { listened, ..self.clone() }
+    }
+
+    /// Tells the listener that the table holds `rows` rows.
+    pub(super) fn notify(&self, rows: u64) {
+        if let (Some(listener), Some(listened)) = (&self.listener, &self.listened) {
+            listener(ProgressUpdate { table: listened.table.clone(), rows, total: listened.total });
+        }
+    }
+
+    /// Counts the `rows` a resumed table already holds. The rate behind the
+    /// ETA is restarted from here, so rows written by an earlier run don't
+    /// count as this run's work and the ETA covers only what is left.
+    pub(super) fn resume_at(&self, rows: u64) {
+        self.inc(rows);
+        self.rows.restart();
+        self.bytes.reset_eta();
+        if let Some(table) = &self.table {
+            table.restart();
+        }
+        let bar = self.table.as_ref().unwrap_or(&self.rows);
+        if rows > 0 && rows < bar.bar.length() {
+            bar.set_note(format!("resuming from id {}", rows));
+        }
+        if let Some(status) = &self.status {
+            status.resume_at(rows);
+        }
+        if let Some(listened) = &self.listened {
+            listened.rows.store(rows, Ordering::Relaxed);
+            self.notify(rows);
+        }
+    }
+
+    /// Shows `message` beside the row count of the table.
+    pub(super) fn set_message(&self, message: String) {
+        self.table.as_ref().unwrap_or(&self.rows).set_note(message);
+    }
+
+    /// Reports a batch of `rows` as committed.
+    pub(super) fn committed(&self, rows: u64) {
+        self.rows.committed();
+        if let Some(table) = &self.table {
+            table.committed();
+        }
+        if let Some(status) = &self.status {
+            status.committed(rows);
+        }
+        if let Some(listened) = &self.listened {
+            self.notify(listened.rows.fetch_add(rows, Ordering::Relaxed) + rows);
+        }
+    }
+
+    pub(super) fn inc(&self, rows: u64) {
+        self.rows.bar.inc(rows);
+        self.bytes.inc(rows * self.row_bytes);
+        if let Some(table) = &self.table {
+            table.bar.inc(rows);
+        }
+    }
+
+    /// Stops the bars where they are, which is short of the end after an
+    /// error or an interruption.
+    pub(super) fn finish(&self) {
+        self.rows.bar.finish_at_current_pos();
+        self.bytes.finish_at_current_pos();
+    }
+}
+
+/// A row bar that shows the time left at the rate of its last `ETA_WINDOW`
+/// before its message, the estimate `--status_file` reports as well.
+/// indicatif's own `{eta}` follows the last few increments however far apart
+/// they were, and the two would disagree.
+pub(super) struct EtaBar {
+    pub(super) bar: ProgressBar,
+    pub(super) state: Mutex<EtaState>,
+}
+
+pub(super) struct EtaState {
+    pub(super) window: RateWindow,
+    /// Shown after the ETA, e.g. the id a resumed run started from.
+    pub(super) note: String,
+}
+
+impl EtaBar {
+    pub(super) fn new(bar: ProgressBar) -> Self {
+        let window = RateWindow::new(bar.position());
+        EtaBar { bar, state: Mutex::new(EtaState { window, note: String::new() }) }
+    }
+
+    pub(super) fn state(&self) -> std::sync::MutexGuard<'_, EtaState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Starts the window over from the current position.
+    pub(super) fn restart(&self) {
+        let mut state = self.state();
+        state.window = RateWindow::new(self.bar.position());
+        self.show(&state);
+    }
+
+    /// Adds the position the bar is at after a committed batch to the window.
+    pub(super) fn committed(&self) {
+        let mut state = self.state();
+        state.window.record(self.bar.position());
+        self.show(&state);
+    }
+
+    pub(super) fn set_note(&self, note: String) {
+        let mut state = self.state();
+        state.note = note;
+        self.show(&state);
+    }
+
+    pub(super) fn show(&self, state: &EtaState) {
+        let message = match state.window.eta(self.bar.length().saturating_sub(self.bar.position())) {
+            Some(eta) => format!("({}) {}", HumanDuration(eta), state.note),
+            None => state.note.clone(),
+        };
+        self.bar.set_message(message.trim_end().to_string());
+    }
+}
+
+/// Machine-readable heartbeat of one table for `--status_file`.
+pub(super) struct StatusFile {
+    pub(super) path: String,
+    pub(super) table: String,
+    pub(super) total: u64,
+    pub(super) row_bytes: u64,
+    pub(super) state: Mutex<StatusState>,
+}
+
+pub(super) struct StatusState {
+    pub(super) rows: u64,
+    /// Restarted after the rows a run resumed from, so the ETA only
+    /// reflects the rate of this run.
+    pub(super) window: RateWindow,
+}
+
+/// How far back the rate behind `eta_s` looks.
+pub(super) const ETA_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Row counts committed over the last `ETA_WINDOW`. Writes slow down as the
+/// file grows and auto_vacuum has more pointer pages to maintain, so the
+/// average over the whole run would promise too early an end late in a
+/// long run.
+pub(super) struct RateWindow {
+    /// Oldest first, with one sample at or before the start of the window.
+    pub(super) samples: std::collections::VecDeque<(std::time::Instant, u64)>,
+}
+
+impl RateWindow {
+    pub(super) fn new(rows: u64) -> Self {
+        RateWindow { samples: [(std::time::Instant::now(), rows)].into() }
+    }
+
+    pub(super) fn record(&mut self, rows: u64) {
+        self.record_at(std::time::Instant::now(), rows);
+    }
+
+    pub(super) fn record_at(&mut self, now: std::time::Instant, rows: u64) {
+        while self.samples.get(1).is_some_and(|(at, _)| now.duration_since(*at) >= ETA_WINDOW) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, rows));
+    }
+
+    /// Rows per second over the window, `None` until a batch was committed.
+    pub(super) fn per_sec(&self) -> Option<f64> {
+        let (&(first_at, first), &(last_at, last)) = (self.samples.front()?, self.samples.back()?);
+        let secs = last_at.duration_since(first_at).as_secs_f64();
+        (last > first && secs > 0.0).then(|| (last - first) as f64 / secs)
+    }
+
+    /// Time the `left` rows take at the rate over the window.
+    pub(super) fn eta(&self, left: u64) -> Option<std::time::Duration> {
+        self.per_sec().map(|per_sec| std::time::Duration::from_secs_f64(left as f64 / per_sec))
+    }
+}
+
+impl StatusFile {
+    pub(super) fn new(path: &str, table: &str, total: u64, row_bytes: u64) -> Self {
+        StatusFile {
+            path: path.to_string(),
+            table: table.to_string(),
+            total,
+            row_bytes,
+            state: Mutex::new(StatusState { rows: 0, window: RateWindow::new(0) }),
+        }
+    }
+
+    pub(super) fn resume_at(&self, rows: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        *state = StatusState { rows, window: RateWindow::new(rows) };
+        self.write(&state);
+    }
+
+    pub(super) fn committed(&self, rows: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.rows += rows;
+        let rows = state.rows;
+        state.window.record(rows);
+        self.write(&state);
+    }
+
+    /// Replaces the file through a rename, so readers never see a partial
+    /// write. A failed write is only logged; it must not stop the run.
+    pub(super) fn write(&self, state: &StatusState) {
+        let rows = state.rows;
+        let eta_s = if rows >= self.total {
+            Some(0)
+        } else {
+            state.window.per_sec().map(|per_sec| ((self.total - rows) as f64 / per_sec).ceil() as u64)
+        };
+        let status = serde_json::json!({
+            "table": self.table,
+            "id": rows,
+            "total": self.total,
+            "bytes": rows * self.row_bytes,
+            "eta_s": eta_s,
+        });
+        let tmp = format!("{}.tmp", self.path);
+        if let Err(err) = std::fs::write(&tmp, status.to_string()).and_then(|()| std::fs::rename(&tmp, &self.path)) {
+            log::warn!("Could not write the status file {}: {}", self.path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn eta_follows_a_slowdown() {
+        let start = Instant::now();
+        let mut window = RateWindow { samples: [(start, 0)].into() };
+        // 1000 rows/s for 100 s, then the disk slows down to 100 rows/s.
+        for sec in 1..=100 {
+            window.record_at(start + Duration::from_secs(sec), sec * 1000);
+        }
+        for sec in 1..=60 {
+            window.record_at(start + Duration::from_secs(100 + sec), 100_000 + sec * 100);
+        }
+        // The average over the run, 662 rows/s, would promise the last
+        // 94000 rows in under 2.5 minutes.
+        assert_eq!(window.per_sec(), Some(100.0));
+        assert_eq!(window.eta(94_000), Some(Duration::from_secs(940)));
+    }
+}
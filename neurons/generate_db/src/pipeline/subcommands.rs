@@ -0,0 +1,1332 @@
+//! The subcommands that read, repair or compare generated tables.
+
+use super::*;
+
+pub(super) fn fetch(args: RowArgs) -> Result<(), GenError> {
+    let (stored, charset) = match args.backend {
+        Backend::Sqlite => {
+            let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+            let codec = stored_codec(&conn, &args.seed)?;
+            let stored = read_row(&conn, &args.seed, args.id as i64)?.map(|row| row.decoded(codec)).transpose()?;
+            (stored, stored_charset(&conn, &args.seed)?)
+        }
+        Backend::RawFile => {
+            let (meta, store) = open_rawfile(&args.path, &args.seed)?;
+            (raw_row(&store, args.id)?, meta.charset)
+        }
+    };
+    let stored = stored
+        .ok_or_else(|| GenError::InvalidArg { name: "id", value: args.id.to_string(), reason: "no such row".to_string() })?;
+    println!("{}", serde_json::json!({
+        "id": stored.id,
+        "data": data_text(&stored.data, charset),
+        "hash": stored.hash,
+    }));
+    Ok(())
+}
+
+/// The data column as JSON text: the chunk characters, or hex for `bytes`
+/// tables, whose chunks aren't text.
+pub(super) fn data_text(data: &[u8], charset: Charset) -> String {
+    match charset {
+        Charset::Bytes => hex::encode(data),
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+/// Writes every stored chunk to stdout in id order. Rows are streamed one at a
+/// time, so tables larger than memory can be piped into other tools.
+pub(super) fn export(args: ExportArgs) -> Result<(), GenError> {
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    match export_rows(&args, &mut out).and_then(|()| Ok(io::Write::flush(&mut out)?)) {
+        // The reader went away, e.g. `export | head`; that's not our failure.
+        Err(GenError::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other,
+    }
+}
+
+pub(super) fn export_rows(args: &ExportArgs, out: &mut impl io::Write) -> Result<(), GenError> {
+    let raw = args.format == ExportFormat::Raw;
+    let mut count = 0;
+    let footer_meta = match args.backend {
+        Backend::Sqlite => {
+            let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+            let codec = stored_codec(&conn, &args.seed)?;
+            let charset = stored_charset(&conn, &args.seed)?;
+            let meta = if raw { Some(raw_export_meta(&conn, &args.seed)?) } else { None };
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM DB{} ORDER BY id", StoredRow::COLUMNS, args.seed))?;
+            let mut rows = stmt.query(params![])?;
+            while let Some(row) = rows.next()? {
+                write_exported(out, args.format, charset, &StoredRow::from_row(row)?.decoded(codec)?)?;
+                count += 1;
+            }
+            meta
+        }
+        Backend::RawFile => {
+            let (meta, store) = open_rawfile(&args.path, &args.seed)?;
+            for id in 0..store.len() as usize {
+                if let Some(row) = raw_row(&store, id)? {
+                    write_exported(out, args.format, meta.charset, &row)?;
+                    count += 1;
+                }
+            }
+            raw.then_some(meta)
+        }
+    };
+    if let Some(meta) = footer_meta {
+        out.write_all(&meta.footer(count)?)?;
+    }
+    Ok(())
+}
+
+/// Settings of a table for the footer of its raw export. The records of a
+/// raw export are all the same size and replay one chain, so every row has
+/// to hold a generated chunk.
+pub(super) fn raw_export_meta(conn: &Connection, table: &str) -> Result<RawMeta, GenError> {
+    let unfit = |reason: String| GenError::InvalidArg { name: "format", value: "raw".to_string(), reason };
+    let odd_row: Option<i64> = conn.query_row(
+        &format!("SELECT MIN(id) FROM DB{} WHERE flag = 'T' OR length(data) = 0", table),
+        params![],
+        |row| row.get(0),
+    )?;
+    if let Some(id) = odd_row {
+        return Err(unfit(format!("row {} of DB{} holds no generated chunk, export it as ndjson instead", id, table)));
+    }
+    let hash_algo = stored_hash_algo(conn, table)?;
+    let chunk_size = stored_chunk_size(conn, table)?
+        .ok_or_else(|| unfit(format!("the chunk size of DB{} is unknown", table)))?;
+    let genesis_seed = stored_genesis_seed(conn, table, hash_algo)?
+        .unwrap_or_else(|| ChunkGenerator::genesis_seed(hash_algo.hasher().as_ref(), chunk_size));
+    Ok(RawMeta {
+        table: table.to_string(),
+        hash_algo,
+        rng: stored_rng(conn, table)?,
+        charset: stored_charset(conn, table)?,
+        chunk_size,
+        genesis_seed,
+    })
+}
+
+/// Rebuilds a table from a file written by `export --format raw`. The table
+/// is written like `merge` writes one, by a generation run with the footer's
+/// settings, which gives it the metadata, checkpoints and manifest of a
+/// generated table, but the run inserts the records of the file instead of
+/// generating chunks, each checked against its chain by `import_range`.
+pub(super) fn import(args: ImportArgs) -> Result<(), GenError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let invalid = |reason: String| GenError::InvalidArg { name: "from", value: args.from.clone(), reason };
+    let mut file = std::fs::File::open(&args.from)?;
+    let len = file.metadata()?.len();
+    if len < FOOTER_LEN as u64 {
+        return Err(invalid("too short to hold a raw export footer".to_string()));
+    }
+    let mut footer = [0u8; FOOTER_LEN];
+    file.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    file.read_exact(&mut footer)?;
+    let (meta, count) = RawMeta::from_footer(&footer)?;
+    if count.checked_mul(meta.record_len()).and_then(|bytes| bytes.checked_add(FOOTER_LEN as u64)) != Some(len) {
+        return Err(invalid(format!(
+            "holds {} bytes, but its footer describes {} chunks of {} bytes",
+            len - FOOTER_LEN as u64, count, meta.record_len()
+        )));
+    }
+    if count == 0 {
+        return Err(invalid("holds no chunks".to_string()));
+    }
+    let table = check_table_seed(args.seed.as_deref().unwrap_or(&meta.table))?;
+
+    let mut argv = vec!["import".to_string(), "--path".to_string(), args.db.clone(), "--seed".to_string(), table.clone()];
+    argv.extend(["--n".to_string(), count.to_string(), "--size".to_string(), meta.chunk_size.to_string()]);
+    argv.extend(["--hash_algo".to_string(), meta.hash_algo.name().to_string()]);
+    argv.extend(["--rng".to_string(), meta.rng.name().to_string(), "--charset".to_string(), meta.charset.name().to_string()]);
+    argv.extend(["--genesis_seed".to_string(), hex::encode(&meta.genesis_seed)]);
+    if args.json {
+        argv.push("--json".to_string());
+    }
+    let matches = App::new("import")
+        .arg(db_path_arg())
+        .arg(Arg::with_name("n").long("n").takes_value(true))
+        .args(&generation_args())
+        .get_matches_from_safe(argv)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    let generation = Args {
+        key: args.key.clone(),
+        import_from: Some(args.from.clone()),
+        ..Args::from_matches(&matches)?
+    };
+    let cancel = generation.cancel.clone();
+    run(generation)?;
+    if !args.json && !cancel.is_cancelled() {
+        println!("Imported {} chunks of {} into DB{}", count, args.from, table);
+    }
+    Ok(())
+}
+
+/// Inserts the records of the raw export `from` for `steps`, checking each
+/// against the chunk its seed chain regenerates before its batch is written,
+/// so the file is read once. A record that differs fails the run with
+/// `Mismatch`; the batches before it stay committed. Works like
+/// `fill_pipelined` on one thread otherwise, leaving `chunk_gen` at the seed
+/// of the returned step.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn import_range(
+    conn: &mut Connection,
+    insert_sql: &str,
+    chunk_gen: &mut ChunkGenerator,
+    steps: std::ops::Range<usize>,
+    from: &str,
+    args: &Args,
+    pb: &Progress,
+    stored_bytes: &AtomicU64,
+) -> Result<usize, GenError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let record_len = args.chunk_size + 2 * args.hash_algo.output_len();
+    let mut file = std::fs::File::open(from)?;
+    file.seek(SeekFrom::Start((steps.start * record_len) as u64))?;
+    let mut reader = io::BufReader::new(file);
+    let mut record = vec![0u8; record_len];
+    let start_seed = chunk_gen.seed().to_vec();
+    let batch_size = generated_batch_size(args);
+    let batches = steps.clone().step_by(batch_size).map(|batch_start| {
+        let mut batch = generate_batch(chunk_gen, batch_start..steps.end.min(batch_start + batch_size), args);
+        for row in &batch.rows {
+            reader.read_exact(&mut record)?;
+            if record != row.data {
+                let column = if record[..args.chunk_size] == row.data[..args.chunk_size] { "hash" } else { "data" };
+                return Err(GenError::Mismatch { id: args.id_layout.id(row.step) as usize, column });
+            }
+        }
+        compress_rows(&mut batch.rows, args)?;
+        Ok(batch)
+    });
+    let (next, last_seed) = write_generated(conn, insert_sql, batches, steps.start, args, pb, stored_bytes)?;
+    chunk_gen.set_seed(last_seed.unwrap_or(start_seed));
+    Ok(next)
+}
+
+pub(super) fn write_exported(out: &mut impl io::Write, format: ExportFormat, charset: Charset, row: &StoredRow) -> io::Result<()> {
+    match format {
+        ExportFormat::Raw => out.write_all(&row.data),
+        ExportFormat::Hex => writeln!(out, "{}", hex::encode(&row.data)),
+        ExportFormat::Ndjson => writeln!(out, "{}", serde_json::json!({
+            "id": row.id,
+            "hash": row.hash,
+            "data": data_text(&row.data, charset),
+        })),
+    }
+}
+
+/// Generator positioned at chunk `id` of the chain of `table`, which is row
+/// `id` of tables with sequential ids. The chain is replayed from the
+/// nearest seed checkpoint at or below `id`, or from genesis for tables
+/// generated without checkpoints.
+pub(super) fn generator_at(
+    conn: &Connection,
+    table: &str,
+    hash_algo: HashAlgo,
+    rng: RngAlgo,
+    charset: Charset,
+    chunk_size: usize,
+    id: usize,
+) -> Result<ChunkGenerator, GenError> {
+    let (start, seed) = checkpoint_at(conn, table, hash_algo, chunk_size, id)?;
+    let mut chunk_gen = ChunkGenerator::new(seed, chunk_size, hash_algo.hasher())
+        .with_rng(rng)
+        .with_charset(charset);
+    for _ in start..id {
+        chunk_gen.next_hash_only();
+    }
+    Ok(chunk_gen)
+}
+
+/// The nearest seed checkpoint at or below `id`, falling back to row 0 and
+/// the genesis seed. A checkpoint of the wrong length can only be torn, so it
+/// is passed over for the one before.
+pub(super) fn checkpoint_at(
+    conn: &Connection,
+    table: &str,
+    hash_algo: HashAlgo,
+    chunk_size: usize,
+    mut id: usize,
+) -> Result<(usize, Vec<u8>), GenError> {
+    loop {
+        match nearest_checkpoint(conn, table, id)? {
+            Some((start, seed)) if seed.len() == hash_algo.output_len() => return Ok((start, seed)),
+            Some((start, _)) if start > 0 => id = start - 1,
+            _ => break,
+        }
+    }
+    let genesis = stored_genesis_seed(conn, table, hash_algo)?
+        .unwrap_or_else(|| ChunkGenerator::genesis_seed(hash_algo.hasher().as_ref(), chunk_size));
+    Ok((0, genesis))
+}
+
+/// Refuses to run `command`, which reads ids as chain steps, on a table
+/// generated with `--id_stride` or `--id_offset`.
+pub(super) fn require_sequential_ids(conn: &Connection, table: &str, command: &str) -> Result<(), GenError> {
+    let layout = IdLayout::stored(conn, table)?;
+    if layout != IdLayout::SEQUENTIAL {
+        return Err(io::Error::other(format!(
+            "`{}` only supports tables with sequential ids, DB{} stores its chunks at ids {} + i * {}",
+            command, table, layout.offset, layout.stride
+        )).into());
+    }
+    Ok(())
+}
+
+/// Drops the rows from the first corrupt or missing one on and regenerates
+/// them up to the previous end of the table, e.g. after a crash left torn rows
+/// behind. Rows are checked from the last seed checkpoint on; if the row at a
+/// checkpoint is already bad, the checkpoint itself may be torn, so the check
+/// starts over from the one before. Miner-stored rows past the first bad row
+/// are replaced by generated chunks like the others.
+pub(super) fn repair(args: RepairArgs) -> Result<(), GenError> {
+    let mut conn = open_db(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    check_table_schema(&conn, table)?;
+    require_sequential_ids(&conn, table, "repair")?;
+    let max_id: Option<i64> = conn.query_row(&format!("SELECT MAX(id) FROM DB{}", table), params![], |row| row.get(0))?;
+    let end = match max_id {
+        Some(max_id) => max_id as usize + 1,
+        None => {
+            println!("DB{} is empty, nothing to repair", table);
+            return Ok(());
+        }
+    };
+    let hash_algo = stored_hash_algo(&conn, table)?;
+    let codec = stored_codec(&conn, table)?;
+    let rng = stored_rng(&conn, table)?;
+    let charset = stored_charset(&conn, table)?;
+    let hash_bytes = stored_hash_bytes(&conn, table, hash_algo)?;
+    let hash_encoding = stored_hash_encoding(&conn, table)?;
+    let chunk_size = table_chunk_size(&conn, table, args.chunk_size)?;
+    let hash_only = first_generated_row(&conn, table, codec)?.is_some_and(|row| row.data.is_empty());
+
+    let mut from = end - 1;
+    let (start, seed, bad_id) = loop {
+        let (start, seed) = checkpoint_at(&conn, table, hash_algo, chunk_size, from)?;
+        let chunk_gen = ChunkGenerator::new(seed.clone(), chunk_size, hash_algo.hasher())
+            .with_rng(rng)
+            .with_charset(charset);
+        match first_bad_row(&conn, table, codec, hash_bytes, chunk_gen, start)? {
+            Some(id) if id == start && start > 0 => from = start - 1,
+            bad => break (start, seed, bad.unwrap_or(end)),
+        }
+    };
+
+    // Replay the chain from the checkpoint the rows were checked from, fixing
+    // any torn checkpoint on the way, and regenerate the rows from the bad one.
+    create_checkpoint_table(&conn)?;
+    let tx = conn.transaction()?;
+    tx.execute(&format!("DELETE FROM DB{} WHERE id >= ?", table), params![bad_id as i64])?;
+    let mut checkpoints_fixed = 0;
+    {
+        let mut insert = tx.prepare(&format!(
+            "INSERT OR ABORT INTO DB{} (id, data, hash, flag, rng_state) VALUES (?, ?, ?, ?, ?)",
+            table
+        ))?;
+        let mut fix_checkpoint = tx.prepare(
+            "UPDATE seed_checkpoints SET seed = ? WHERE table_name = ? AND id = ? AND seed != ?",
+        )?;
+        let mut chunk_gen = ChunkGenerator::new(seed, chunk_size, hash_algo.hasher())
+            .with_rng(rng)
+            .with_charset(charset);
+        for id in start..end {
+            checkpoints_fixed += fix_checkpoint.execute(params![chunk_gen.seed(), table, id as i64, chunk_gen.seed()])?;
+            if id < bad_id || hash_only {
+                let hash = chunk_gen.next_hash_only();
+                if id >= bad_id {
+                    insert.execute(params![id as i64, &[] as &[u8], hash_encoding.encode(&hash[..hash_bytes]), "F", chunk_gen.seed()])
+                        .map_err(|err| insert_error(err, table, id as i64))?;
+                }
+            } else {
+                let (chunk_data, chunk_hash) = chunk_gen.next();
+                let hash = hash_encoding.encode(&chunk_hash[..hash_bytes]);
+                insert.execute(params![id as i64, codec.encode(&chunk_data)?.as_ref(), hash, "F", chunk_gen.seed()])
+                    .map_err(|err| insert_error(err, table, id as i64))?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    let repaired = end - bad_id;
+    log::info!(
+        "event=repaired table=DB{} from_id={} rows={} checkpoints={}",
+        table, bad_id, repaired, checkpoints_fixed
+    );
+    match (repaired, checkpoints_fixed) {
+        (0, 0) => println!("Checked DB{} from id {}, no corrupt rows", table, start),
+        (0, _) => println!("Repaired {} seed checkpoints of DB{}", checkpoints_fixed, table),
+        _ => println!(
+            "Repaired {} rows of DB{} (ids {}..{}) and {} seed checkpoints",
+            repaired, table, bad_id, end, checkpoints_fixed
+        ),
+    }
+    Ok(())
+}
+
+/// Rewrites the `hash` column of every generated row from its data, in one
+/// transaction. The stored hash is that of the whole data column, i.e. the
+/// characters followed by the hex hash of the characters, cut to
+/// `hash_bytes`. A row whose trailing hex doesn't match its characters
+/// has corrupt data as well, so its hash is left alone and the row counted
+/// for `repair`. Hash-only and miner-stored rows have no generated data to
+/// hash and are skipped.
+pub(super) fn rehash(args: InfoArgs) -> Result<(), GenError> {
+    let mut conn = open_db(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    check_table_schema(&conn, table)?;
+    let hash_algo = stored_hash_algo(&conn, table)?;
+    let codec = stored_codec(&conn, table)?;
+    let hash_bytes = stored_hash_bytes(&conn, table, hash_algo)?;
+    let hash_encoding = stored_hash_encoding(&conn, table)?;
+    let hasher = hash_algo.hasher();
+    let hex_len = 2 * hash_algo.output_len();
+
+    let (mut checked, mut skipped, mut bad_data) = (0usize, 0usize, Vec::new());
+    let mut changed = Vec::new();
+    {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM DB{} ORDER BY id", StoredRow::COLUMNS, table))?;
+        let mut rows = stmt.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let row = StoredRow::from_row(row)?;
+            if row.is_user_data() || row.data.is_empty() {
+                skipped += 1;
+                continue;
+            }
+            let row = row.decoded(codec)?;
+            let (chars, trailer) = row.data.split_at(row.data.len().saturating_sub(hex_len));
+            if trailer.len() != hex_len || trailer != hex::encode(hasher.hash(chars)).as_bytes() {
+                bad_data.push(row.id);
+                continue;
+            }
+            checked += 1;
+            let hash = &hasher.hash(&row.data)[..hash_bytes];
+            if row.hash != hex::encode(hash) {
+                changed.push((row.id, hash_encoding.encode(hash)));
+            }
+        }
+    }
+    let tx = conn.transaction()?;
+    {
+        let mut update = tx.prepare(&format!("UPDATE DB{} SET hash = ? WHERE id = ?", table))?;
+        for (id, hash) in &changed {
+            update.execute(params![hash, id])?;
+        }
+    }
+    tx.commit()?;
+    log::info!(
+        "event=rehashed table=DB{} rows={} changed={} bad_data={} skipped={}",
+        table, checked, changed.len(), bad_data.len(), skipped
+    );
+
+    if args.json {
+        println!("{}", serde_json::json!({
+            "table": format!("DB{}", table),
+            "rows": checked,
+            "changed": changed.len(),
+            "bad_data": bad_data.len(),
+            "first_bad_data_id": bad_data.first(),
+            "skipped": skipped,
+        }));
+    } else {
+        println!("Rehashed {} rows of DB{}: {} hashes changed ({} skipped without generated data)", checked, table, changed.len(), skipped);
+        if let (Some(first), count) = (bad_data.first(), bad_data.len()) {
+            println!("{} rows have corrupt data and kept their hash, the first at id {}; run `repair`", count, first);
+        }
+    }
+    Ok(())
+}
+
+/// Id of the first row from `start` on that is missing, unreadable or differs
+/// from its regenerated chunk, or `None` if every row matches.
+pub(super) fn first_bad_row(
+    conn: &Connection,
+    table: &str,
+    codec: Codec,
+    hash_bytes: usize,
+    mut chunk_gen: ChunkGenerator,
+    start: usize,
+) -> Result<Option<usize>, GenError> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM DB{} WHERE id >= ? ORDER BY id", StoredRow::COLUMNS, table))?;
+    let mut rows = stmt.query(params![start as i64])?;
+    let mut expected_id = start;
+    while let Some(row) = rows.next()? {
+        let (chunk_data, chunk_hash) = chunk_gen.next();
+        let good = match StoredRow::from_row(row).map_err(GenError::from).and_then(|stored| stored.decoded(codec)) {
+            Ok(stored) => stored.id == expected_id as i64 && stored.mismatch(&chunk_data, &chunk_hash, hash_bytes).is_none(),
+            Err(_) => false,
+        };
+        if !good {
+            return Ok(Some(expected_id));
+        }
+        expected_id += 1;
+    }
+    Ok(None)
+}
+
+/// Regenerates the ids `args.from..args.to` without touching the rest of the
+/// table, printing one `{"id", "hash"}` line per chunk or, with `--write`,
+/// rewriting the stored rows in one transaction.
+pub(super) fn regenerate(args: RegenerateArgs) -> Result<(), GenError> {
+    let mut conn = open_db(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    check_table_schema(&conn, table)?;
+    require_sequential_ids(&conn, table, "regenerate")?;
+    let hash_algo = stored_hash_algo(&conn, table)?;
+    let codec = stored_codec(&conn, table)?;
+    let rng = stored_rng(&conn, table)?;
+    let charset = stored_charset(&conn, table)?;
+    let hash_bytes = stored_hash_bytes(&conn, table, hash_algo)?;
+    let hash_encoding = stored_hash_encoding(&conn, table)?;
+    let chunk_size = table_chunk_size(&conn, table, args.chunk_size)?;
+    let mut chunk_gen = generator_at(&conn, table, hash_algo, rng, charset, chunk_size, args.from)?;
+
+    if !args.write {
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        return match print_hashes(&mut out, &mut chunk_gen, hash_bytes, args.from..args.to) {
+            // The reader went away, e.g. `regenerate | head`.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            other => Ok(other?),
+        };
+    }
+
+    let hash_only = first_generated_row(&conn, table, codec)?.is_some_and(|row| row.data.is_empty());
+    let tx = conn.transaction()?;
+    let mut rewritten = 0;
+    {
+        let mut update = tx.prepare(&format!(
+            "UPDATE DB{} SET data = ?, hash = ?, rng_state = ? WHERE id = ? AND flag != 'T'",
+            table
+        ))?;
+        for id in args.from..args.to {
+            let (chunk_data, chunk_hash) = if hash_only {
+                (Vec::new(), chunk_gen.next_hash_only())
+            } else {
+                let (chunk_data, chunk_hash) = chunk_gen.next();
+                (codec.encode(&chunk_data)?.into_owned(), chunk_hash)
+            };
+            let hash = hash_encoding.encode(&chunk_hash[..hash_bytes]);
+            rewritten += update.execute(params![chunk_data, hash, chunk_gen.seed(), id as i64])?;
+        }
+    }
+    tx.commit()?;
+
+    log::info!(
+        "event=regenerated table=DB{} from_id={} to_id={} rows={}",
+        table, args.from, args.to, rewritten
+    );
+    println!("Rewrote {} rows of DB{} (ids {}..{})", rewritten, table, args.from, args.to);
+    Ok(())
+}
+
+pub(super) fn print_hashes(
+    out: &mut impl io::Write,
+    chunk_gen: &mut ChunkGenerator,
+    hash_bytes: usize,
+    ids: std::ops::Range<usize>,
+) -> io::Result<()> {
+    for id in ids {
+        let hash = chunk_gen.next_hash_only();
+        writeln!(out, "{}", serde_json::json!({ "id": id, "hash": hex::encode(&hash[..hash_bytes]) }))?;
+    }
+    out.flush()
+}
+
+/// Regenerates the chunk at `args.id` and compares it with the stored row.
+pub(super) fn challenge(args: RowArgs) -> Result<(), GenError> {
+    if args.backend == Backend::RawFile {
+        let (meta, store) = open_rawfile(&args.path, &args.seed)?;
+        return report_challenge(args.id, raw_mismatch(&store, &meta, args.id)?);
+    }
+    let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+    let hash_algo = stored_hash_algo(&conn, &args.seed)?;
+    let codec = stored_codec(&conn, &args.seed)?;
+    let stored = read_row(&conn, &args.seed, args.id as i64)?
+        .ok_or_else(|| GenError::InvalidArg { name: "id", value: args.id.to_string(), reason: "no such row".to_string() })?
+        .decoded(codec)?;
+    let chunk_size = table_chunk_size(&conn, &args.seed, args.chunk_size)?;
+
+    let rng = stored_rng(&conn, &args.seed)?;
+    let charset = stored_charset(&conn, &args.seed)?;
+    let hash_bytes = stored_hash_bytes(&conn, &args.seed, hash_algo)?;
+    let step = IdLayout::stored(&conn, &args.seed)?.step(stored.id).ok_or(GenError::Mismatch { id: args.id, column: "id" })?;
+    let (chunk_data, chunk_hash) = generator_at(&conn, &args.seed, hash_algo, rng, charset, chunk_size, step)?.next();
+
+    report_challenge(args.id, stored.mismatch(&chunk_data, &chunk_hash, hash_bytes))
+}
+
+/// Answers a progress challenge for chunk `--id`: the table's hash of the
+/// seed after that chunk, which is its `rng_state`, followed by the nonce.
+/// The seed is replayed from the nearest checkpoint rather than read from
+/// the row, so the answer takes the chain and not only the stored column.
+/// A validator holding the same chain, e.g. a table generated with `--hash`,
+/// checks it by running the same command.
+pub(super) fn prove_progress(args: ProgressProofArgs) -> Result<(), GenError> {
+    let RowArgs { path, key, seed: table, id, chunk_size, .. } = &args.row;
+    let conn = open_db_read_only(path, key.as_deref())?;
+    let not_stored = || GenError::InvalidArg {
+        name: "id",
+        value: id.to_string(),
+        reason: format!("DB{} holds no chunk with this id", table),
+    };
+    let stored = read_row(&conn, table, *id as i64)?.ok_or_else(not_stored)?;
+    let step = IdLayout::stored(&conn, table)?.step(stored.id).ok_or_else(not_stored)?;
+    let hash_algo = stored_hash_algo(&conn, table)?;
+    let chunk_size = table_chunk_size(&conn, table, *chunk_size)?;
+    let rng = stored_rng(&conn, table)?;
+    let charset = stored_charset(&conn, table)?;
+
+    let mut chunk_gen = generator_at(&conn, table, hash_algo, rng, charset, chunk_size, step)?;
+    chunk_gen.next_hash_only();
+    let mut state = hash_algo.hasher().start();
+    state.update(chunk_gen.seed());
+    state.update(&args.nonce);
+    println!("{}", hex::encode(state.finalize()));
+    Ok(())
+}
+
+/// Streams the table from both databases in id order and stops at the first
+/// row that differs or that only one of them holds. Data is compared after
+/// undoing each table's own compression.
+pub(super) fn diff(args: DiffArgs) -> Result<(), GenError> {
+    let conn_a = open_db_read_only(&args.db_a, args.key.as_deref())?;
+    let conn_b = open_db_read_only(&args.db_b, args.key.as_deref())?;
+    let codec_a = stored_codec(&conn_a, &args.seed)?;
+    let codec_b = stored_codec(&conn_b, &args.seed)?;
+    let sql = format!("SELECT {} FROM DB{} ORDER BY id", StoredRow::COLUMNS, args.seed);
+    let mut stmt_a = conn_a.prepare(&sql)?;
+    let mut stmt_b = conn_b.prepare(&sql)?;
+    let mut rows_a = stmt_a.query(params![])?;
+    let mut rows_b = stmt_b.query(params![])?;
+
+    let mut matching = 0;
+    let divergence = loop {
+        let row_a = rows_a.next()?.map(StoredRow::from_row).transpose()?;
+        let row_b = rows_b.next()?.map(StoredRow::from_row).transpose()?;
+        let (row_a, row_b) = match (row_a, row_b) {
+            (None, None) => break None,
+            (Some(row_a), None) => break Some(GenError::Diverged { id: row_a.id as usize, column: "id", missing_from: Some("db_b") }),
+            (None, Some(row_b)) => break Some(GenError::Diverged { id: row_b.id as usize, column: "id", missing_from: Some("db_a") }),
+            (Some(row_a), Some(row_b)) => (row_a, row_b),
+        };
+        if row_a.id != row_b.id {
+            let (id, missing_from) = if row_a.id < row_b.id { (row_a.id, "db_b") } else { (row_b.id, "db_a") };
+            break Some(GenError::Diverged { id: id as usize, column: "id", missing_from: Some(missing_from) });
+        }
+        let (row_a, row_b) = (row_a.decoded(codec_a)?, row_b.decoded(codec_b)?);
+        let column = if row_a.data != row_b.data {
+            Some("data")
+        } else if row_a.hash != row_b.hash {
+            Some("hash")
+        } else if row_a.flag != row_b.flag {
+            Some("flag")
+        } else if row_a.rng_state != row_b.rng_state {
+            Some("rng_state")
+        } else {
+            None
+        };
+        if let Some(column) = column {
+            break Some(GenError::Diverged { id: row_a.id as usize, column, missing_from: None });
+        }
+        matching += 1;
+    };
+
+    let mut report = serde_json::json!({
+        "table": format!("DB{}", args.seed),
+        "match": divergence.is_none(),
+        "matching_rows": matching,
+    });
+    if let Some(GenError::Diverged { id, column, missing_from }) = &divergence {
+        report["id"] = (*id).into();
+        report["column"] = (*column).into();
+        report["missing_from"] = (*missing_from).into();
+    }
+    println!("{}", report);
+    divergence.map_or(Ok(()), Err)
+}
+
+/// The one generated table an input of `merge` holds, and the settings
+/// every input has to share for their rows to become a single chain, by the
+/// name of the generation argument that sets them.
+pub(super) struct MergeInput {
+    pub(super) path: String,
+    pub(super) table: String,
+    pub(super) rows: usize,
+    pub(super) hash_only: bool,
+    pub(super) chunk_size: usize,
+    pub(super) settings: Vec<(&'static str, String)>,
+}
+
+impl MergeInput {
+    pub(super) fn open(path: &str, key: Option<&str>) -> Result<Self, GenError> {
+        let conn = open_db_read_only(path, key)?;
+        let invalid = |reason: String| GenError::InvalidArg { name: "inputs", value: path.to_string(), reason };
+        let tables: Vec<String> = conn
+            .prepare(
+                "SELECT DISTINCT table_name FROM metadata \
+                 WHERE 'DB' || table_name IN (SELECT name FROM sqlite_master WHERE type = 'table') ORDER BY table_name",
+            )
+            .and_then(|mut stmt| stmt.query_map(params![], |row| row.get(0))?.collect())
+            .map_err(|_| invalid("holds no generated table".to_string()))?;
+        let table = match tables.as_slice() {
+            [table] => table.clone(),
+            [] => return Err(invalid("holds no generated table".to_string())),
+            _ => return Err(invalid(format!("holds several tables ({}), merge takes one per file", tables.join(", ")))),
+        };
+
+        require_sequential_ids(&conn, &table, "merge")?;
+        let (rows, max_id): (i64, Option<i64>) = conn.query_row(
+            &format!("SELECT COUNT(*), MAX(id) FROM DB{}", table),
+            params![],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if max_id.map_or(0, |max_id| max_id + 1) != rows {
+            return Err(invalid(format!("DB{} has gaps in its ids; run `repair` on it first", table)));
+        }
+        let hash_algo = stored_hash_algo(&conn, &table)?;
+        let codec = stored_codec(&conn, &table)?;
+        let chunk_size = stored_chunk_size(&conn, &table)?
+            .ok_or_else(|| invalid(format!("DB{} records no chunk size", table)))?;
+        let hash_only = first_generated_row(&conn, &table, codec)?.is_some_and(|row| row.data.is_empty());
+        let settings = vec![
+            ("hash_algo", hash_algo.name().to_string()),
+            ("hash_bytes", stored_hash_bytes(&conn, &table, hash_algo)?.to_string()),
+            ("hash_encoding", stored_hash_encoding(&conn, &table)?.name().to_string()),
+            ("compress", codec.name().to_string()),
+            ("rng", stored_rng(&conn, &table)?.name().to_string()),
+            ("charset", stored_charset(&conn, &table)?.name().to_string()),
+            ("size", chunk_size.to_string()),
+        ];
+        Ok(MergeInput { path: path.to_string(), table, rows: rows as usize, hash_only, chunk_size, settings })
+    }
+}
+
+/// Generates `DB{table}` in `--output` with as many rows as the inputs hold
+/// together and the settings they share, then copies the miner-stored rows
+/// of each input to their renumbered ids.
+///
+/// The chunks themselves are regenerated rather than copied: every seed of
+/// the chain depends on the position of its row, so the rows of a later
+/// input would not follow from those before them. Rerunning an interrupted
+/// merge resumes the generation.
+pub(super) fn merge(args: MergeArgs) -> Result<(), GenError> {
+    if args.inputs.contains(&args.output) {
+        return Err(GenError::InvalidArg {
+            name: "output",
+            value: args.output.clone(),
+            reason: "must not be one of the --inputs".to_string(),
+        });
+    }
+    let inputs = args.inputs.iter().map(|path| MergeInput::open(path, args.key.as_deref())).collect::<Result<Vec<_>, _>>()?;
+    let first = &inputs[0];
+    for input in &inputs[1..] {
+        if input.hash_only != first.hash_only {
+            return Err(GenError::InvalidArg {
+                name: "inputs",
+                value: input.path.clone(),
+                reason: format!("DB{} and DB{} don't both store only hashes", first.table, input.table),
+            });
+        }
+        for ((key, expected), (_, value)) in first.settings.iter().zip(&input.settings) {
+            if value != expected {
+                return Err(GenError::InvalidArg {
+                    name: "inputs",
+                    value: input.path.clone(),
+                    reason: format!("DB{} has {} {}, DB{} has {}", input.table, key, value, first.table, expected),
+                });
+            }
+        }
+    }
+    let total: usize = inputs.iter().map(|input| input.rows).sum();
+    if total == 0 {
+        return Err(GenError::InvalidArg {
+            name: "inputs",
+            value: args.inputs.join(","),
+            reason: "the tables hold no rows to merge".to_string(),
+        });
+    }
+
+    // The merged table is the one a generation run of the combined length
+    // writes, with its metadata, checkpoints and progress bars.
+    let mut argv = vec!["merge".to_string(), "--path".to_string(), args.output.clone(), "--seed".to_string(), args.seed.clone()];
+    argv.extend(["--n".to_string(), total.to_string()]);
+    for (arg, value) in &first.settings {
+        argv.extend([format!("--{}", arg), value.clone()]);
+    }
+    if first.hash_only {
+        argv.push("--hash".to_string());
+    }
+    if args.json {
+        argv.push("--json".to_string());
+    }
+    let matches = App::new("merge")
+        .arg(db_path_arg())
+        .arg(Arg::with_name("n").long("n").takes_value(true))
+        .args(&generation_args())
+        .get_matches_from_safe(argv)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    let generation = Args { key: args.key.clone(), ..Args::from_matches(&matches)? };
+    let cancel = generation.cancel.clone();
+    run(generation)?;
+    if cancel.is_cancelled() {
+        return Ok(());
+    }
+
+    let mut conn = open_db(&args.output, args.key.as_deref())?;
+    let tx = conn.transaction()?;
+    let mut user_rows = 0;
+    {
+        let mut update = tx.prepare(&format!("UPDATE DB{} SET data = ?, hash = ?, flag = 'T' WHERE id = ?", args.seed))?;
+        let mut offset = 0;
+        for input in &inputs {
+            let input_conn = open_db_read_only(&input.path, args.key.as_deref())?;
+            let mut stmt = input_conn.prepare(&format!("SELECT id, data, hash FROM DB{} WHERE flag = 'T'", input.table))?;
+            let mut rows = stmt.query(params![])?;
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                let hash: Value = row.get(2)?;
+                user_rows += update.execute(params![column_bytes(row, 1)?, hash, offset + id])?;
+            }
+            offset += input.rows as i64;
+        }
+    }
+    tx.commit()?;
+
+    log::info!(
+        "event=merged table=DB{} inputs={} rows={} user_rows={}",
+        args.seed, inputs.len(), total, user_rows
+    );
+    if !args.json {
+        println!(
+            "Merged {} rows of {} tables into DB{} ({} rows of miner-stored data copied, chunk size {})",
+            total, inputs.len(), args.seed, user_rows, first.chunk_size
+        );
+    }
+    Ok(())
+}
+
+/// Prints the outcome of a challenge and fails on a mismatch.
+pub(super) fn report_challenge(id: usize, column: Option<&'static str>) -> Result<(), GenError> {
+    println!("{}", serde_json::json!({
+        "id": id,
+        "match": column.is_none(),
+        "column": column,
+    }));
+    match column {
+        Some(column) => Err(GenError::Mismatch { id, column }),
+        None => Ok(()),
+    }
+}
+
+/// Prints the seed the chain reaches after `args.n_chunks` chunks, i.e. the
+/// `rng_state` of the last row of a table generated with the same settings.
+/// Chunks are hashed as they are drawn and never held in full.
+pub(super) fn final_seed(args: ChainArgs) -> Result<(), GenError> {
+    let mut chunk_gen = args.chunk_generator();
+    for _ in 0..args.n_chunks {
+        chunk_gen.next_hash_only();
+    }
+    println!("{}", hex::encode(chunk_gen.seed()));
+    Ok(())
+}
+
+/// Prints the settings and genesis seed of the chain and its first chunks as
+/// they are stored: `data` is the chunk followed by the hex hash of the
+/// chunk, and `hash`, the hash of `data`, is the seed of the next chunk.
+pub(super) fn testvectors(args: ChainArgs) -> Result<(), GenError> {
+    let mut chunk_gen = args.chunk_generator();
+    let derivation = match (&args.seed, args.partition) {
+        (Some(_), Some(partition)) => format!(
+            "{}(u64_le({}) || table || {} zero bytes)",
+            args.hash_algo.name(), partition.0, args.chunk_size
+        ),
+        (Some(_), None) => format!("{}(table || {} zero bytes)", args.hash_algo.name(), args.chunk_size),
+        (None, _) => format!("{}({} zero bytes)", args.hash_algo.name(), args.chunk_size),
+    };
+    let genesis = serde_json::json!({
+        "table": args.seed.as_ref().map(|seed| format!("DB{}", seed)),
+        "derivation": derivation,
+        "seed_hex": hex::encode(chunk_gen.seed()),
+    });
+    let vectors: Vec<serde_json::Value> = (0..args.n_chunks)
+        .map(|id| {
+            let seed = hex::encode(chunk_gen.seed());
+            let (data, hash) = chunk_gen.next();
+            serde_json::json!({ "id": id, "seed_hex": seed, "data_hex": hex::encode(data), "hash_hex": hex::encode(hash) })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+        "hash_algo": args.hash_algo.name(),
+        "rng": args.rng.name(),
+        "charset": args.charset.name(),
+        "chunk_size": args.chunk_size,
+        "genesis": genesis,
+        "vectors": vectors,
+    })).map_err(io::Error::from)?);
+    Ok(())
+}
+
+/// Walks the chain like `ChunkGenerator::next`, timing the PRNG expansion and
+/// the two hashes separately. Nothing is written to disk.
+pub(super) fn bench(args: BenchArgs) -> Result<(), GenError> {
+    use std::time::{Duration, Instant};
+
+    let hasher = args.hash_algo.hasher();
+    let seed = ChunkGenerator::genesis_seed(hasher.as_ref(), args.chunk_size);
+    let mut chunk_gen = ChunkGenerator::new(seed, args.chunk_size, hasher)
+        .with_rng(args.rng)
+        .with_charset(args.charset);
+    let mut rng_time = Duration::ZERO;
+    let mut hash_time = Duration::ZERO;
+
+    let started = Instant::now();
+    for _ in 0..args.n_chunks {
+        let step = Instant::now();
+        let mut chunk = chunk_gen.generate_string_chunk(chunk_gen.seed());
+        rng_time += step.elapsed();
+
+        let step = Instant::now();
+        let chunk_hash = chunk_gen.hash_data(&chunk);
+        chunk.extend(hex::encode(chunk_hash).into_bytes());
+        let next_seed = chunk_gen.hash_data(&chunk);
+        hash_time += step.elapsed();
+        chunk_gen.set_seed(next_seed);
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let n = args.n_chunks.max(1) as f64;
+    println!(
+        "Generated {} chunks of {} bytes in {:.2}s ({}, {}, {})",
+        args.n_chunks, args.chunk_size, elapsed, args.hash_algo.name(), args.rng.name(), args.charset.name()
+    );
+    println!(
+        "  throughput: {:.0} chunks/s, {:.2} MB/s",
+        args.n_chunks as f64 / elapsed,
+        (args.n_chunks * args.chunk_size) as f64 / elapsed / 1e6
+    );
+    println!(
+        "  per chunk:  rng {:.1}us, hash {:.1}us",
+        rng_time.as_secs_f64() * 1e6 / n,
+        hash_time.as_secs_f64() * 1e6 / n
+    );
+
+    // The same chain through `next`, which allocates every chunk afresh, and
+    // through `next_chunk`, which writes each one over the last.
+    let chain_time = |reuse: bool| {
+        let mut chunk_gen = ChunkGenerator::new(chunk_gen.seed().to_vec(), args.chunk_size, args.hash_algo.hasher())
+            .with_rng(args.rng)
+            .with_charset(args.charset);
+        let started = Instant::now();
+        for _ in 0..args.n_chunks {
+            if reuse {
+                std::hint::black_box(chunk_gen.next_chunk());
+            } else {
+                std::hint::black_box(chunk_gen.next());
+            }
+        }
+        started.elapsed().as_secs_f64()
+    };
+    let (fresh, reused) = (chain_time(false), chain_time(true));
+    println!(
+        "  fresh:      {:.1}us per chunk, allocating {} bytes each",
+        fresh * 1e6 / n, args.chunk_size + 2 * args.hash_algo.output_len()
+    );
+    println!("  reused:     {:.1}us per chunk, writing over one buffer", reused * 1e6 / n);
+    Ok(())
+}
+
+pub(super) fn truncate(args: TruncateArgs) -> Result<(), GenError> {
+    let mut conn = open_db(&args.path, args.key.as_deref())?;
+    let seed_value = args.seed.as_str();
+    let size_before = database_size(&args.path)?;
+
+    let tx = conn.transaction()?;
+    let deleted = tx.execute(&format!("DELETE FROM DB{} WHERE id >= ?", seed_value), params![args.to as i64])?;
+    create_checkpoint_table(&tx)?;
+    // Checkpoints are kept by chain step.
+    let to_step = IdLayout::stored(&tx, seed_value)?.step_from(args.to);
+    tx.execute(
+        "DELETE FROM seed_checkpoints WHERE table_name = ? AND id >= ?",
+        params![seed_value, to_step as i64],
+    )?;
+    tx.commit()?;
+    reclaim_space(&conn, args.auto_vacuum.as_deref())?;
+    // The next generation run records the manifest of what is left.
+    for key in MANIFEST_KEYS {
+        remove_metadata(&conn, seed_value, key)?;
+    }
+
+    let freed = size_before.saturating_sub(database_size(&args.path)?);
+    log::info!("event=truncated table=DB{} from_id={} rows_deleted={} bytes_freed={}", seed_value, args.to, deleted, freed);
+    println!("Deleted {} rows from DB{}, freed {}", deleted, seed_value, HumanBytes(freed));
+    Ok(())
+}
+
+/// Size of the database file plus its write-ahead log, if any.
+pub(super) fn database_size(path: &str) -> Result<u64, io::Error> {
+    let wal = match std::fs::metadata(format!("{}-wal", path)) {
+        Ok(meta) => meta.len(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err),
+    };
+    Ok(std::fs::metadata(path)?.len() + wal)
+}
+
+/// Returns the pages freed by a delete to the filesystem. Databases without
+/// auto_vacuum need a full VACUUM, which rewrites the whole file (briefly
+/// needing as much free space again) and switches it to `mode`, if given, on
+/// the way. Incremental ones only release the free list, and FULL ones
+/// already shrank at commit.
+pub(super) fn reclaim_space(conn: &Connection, mode: Option<&str>) -> Result<(), rusqlite::Error> {
+    let auto_vacuum: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+    match auto_vacuum {
+        0 => {
+            if let Some(mode) = mode {
+                conn.pragma_update(None, "auto_vacuum", &mode)?;
+            }
+            conn.execute_batch("VACUUM")?;
+        }
+        2 => {
+            // Every step of the pragma frees a single page, so run it to the end.
+            let mut stmt = conn.prepare("PRAGMA incremental_vacuum")?;
+            let mut rows = stmt.query(params![])?;
+            while rows.next()?.is_some() {}
+        }
+        _ => {}
+    }
+    // In WAL mode the shrunk database only reaches the main file at a checkpoint.
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", params![], |_| Ok(()))?;
+    Ok(())
+}
+
+pub(super) fn info(args: InfoArgs) -> Result<(), GenError> {
+    // Read-only, so auditing a mistyped path doesn't leave an empty database behind.
+    let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+    let seed_value = args.seed.as_str();
+    let hash_algo = stored_hash_algo(&conn, seed_value)?;
+    let codec = stored_codec(&conn, seed_value)?;
+    let rng = stored_rng(&conn, seed_value)?;
+    let charset = stored_charset(&conn, seed_value)?;
+    let hash_bytes = stored_hash_bytes(&conn, seed_value, hash_algo)?;
+    let hash_encoding = stored_hash_encoding(&conn, seed_value)?;
+
+    let (rows, min_id, max_id): (i64, Option<i64>, Option<i64>) = conn.query_row(
+        &format!("SELECT COUNT(*), MIN(id), MAX(id) FROM DB{}", seed_value),
+        params![],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let sample = first_generated_row(&conn, seed_value, codec)?;
+    let only_hash = sample.as_ref().map(|row| row.data.is_empty());
+    let chunk_size = stored_chunk_size(&conn, seed_value)?;
+    let salt = read_metadata(&conn, seed_value, "salt")?;
+    let partition_index = read_metadata(&conn, seed_value, "partition_index")?;
+    let layout = IdLayout::stored(&conn, seed_value)?;
+    let generator_version = read_metadata(&conn, seed_value, "generator_version")?;
+    let file_size = std::fs::metadata(&args.path)?.len();
+    // What the disk check before generation expects the rows to take.
+    let estimated_size = chunk_size
+        .map(|size| estimate_db_bytes(rows as u64, size, only_hash.unwrap_or(false), hash_algo, hash_bytes, hash_encoding));
+
+    if args.json {
+        println!("{}", serde_json::json!({
+            "table": format!("DB{}", seed_value),
+            "rows": rows,
+            "min_id": min_id,
+            "max_id": max_id,
+            "id_stride": layout.stride,
+            "chunk_size": chunk_size,
+            "only_hash": only_hash,
+            "hash_algo": hash_algo.name(),
+            "hash_bytes": hash_bytes,
+            "hash_encoding": hash_encoding.name(),
+            "compression": codec.name(),
+            "rng": rng.name(),
+            "charset": charset.name(),
+            "salt": salt,
+            "partition_index": partition_index.as_ref().and_then(|index| index.parse::<u64>().ok()),
+            "generator_version": generator_version,
+            "file_size": file_size,
+            "estimated_size": estimated_size,
+        }));
+        return Ok(());
+    }
+
+    let unknown = || "unknown".to_string();
+    println!("Table DB{} in {}", seed_value, args.path);
+    match (min_id, max_id) {
+        (Some(min_id), Some(max_id)) if layout.stride > 1 => {
+            println!("  rows:        {} (ids {}..={} every {})", rows, min_id, max_id, layout.stride)
+        }
+        (Some(min_id), Some(max_id)) => println!("  rows:        {} (ids {}..={})", rows, min_id, max_id),
+        _ => println!("  rows:        0"),
+    }
+    println!("  chunk_size:  {}", chunk_size.map_or_else(unknown, |size| size.to_string()));
+    println!("  only_hash:   {}", only_hash.map_or_else(unknown, |only_hash| only_hash.to_string()));
+    println!("  hash_algo:   {}", hash_algo.name());
+    println!("  hash_bytes:  {} as {}", hash_bytes, hash_encoding.name());
+    println!("  compression: {}", codec.name());
+    println!("  rng:         {}", rng.name());
+    println!("  charset:     {}", charset.name());
+    match partition_index {
+        Some(index) => println!("  salt:        {} {}", salt.unwrap_or_else(unknown), index),
+        None => println!("  salt:        {}", salt.unwrap_or_else(unknown)),
+    }
+    println!("  created by:  {}", generator_version.map_or_else(unknown, |version| format!("version {}", version)));
+    match estimated_size {
+        Some(estimated) => println!("  file size:   {} (estimated {})", HumanBytes(file_size), HumanBytes(estimated)),
+        None => println!("  file size:   {}", HumanBytes(file_size)),
+    }
+    Ok(())
+}
+
+/// Prints every `DB{seed}` table in `args.path`. Tables whose columns aren't
+/// those of a generated table, or whose name couldn't be a seed, are listed
+/// as stray without looking inside them.
+pub(super) fn list(args: ListArgs) -> Result<(), GenError> {
+    let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+    let names: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name GLOB 'DB*' ORDER BY name")?
+        .query_map(params![], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    if names.is_empty() && !args.json {
+        println!("No DB{{seed}} tables in {}", args.path);
+    }
+    for name in names {
+        let seed = &name[2..];
+        let generated = check_table_seed(seed).is_ok() && check_table_schema(&conn, seed).is_ok();
+        let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")), params![], |row| row.get(0))?;
+        let (chunk_size, source) = if !generated {
+            (None, None)
+        } else if let Some(value) = read_metadata(&conn, seed, "chunk_size")? {
+            (value.parse::<usize>().ok(), Some("metadata"))
+        } else {
+            let inferred = stored_chunk_size(&conn, seed)?;
+            (inferred, inferred.map(|_| "inferred"))
+        };
+
+        if args.json {
+            println!("{}", serde_json::json!({
+                "table": name,
+                "rows": rows,
+                "chunk_size": chunk_size,
+                "chunk_size_from": source,
+                "stray": !generated,
+            }));
+            continue;
+        }
+        let size = match (chunk_size, source) {
+            (Some(size), Some("inferred")) => format!("chunk_size {} (inferred)", size),
+            (Some(size), _) => format!("chunk_size {}", size),
+            _ if generated => "chunk_size unknown".to_string(),
+            _ => "stray: not a generated table".to_string(),
+        };
+        println!("{:<24} {:>12} rows  {}", name, rows, size);
+    }
+    Ok(())
+}
+
+/// Outcome of one `doctor` check.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum CheckStatus {
+    Pass,
+    /// Worth a look, but the table can still be resumed and served.
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+/// One line of the `doctor` report.
+pub(super) struct Check {
+    pub(super) name: &'static str,
+    pub(super) status: CheckStatus,
+    pub(super) detail: String,
+}
+
+impl Check {
+    pub(super) fn new(name: &'static str, status: CheckStatus, detail: String) -> Self {
+        Check { name, status, detail }
+    }
+
+    /// `check`, or a failure of `name` with the error it ran into.
+    pub(super) fn or_failed(name: &'static str, check: Result<Check, GenError>) -> Self {
+        check.unwrap_or_else(|err| Check::new(name, CheckStatus::Fail, err.to_string()))
+    }
+}
+
+/// Settings recorded in metadata for every table generated by this version.
+pub(super) const RECORDED_SETTINGS: [&str; 10] = [
+    "genesis_seed", "salt", "hash_algo", "hash_bytes", "hash_encoding", "chunk_size", "compression", "rng",
+    "charset", "generator_version",
+];
+
+/// Runs the checks an operator would otherwise piece together from `info`,
+/// `verify --from` and `df`, and fails if any of them does. The checks that
+/// read the table are skipped when it is missing or isn't a generated table.
+pub(super) fn doctor(args: DoctorArgs) -> Result<(), GenError> {
+    let conn = open_db_read_only(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    let schema = Check::or_failed("schema", doctor_schema(&conn, &args));
+    let table_ok = schema.status != CheckStatus::Fail;
+    let mut checks = vec![schema];
+    if table_ok {
+        checks.push(Check::or_failed("metadata", doctor_metadata(&conn, table)));
+        checks.push(Check::or_failed("trailing_rows", doctor_trailing_rows(&conn, &args)));
+        checks.push(Check::or_failed("disk_space", doctor_disk_space(&conn, &args)));
+    }
+    checks.push(Check::or_failed("pragmas", doctor_pragmas(&conn)));
+
+    if !args.json {
+        println!("Table DB{} in {}", table, args.path);
+    }
+    for check in &checks {
+        if args.json {
+            println!("{}", serde_json::json!({
+                "check": check.name,
+                "status": check.status.name(),
+                "detail": check.detail,
+            }));
+        } else {
+            println!("  {}  {:<14} {}", check.status.name(), check.name, check.detail);
+        }
+    }
+    let failed: Vec<&'static str> = checks.iter().filter(|check| check.status == CheckStatus::Fail).map(|check| check.name).collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(GenError::ChecksFailed { failed })
+    }
+}
+
+pub(super) fn doctor_schema(conn: &Connection, args: &DoctorArgs) -> Result<Check, GenError> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+        params![format!("DB{}", args.seed)],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Ok(Check::new("schema", CheckStatus::Fail, format!("no table DB{} in {}", args.seed, args.path)));
+    }
+    check_table_schema(conn, &args.seed)?;
+    let columns: Vec<&str> = TABLE_COLUMNS.iter().map(|(name, _)| *name).collect();
+    Ok(Check::new("schema", CheckStatus::Pass, format!("columns {}", columns.join(", "))))
+}
+
+/// Every recorded setting must parse; missing ones only mean the table was
+/// generated by an earlier version, which the defaults stand in for.
+pub(super) fn doctor_metadata(conn: &Connection, table: &str) -> Result<Check, GenError> {
+    let settings = ChainSettings::stored(conn, table, None)?;
+    stored_genesis_seed(conn, table, settings.hash_algo)?;
+    stored_hash_encoding(conn, table)?;
+    let mut missing = Vec::new();
+    for key in RECORDED_SETTINGS {
+        if read_metadata(conn, table, key)?.is_none() {
+            missing.push(key);
+        }
+    }
+    Ok(if read_metadata(conn, table, "fill_in_progress")?.is_some() {
+        Check::new(
+            "metadata",
+            CheckStatus::Warn,
+            "an interrupted --fill_data left hash-only rows, rerun it to store their data".to_string(),
+        )
+    } else if !missing.is_empty() {
+        Check::new(
+            "metadata",
+            CheckStatus::Warn,
+            format!("no {} recorded, the defaults of earlier versions are assumed", missing.join(", ")),
+        )
+    } else {
+        Check::new("metadata", CheckStatus::Pass, format!("all {} settings recorded", RECORDED_SETTINGS.len()))
+    })
+}
+
+/// Regenerates the last `--rows` chunks, where a crash or a full disk would
+/// have left its damage.
+pub(super) fn doctor_trailing_rows(conn: &Connection, args: &DoctorArgs) -> Result<Check, GenError> {
+    let max_id: Option<i64> = conn.query_row(&format!("SELECT MAX(id) FROM DB{}", args.seed), params![], |row| row.get(0))?;
+    let Some(max_id) = max_id else {
+        return Ok(Check::new("trailing_rows", CheckStatus::Pass, "the table is empty".to_string()));
+    };
+    let layout = IdLayout::stored(conn, &args.seed)?;
+    let end = layout.step_from(max_id as usize + 1);
+    let verified = verify_table(&VerifyArgs {
+        path: args.path.clone(),
+        key: args.key.clone(),
+        seed: args.seed.clone(),
+        chunk_size: None,
+        from: layout.id(end.saturating_sub(args.rows)) as usize,
+        threads: 1,
+        sample: None,
+        only_hashes: false,
+        backend: Backend::Sqlite,
+    })?;
+    Ok(Check::new(
+        "trailing_rows",
+        CheckStatus::Pass,
+        format!("the last {} rows match the chain, up to id {}", verified.rows + verified.skipped, max_id),
+    ))
+}
+
+/// Free space on the disk, and with `--n` whether it holds the rows still
+/// to come, estimated as the check before generation does.
+pub(super) fn doctor_disk_space(conn: &Connection, args: &DoctorArgs) -> Result<Check, GenError> {
+    let available = fs2::available_space(&args.path)?;
+    let Some(n_chunks) = args.n_chunks else {
+        return Ok(Check::new("disk_space", CheckStatus::Pass, format!("{} free", HumanBytes(available))));
+    };
+    let table = args.seed.as_str();
+    let Some(chunk_size) = stored_chunk_size(conn, table)? else {
+        return Ok(Check::new("disk_space", CheckStatus::Warn, "unknown chunk size, nothing to estimate".to_string()));
+    };
+    let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM DB{}", table), params![], |row| row.get(0))?;
+    let hash_algo = stored_hash_algo(conn, table)?;
+    let only_hash = first_generated_row(conn, table, stored_codec(conn, table)?)?.is_some_and(|row| row.data.is_empty());
+    let needed = estimate_db_bytes(
+        n_chunks.saturating_sub(rows as usize) as u64,
+        chunk_size,
+        only_hash,
+        hash_algo,
+        stored_hash_bytes(conn, table, hash_algo)?,
+        stored_hash_encoding(conn, table)?,
+    );
+    let detail = format!("{} free, about {} needed up to --n {}", HumanBytes(available), HumanBytes(needed), n_chunks);
+    Ok(Check::new("disk_space", if needed > available { CheckStatus::Fail } else { CheckStatus::Pass }, detail))
+}
+
+/// The pragmas generation sets that are stored in the file, and so show
+/// whether they took effect.
+pub(super) fn doctor_pragmas(conn: &Connection) -> Result<Check, GenError> {
+    let journal_mode: String = conn.pragma_query_value(None, "journal_mode", |row| row.get(0))?;
+    let page_size: i64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+    let auto_vacuum: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+    let mut problems = Vec::new();
+    if !journal_mode.eq_ignore_ascii_case("WAL") {
+        problems.push(format!(
+            "journal_mode={}, not WAL: only WAL keeps the committed rows through a crash and lets readers in during a run",
+            journal_mode.to_ascii_lowercase()
+        ));
+    }
+    if page_size != PAGE_SIZE {
+        problems.push(format!("page_size={}, not {}: the file was created with another page size", page_size, PAGE_SIZE));
+    }
+    if problems.is_empty() {
+        let auto_vacuum = ["NONE", "FULL", "INCREMENTAL"][auto_vacuum.clamp(0, 2) as usize];
+        let detail = format!("journal_mode=wal page_size={} auto_vacuum={}", page_size, auto_vacuum);
+        return Ok(Check::new("pragmas", CheckStatus::Pass, detail));
+    }
+    Ok(Check::new("pragmas", CheckStatus::Warn, problems.join("; ")))
+}
@@ -0,0 +1,411 @@
+//! `verify`, of every row or of a sample of them.
+
+use super::*;
+
+/// Opens the raw store at `path` for reading and checks it holds `table`.
+pub(super) fn open_rawfile(path: &str, table: &str) -> Result<(RawMeta, RawStore), GenError> {
+    let meta = RawMeta::load(path)?.ok_or_else(|| GenError::InvalidArg {
+        name: "path",
+        value: path.to_string(),
+        reason: "no rawfile store here".to_string(),
+    })?;
+    if meta.table != table {
+        return Err(GenError::ParamMismatch { field: "table", stored: meta.table, requested: table.to_string() });
+    }
+    let store = RawStore::open(path, &meta, false)?;
+    Ok((meta, store))
+}
+
+/// Seed of chunk `id` in a raw store: the hash of the chunk before it, or the
+/// genesis seed for the first one.
+pub(super) fn raw_seed(store: &RawStore, meta: &RawMeta, id: usize) -> Result<Vec<u8>, GenError> {
+    match id.checked_sub(1) {
+        Some(prev) => Ok(store.hash(prev as u64)?),
+        None => Ok(meta.genesis_seed.clone()),
+    }
+}
+
+/// Chunk `id` of a raw store in the shape of a table row, so it can be
+/// checked and printed like one.
+pub(super) fn raw_row(store: &RawStore, id: usize) -> Result<Option<StoredRow>, GenError> {
+    Ok(store.read(id as u64)?.map(|(data, hash)| StoredRow {
+        id: id as i64,
+        data,
+        hash: hex::encode(&hash),
+        flag: "F".to_string(),
+        rng_state: hash,
+    }))
+}
+
+/// Regenerates chunk `id` of a raw store from the index and returns the
+/// first column that differs, if any.
+pub(super) fn raw_mismatch(store: &RawStore, meta: &RawMeta, id: usize) -> Result<Option<&'static str>, GenError> {
+    let stored = raw_row(store, id)?
+        .ok_or_else(|| GenError::InvalidArg { name: "id", value: id.to_string(), reason: "no such row".to_string() })?;
+    let (chunk_data, chunk_hash) = ChunkGenerator::new(raw_seed(store, meta, id)?, meta.chunk_size, meta.hash_algo.hasher())
+        .with_rng(meta.rng)
+        .with_charset(meta.charset)
+        .next();
+    Ok(stored.mismatch(&chunk_data, &chunk_hash, meta.hash_algo.output_len()))
+}
+
+/// `verify` over a raw store: walks the chain from the seed of `--from` and
+/// checks each record and its index entry.
+pub(super) fn verify_rawfile(args: &VerifyArgs) -> Result<Verified, GenError> {
+    let (meta, store) = open_rawfile(&args.path, &args.seed)?;
+    let len = store.len() as usize;
+    if args.threads == 1 || len <= args.from {
+        return verify_raw_range(&store, &meta, args.from..len);
+    }
+    // The index holds the seed of every chunk, so the store splits anywhere.
+    let threads = args.threads.min(len - args.from);
+    let ranges: Vec<_> = (0..threads)
+        .map(|t| args.from + (len - args.from) * t / threads..args.from + (len - args.from) * (t + 1) / threads)
+        .collect();
+    drop(store);
+    let first_failure = FirstFailure::default();
+    let verified = std::thread::scope(|scope| {
+        let workers: Vec<_> = ranges.into_iter().map(|range| {
+            let (meta, first_failure) = (&meta, &first_failure);
+            scope.spawn(move || {
+                let start = range.start;
+                RawStore::open(&args.path, meta, false)
+                    .map_err(GenError::from)
+                    .and_then(|store| verify_raw_range(&store, meta, range))
+                    .map_err(|err| first_failure.record(start, err))
+                    .ok()
+            })
+        }).collect();
+        workers.into_iter()
+            .filter_map(|worker| worker.join().unwrap_or_else(|_| {
+                first_failure.record(0, io::Error::other("worker thread panicked").into());
+                None
+            }))
+            .fold(Verified { rows: 0, skipped: 0 }, |total, verified| total.add(&verified))
+    });
+    match first_failure.take() {
+        Some(err) => Err(err),
+        None => Ok(verified),
+    }
+}
+
+pub(super) fn verify_raw_range(store: &RawStore, meta: &RawMeta, ids: std::ops::Range<usize>) -> Result<Verified, GenError> {
+    let chunk_gen = ChunkGenerator::new(raw_seed(store, meta, ids.start)?, meta.chunk_size, meta.hash_algo.hasher())
+        .with_rng(meta.rng)
+        .with_charset(meta.charset);
+    let rows = ids.len();
+    for (id, (chunk_data, chunk_hash)) in ids.zip(chunk_gen) {
+        let stored = raw_row(store, id)?.expect("id is below the store length");
+        if let Some(column) = stored.mismatch(&chunk_data, &chunk_hash, meta.hash_algo.output_len()) {
+            return Err(GenError::Mismatch { id, column });
+        }
+    }
+    Ok(Verified { rows, skipped: 0 })
+}
+
+/// Rows checked by a verification pass.
+pub(super) struct Verified {
+    pub(super) rows: usize,
+    /// Miner-stored rows, whose seed chain was checked but not their data.
+    pub(super) skipped: usize,
+}
+
+impl Verified {
+    /// Counts of this pass and `other` together.
+    pub(super) fn add(self, other: &Verified) -> Verified {
+        Verified { rows: self.rows + other.rows, skipped: self.skipped + other.skipped }
+    }
+}
+
+pub(super) fn verify(args: VerifyArgs) -> Result<(), GenError> {
+    if let Some(sample) = &args.sample {
+        return sample_verify(&args, sample);
+    }
+    let verified = match args.backend {
+        Backend::Sqlite => verify_table(&args)?,
+        Backend::RawFile => verify_rawfile(&args)?,
+    };
+    println!("Verified {} rows ({} skipped as miner-stored data)", verified.rows, verified.skipped);
+    Ok(())
+}
+
+/// Regenerates the table's chain from `args.from` and checks every row.
+///
+/// The chain is cut at the table's seed checkpoints into ranges that are
+/// replayed independently, by up to `args.threads` workers. A failing range
+/// stops the ranges above it, and the error of the lowest one is returned,
+/// which is where a single pass down the chain stops too.
+pub(super) fn verify_table(args: &VerifyArgs) -> Result<Verified, GenError> {
+    let conn = open_db(&args.path, args.key.as_deref())?;
+    let seed_value = args.seed.as_str();
+
+    let max_id: Option<i64> = conn.query_row(&format!("SELECT MAX(id) FROM DB{}", seed_value), params![], |row| row.get(0))?;
+    // `--from` is a stored id; the chain is replayed from the step stored there.
+    let layout = IdLayout::stored(&conn, seed_value)?;
+    let from = layout.step_from(args.from);
+    let end = max_id.map_or(0, |max_id| layout.step_from(max_id as usize + 1));
+    if from >= end {
+        return Ok(Verified { rows: 0, skipped: 0 });
+    }
+    let mut settings = ChainSettings::stored(&conn, seed_value, args.chunk_size)?;
+    settings.only_hashes = args.only_hashes
+        || first_generated_row(&conn, seed_value, settings.codec)?.is_some_and(|row| row.data.is_empty());
+    // Shared-cache connections fail with SQLITE_LOCKED instead of waiting.
+    if args.threads == 1 || args.path == MEMORY_DB {
+        return verify_range(&conn, seed_value, &settings, from..end);
+    }
+    let mut bounds = vec![from];
+    bounds.extend(checkpoint_steps(&conn, seed_value, from + 1..end)?);
+    bounds.push(end);
+    let ranges: Vec<_> = bounds.windows(2).map(|bound| bound[0]..bound[1]).collect();
+    drop(conn);
+    let first_failure = FirstFailure::default();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads.min(ranges.len()))
+        .build()
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    let verified = pool.install(|| {
+        ranges.into_par_iter()
+            .map_init(
+                || None,
+                |conn: &mut Option<Connection>, range| {
+                    if first_failure.is_below(range.start) {
+                        return None;
+                    }
+                    let start = range.start;
+                    let verified = match conn {
+                        Some(conn) => verify_range(conn, seed_value, &settings, range),
+                        None => open_db_read_only(&args.path, args.key.as_deref()).map_err(GenError::from).and_then(|opened| {
+                            verify_range(conn.insert(opened), seed_value, &settings, range)
+                        }),
+                    };
+                    verified.map_err(|err| first_failure.record(start, err)).ok()
+                },
+            )
+            .flatten()
+            .reduce(|| Verified { rows: 0, skipped: 0 }, |total, verified| total.add(&verified))
+    });
+    match first_failure.take() {
+        Some(err) => Err(err),
+        None => Ok(verified),
+    }
+}
+
+/// Settings of a table a verification pass regenerates its chunks with.
+pub(super) struct ChainSettings {
+    pub(super) hash_algo: HashAlgo,
+    pub(super) codec: Codec,
+    pub(super) rng: RngAlgo,
+    pub(super) charset: Charset,
+    pub(super) hash_bytes: usize,
+    pub(super) chunk_size: usize,
+    pub(super) layout: IdLayout,
+    /// Check the rows with `StoredRow::hash_mismatch` against the hash-only
+    /// chain, which never builds the chunks.
+    pub(super) only_hashes: bool,
+}
+
+impl ChainSettings {
+    pub(super) fn stored(conn: &Connection, table: &str, chunk_size: Option<usize>) -> Result<Self, GenError> {
+        let hash_algo = stored_hash_algo(conn, table)?;
+        Ok(ChainSettings {
+            hash_algo,
+            codec: stored_codec(conn, table)?,
+            rng: stored_rng(conn, table)?,
+            charset: stored_charset(conn, table)?,
+            hash_bytes: stored_hash_bytes(conn, table, hash_algo)?,
+            chunk_size: table_chunk_size(conn, table, chunk_size)?,
+            layout: IdLayout::stored(conn, table)?,
+            only_hashes: false,
+        })
+    }
+}
+
+/// Checks the rows of chain steps `steps`, which must all be present.
+pub(super) fn verify_range(
+    conn: &Connection,
+    table: &str,
+    settings: &ChainSettings,
+    steps: std::ops::Range<usize>,
+) -> Result<Verified, GenError> {
+    let layout = settings.layout;
+    let mut chunk_gen = generator_at(
+        conn, table, settings.hash_algo, settings.rng, settings.charset, settings.chunk_size, steps.start,
+    )?;
+    // The data isn't read at all when only the hashes are compared.
+    let columns = if settings.only_hashes { "id, x'', hash, flag, rng_state" } else { StoredRow::COLUMNS };
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM DB{} WHERE id >= ? AND id < ? ORDER BY id",
+        columns, table
+    ))?;
+    let mut rows = stmt.query(params![layout.id(steps.start), layout.id(steps.end)])?;
+    let mut expected_id = steps.start;
+    let mut skipped = 0;
+
+    while let Some(row) = rows.next()? {
+        let (stored, mismatch) = if settings.only_hashes {
+            let stored = StoredRow::from_row(row)?;
+            let mismatch = stored.hash_mismatch(&chunk_gen.next_hash_only(), settings.hash_bytes);
+            (stored, mismatch)
+        } else {
+            let stored = StoredRow::from_row(row)?.decoded(settings.codec)?;
+            let (chunk_data, chunk_hash) = chunk_gen.next();
+            let mismatch = stored.mismatch(&chunk_data, &chunk_hash, settings.hash_bytes);
+            (stored, mismatch)
+        };
+
+        if stored.id != layout.id(expected_id) {
+            return Err(GenError::Mismatch { id: layout.id(expected_id) as usize, column: "id" });
+        }
+        expected_id += 1;
+        if let Some(column) = mismatch {
+            return Err(GenError::Mismatch { id: stored.id as usize, column });
+        }
+        if stored.is_user_data() {
+            skipped += 1;
+        }
+    }
+    if expected_id < steps.end {
+        return Err(GenError::Mismatch { id: layout.id(expected_id) as usize, column: "id" });
+    }
+    Ok(Verified { rows: expected_id - steps.start - skipped, skipped })
+}
+
+/// Failure of the lowest range of a parallel verification seen so far,
+/// keyed by the first step of the range.
+#[derive(Default)]
+pub(super) struct FirstFailure(Mutex<Option<(usize, GenError)>>);
+
+impl FirstFailure {
+    /// Whether a range below `start` has failed, so it needn't be checked.
+    pub(super) fn is_below(&self, start: usize) -> bool {
+        let failure = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        failure.as_ref().is_some_and(|(failed, _)| *failed < start)
+    }
+
+    pub(super) fn record(&self, start: usize, err: GenError) {
+        let mut failure = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if failure.as_ref().is_none_or(|(failed, _)| start < *failed) {
+            *failure = Some((start, err));
+        }
+    }
+
+    pub(super) fn take(self) -> Option<GenError> {
+        self.0.into_inner().unwrap_or_else(|err| err.into_inner()).map(|(_, err)| err)
+    }
+}
+
+/// Outcome of `--sample_verify`.
+pub(super) struct SampleReport {
+    /// Rows the sample was drawn from.
+    pub(super) rows: usize,
+    pub(super) sampled: usize,
+    /// Sampled miner-stored rows, whose seed chain was checked but not their data.
+    pub(super) skipped: usize,
+    pub(super) failures: Vec<GenError>,
+}
+
+/// Checks a random sample of the rows and prints every failure along with
+/// the size of the sample. Each sampled chunk is regenerated on its own, so
+/// the cost grows with the sample rather than with the table.
+pub(super) fn sample_verify(args: &VerifyArgs, sample: &Sample) -> Result<(), GenError> {
+    let report = match args.backend {
+        Backend::Sqlite => sample_table(args, sample)?,
+        Backend::RawFile => sample_rawfile(args, sample)?,
+    };
+    for failure in &report.failures {
+        println!("{}", failure);
+    }
+    println!(
+        "Sampled {} of {} rows ({}%) with --sample_seed {}: {} failed ({} skipped as miner-stored data)",
+        report.sampled, report.rows, sample.percent, sample.seed, report.failures.len(), report.skipped
+    );
+    if !report.failures.is_empty() {
+        return Err(io::Error::other(format!("{} of {} sampled rows failed", report.failures.len(), report.sampled)).into());
+    }
+    Ok(())
+}
+
+/// `sample.percent` of `range`, at least one, in ascending order.
+pub(super) fn sample_steps(range: std::ops::Range<usize>, sample: &Sample) -> Vec<usize> {
+    use rand::SeedableRng;
+
+    if range.is_empty() {
+        return Vec::new();
+    }
+    let amount = ((range.len() as f64 * sample.percent / 100.0).ceil() as usize).clamp(1, range.len());
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(sample.seed);
+    let mut steps = rand::seq::index::sample(&mut rng, range.len(), amount).into_vec();
+    steps.sort_unstable();
+    steps.into_iter().map(|step| range.start + step).collect()
+}
+
+pub(super) fn sample_table(args: &VerifyArgs, sample: &Sample) -> Result<SampleReport, GenError> {
+    let conn = open_db(&args.path, args.key.as_deref())?;
+    let table = args.seed.as_str();
+    let max_id: Option<i64> = conn.query_row(&format!("SELECT MAX(id) FROM DB{}", table), params![], |row| row.get(0))?;
+    let layout = IdLayout::stored(&conn, table)?;
+    let from = layout.step_from(args.from);
+    let end = max_id.map_or(0, |max_id| layout.step_from(max_id as usize + 1));
+    let steps = sample_steps(from..end.max(from), sample);
+    let mut report = SampleReport { rows: end.saturating_sub(from), sampled: steps.len(), skipped: 0, failures: Vec::new() };
+    if steps.is_empty() {
+        return Ok(report);
+    }
+    let settings = ChainSettings::stored(&conn, table, args.chunk_size)?;
+
+    // Sampled rows close together share the replay from their checkpoint.
+    let mut replay: Option<(usize, ChunkGenerator)> = None;
+    for step in steps {
+        let id = layout.id(step);
+        let stored = match read_row(&conn, table, id)? {
+            Some(stored) => stored.decoded(settings.codec)?,
+            None => {
+                report.failures.push(GenError::Mismatch { id: id as usize, column: "id" });
+                continue;
+            }
+        };
+        let (checkpoint, seed) = checkpoint_at(&conn, table, settings.hash_algo, settings.chunk_size, step)?;
+        let (mut position, mut chunk_gen) = match replay.take() {
+            Some((position, chunk_gen)) if (checkpoint..=step).contains(&position) => (position, chunk_gen),
+            _ => {
+                let chunk_gen = ChunkGenerator::new(seed, settings.chunk_size, settings.hash_algo.hasher())
+                    .with_rng(settings.rng)
+                    .with_charset(settings.charset);
+                (checkpoint, chunk_gen)
+            }
+        };
+        while position < step {
+            chunk_gen.next_hash_only();
+            position += 1;
+        }
+        let (chunk_data, chunk_hash) = chunk_gen.next();
+        replay = Some((step + 1, chunk_gen));
+        match stored.mismatch(&chunk_data, &chunk_hash, settings.hash_bytes) {
+            Some(column) => report.failures.push(GenError::Mismatch { id: id as usize, column }),
+            None if stored.is_user_data() => report.skipped += 1,
+            None => {}
+        }
+    }
+    Ok(report)
+}
+
+/// Like `sample_table`, but every chunk of a raw store is regenerated from
+/// the hash of the one before it, so nothing is replayed.
+pub(super) fn sample_rawfile(args: &VerifyArgs, sample: &Sample) -> Result<SampleReport, GenError> {
+    let (meta, store) = open_rawfile(&args.path, &args.seed)?;
+    let len = store.len() as usize;
+    let ids = sample_steps(args.from..len.max(args.from), sample);
+    let mut report = SampleReport { rows: len.saturating_sub(args.from), sampled: ids.len(), skipped: 0, failures: Vec::new() };
+    for id in ids {
+        let stored = raw_row(&store, id)?.expect("id is below the store length");
+        let (chunk_data, chunk_hash) = ChunkGenerator::new(raw_seed(&store, &meta, id)?, meta.chunk_size, meta.hash_algo.hasher())
+            .with_rng(meta.rng)
+            .with_charset(meta.charset)
+            .next();
+        if let Some(column) = stored.mismatch(&chunk_data, &chunk_hash, meta.hash_algo.output_len()) {
+            report.failures.push(GenError::Mismatch { id, column });
+        }
+    }
+    Ok(report)
+}
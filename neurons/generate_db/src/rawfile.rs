@@ -0,0 +1,262 @@
+//! Flat-file chunk store used by `--backend rawfile`.
+//!
+//! `{path}` holds the chunks back to back in fixed-size records, so chunk `i`
+//! starts at `i * record_len`. `{path}.idx` holds the hash of every chunk at
+//! `i * hash_len`; since that hash is also the seed of the next chunk, any
+//! chunk can be regenerated from the index without replaying the chain. The
+//! table settings live in `{path}.json`.
+//!
+//! A chunk counts as stored once both its record and its hash are on disk.
+//! Records are always written before their hashes, and a torn tail left by a
+//! crash is cut off when the store is opened.
+//!
+//! `export --format raw` writes the same records back to back, followed by a
+//! `FOOTER_LEN` byte footer holding the settings and chunk count, so that
+//! `import` can rebuild the table from the exported file alone.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::{Charset, HashAlgo, RngAlgo};
+
+/// Length of the footer of an exported raw file.
+pub const FOOTER_LEN: usize = 256;
+
+/// Last bytes of the footer; the digit is the version of the layout.
+const FOOTER_MAGIC: &[u8; 8] = b"TSRAWFT1";
+
+/// Settings of a raw store, fixed when it is created.
+pub struct RawMeta {
+    pub table: String,
+    pub hash_algo: HashAlgo,
+    pub rng: RngAlgo,
+    pub charset: Charset,
+    pub chunk_size: usize,
+    pub genesis_seed: Vec<u8>,
+}
+
+fn invalid(reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+impl RawMeta {
+    fn path(path: &str) -> String {
+        format!("{}.json", path)
+    }
+
+    /// Reads the settings of the store at `path`, or `None` if there is none.
+    pub fn load(path: &str) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(Self::path(path)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|err| invalid(format!("{}: {}", Self::path(path), err)))?;
+        let field = |key: &str| {
+            value[key].as_str().ok_or_else(|| invalid(format!("{}: missing {}", Self::path(path), key)))
+        };
+        Ok(Some(RawMeta {
+            table: field("table")?.to_string(),
+            hash_algo: HashAlgo::from_name(field("hash_algo")?)
+                .ok_or_else(|| invalid(format!("{}: unknown hash_algo", Self::path(path))))?,
+            rng: RngAlgo::from_name(field("rng")?)
+                .ok_or_else(|| invalid(format!("{}: unknown rng", Self::path(path))))?,
+            // Stores created before the charset was configurable are alphanumeric.
+            charset: match value["charset"].as_str() {
+                Some(name) => Charset::from_name(name)
+                    .ok_or_else(|| invalid(format!("{}: unknown charset", Self::path(path))))?,
+                None => Charset::Alphanumeric,
+            },
+            chunk_size: value["chunk_size"].as_u64()
+                .ok_or_else(|| invalid(format!("{}: missing chunk_size", Self::path(path))))? as usize,
+            genesis_seed: hex::decode(field("genesis_seed")?)
+                .map_err(|err| invalid(format!("{}: genesis_seed: {}", Self::path(path), err)))?,
+        }))
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let value = serde_json::json!({
+            "table": self.table,
+            "hash_algo": self.hash_algo.name(),
+            "rng": self.rng.name(),
+            "charset": self.charset.name(),
+            "chunk_size": self.chunk_size,
+            "genesis_seed": hex::encode(&self.genesis_seed),
+        });
+        fs::write(Self::path(path), value.to_string())
+    }
+
+    /// Bytes per stored chunk: the characters plus their hex hash.
+    pub fn record_len(&self) -> u64 {
+        (self.chunk_size + 2 * self.hash_algo.output_len()) as u64
+    }
+
+    pub fn hash_len(&self) -> u64 {
+        self.hash_algo.output_len() as u64
+    }
+
+    /// Footer of an exported raw file of `count` chunks. Names and the
+    /// genesis seed are zero padded, integers little-endian:
+    ///
+    /// - 0..64: table
+    /// - 64..80: hash_algo
+    /// - 80..96: rng
+    /// - 96..112: charset
+    /// - 112..120: chunk_size
+    /// - 120..128: count
+    /// - 128..192: genesis_seed
+    /// - 192..248: reserved, zero
+    /// - 248..256: `TSRAWFT1`
+    pub fn footer(&self, count: u64) -> io::Result<[u8; FOOTER_LEN]> {
+        let mut footer = [0u8; FOOTER_LEN];
+        let mut put = |range: std::ops::Range<usize>, field: &str, bytes: &[u8]| {
+            if bytes.len() > range.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is {} bytes, the raw footer has room for {}", field, bytes.len(), range.len()),
+                ));
+            }
+            footer[range.start..range.start + bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        };
+        put(0..64, "table", self.table.as_bytes())?;
+        put(64..80, "hash_algo", self.hash_algo.name().as_bytes())?;
+        put(80..96, "rng", self.rng.name().as_bytes())?;
+        put(96..112, "charset", self.charset.name().as_bytes())?;
+        put(112..120, "chunk_size", &(self.chunk_size as u64).to_le_bytes())?;
+        put(120..128, "count", &count.to_le_bytes())?;
+        put(128..192, "genesis_seed", &self.genesis_seed)?;
+        put(248..256, "magic", FOOTER_MAGIC)?;
+        Ok(footer)
+    }
+
+    /// Settings and chunk count recorded in `footer`.
+    pub fn from_footer(footer: &[u8; FOOTER_LEN]) -> io::Result<(Self, u64)> {
+        if &footer[248..] != FOOTER_MAGIC {
+            return Err(invalid("no raw export footer at the end of the file".to_string()));
+        }
+        let name = |range: std::ops::Range<usize>, field: &str| {
+            let bytes = &footer[range];
+            let len = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+            std::str::from_utf8(&bytes[..len])
+                .map(str::to_string)
+                .map_err(|_| invalid(format!("footer: {} is not text", field)))
+        };
+        let number = |range: std::ops::Range<usize>| u64::from_le_bytes(footer[range].try_into().expect("8 byte field"));
+        let hash_algo = HashAlgo::from_name(&name(64..80, "hash_algo")?)
+            .ok_or_else(|| invalid("footer: unknown hash_algo".to_string()))?;
+        let meta = RawMeta {
+            table: name(0..64, "table")?,
+            hash_algo,
+            rng: RngAlgo::from_name(&name(80..96, "rng")?).ok_or_else(|| invalid("footer: unknown rng".to_string()))?,
+            charset: Charset::from_name(&name(96..112, "charset")?)
+                .ok_or_else(|| invalid("footer: unknown charset".to_string()))?,
+            chunk_size: number(112..120) as usize,
+            genesis_seed: footer[128..128 + hash_algo.output_len()].to_vec(),
+        };
+        Ok((meta, number(120..128)))
+    }
+}
+
+/// Removes the store at `path`, if there is one.
+pub fn remove(path: &str) -> io::Result<()> {
+    for file in [path.to_string(), format!("{}.idx", path), RawMeta::path(path)] {
+        match fs::remove_file(&file) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Open data and index files of a raw store.
+pub struct RawStore {
+    data: File,
+    index: File,
+    record_len: u64,
+    hash_len: u64,
+    len: u64,
+}
+
+impl RawStore {
+    /// Opens the store at `path`, creating its files if `write` is set.
+    pub fn open(path: &str, meta: &RawMeta, write: bool) -> io::Result<Self> {
+        let open = |file: &str| OpenOptions::new().read(true).write(write).create(write).open(file);
+        let data = open(path)?;
+        let index = open(&format!("{}.idx", path))?;
+        let record_len = meta.record_len();
+        let hash_len = meta.hash_len();
+        let len = (data.metadata()?.len() / record_len).min(index.metadata()?.len() / hash_len);
+        let mut store = RawStore { data, index, record_len, hash_len, len };
+        if write {
+            store.truncate(len)?;
+        }
+        Ok(store)
+    }
+
+    /// Number of chunks stored.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Record and hash of chunk `id`, or `None` past the end of the store.
+    pub fn read(&self, id: u64) -> io::Result<Option<(Vec<u8>, Vec<u8>)>> {
+        if id >= self.len {
+            return Ok(None);
+        }
+        let mut record = vec![0u8; self.record_len as usize];
+        (&self.data).seek(SeekFrom::Start(id * self.record_len))?;
+        (&self.data).read_exact(&mut record)?;
+        Ok(Some((record, self.hash(id)?)))
+    }
+
+    /// Hash of chunk `id`, which is also the seed of chunk `id + 1`.
+    pub fn hash(&self, id: u64) -> io::Result<Vec<u8>> {
+        let mut hash = vec![0u8; self.hash_len as usize];
+        (&self.index).seek(SeekFrom::Start(id * self.hash_len))?;
+        (&self.index).read_exact(&mut hash)?;
+        Ok(hash)
+    }
+
+    /// Drops every chunk from `len` on.
+    pub fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.index.set_len(len * self.hash_len)?;
+        self.data.set_len(len * self.record_len)?;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Flushes both files to the disk.
+    pub fn sync(&self) -> io::Result<()> {
+        self.data.sync_data()?;
+        self.index.sync_data()
+    }
+
+    /// Appends `chunks` as one sequential write to each file. With `sync` the
+    /// records reach the disk before their hashes, and both before returning.
+    pub fn append(&mut self, chunks: &[(Vec<u8>, Vec<u8>)], sync: bool) -> io::Result<()> {
+        let mut records = Vec::with_capacity(chunks.len() * self.record_len as usize);
+        let mut hashes = Vec::with_capacity(chunks.len() * self.hash_len as usize);
+        for (record, hash) in chunks {
+            if record.len() as u64 != self.record_len || hash.len() as u64 != self.hash_len {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk does not fit the store's record size"));
+            }
+            records.extend_from_slice(record);
+            hashes.extend_from_slice(hash);
+        }
+
+        self.data.seek(SeekFrom::Start(self.len * self.record_len))?;
+        self.data.write_all(&records)?;
+        if sync {
+            self.data.sync_data()?;
+        }
+        self.index.seek(SeekFrom::Start(self.len * self.hash_len))?;
+        self.index.write_all(&hashes)?;
+        if sync {
+            self.index.sync_data()?;
+        }
+        self.len += chunks.len() as u64;
+        Ok(())
+    }
+}
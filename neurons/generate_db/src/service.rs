@@ -0,0 +1,45 @@
+//! `generate`, for services that run generation from a tokio runtime.
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{CancelToken, GenError, GenerateConfig, ProgressUpdate, Summary, generate_blocking};
+
+/// Cancels its token when dropped before the run is done, i.e. along with
+/// the future of `generate`.
+struct CancelOnDrop(Option<CancelToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.0.take() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Runs `config` on tokio's blocking pool, so its SQLite writes never hold
+/// up the runtime's workers, and sends a `ProgressUpdate` on `progress`
+/// after every committed batch. The channel is unbounded so that a slow
+/// receiver can't stall the writes; once it is dropped, updates are
+/// discarded and the run goes on. A panic of the run is resumed here.
+///
+/// The run stops at its next batch boundary once `cancel` is cancelled, or
+/// once the returned future is dropped, which cancels `cancel` as well.
+pub async fn generate(
+    config: GenerateConfig,
+    cancel: CancelToken,
+    progress: UnboundedSender<ProgressUpdate>,
+) -> Result<Summary, GenError> {
+    let mut on_drop = CancelOnDrop(Some(cancel.clone()));
+    let run = tokio::task::spawn_blocking(move || {
+        generate_blocking(&config, &cancel, move |update| {
+            let _ = progress.send(update);
+        })
+    });
+    let result = run.await;
+    on_drop.0 = None;
+    match result {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(std::io::Error::other(format!("generation task was cancelled: {}", err)).into()),
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+use common::{storer, storer_command, temp_db, temp_dir};
+
+/// Stderr of a run that is expected to fail before touching the database.
+fn rejection(args: &[&str]) -> String {
+    let path = temp_db("arguments");
+    let output = storer_command()
+        .args(["--path", path.to_str().unwrap(), "--seed", "arguments", "--size", "16"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?} was accepted", args);
+    assert!(!path.exists());
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+#[test]
+fn numeric_arguments_name_the_bad_value() {
+    assert_eq!(rejection(&["--n", "abc"]), "Error: invalid value 'abc' for --n: expected a non-negative integer");
+    assert_eq!(
+        rejection(&["--n=-5"]),
+        "Error: invalid value '-5' for --n: expected a non-negative integer without a sign"
+    );
+    assert_eq!(
+        rejection(&["--n", "+5"]),
+        "Error: invalid value '+5' for --n: expected a non-negative integer without a sign"
+    );
+    assert_eq!(
+        rejection(&["--n", "9223372036854775808"]),
+        "Error: invalid value '9223372036854775808' for --n: larger than the maximum 9223372036854775807"
+    );
+    for arg in ["--n", "--batch_size", "--threads", "--cache_mb", "--wal_autocheckpoint"] {
+        let args = if arg == "--n" { vec![arg, "0"] } else { vec!["--n", "10", arg, "0"] };
+        assert_eq!(rejection(&args), format!("Error: invalid value '0' for {}: must be at least 1", arg));
+    }
+}
+
+#[test]
+fn ids_beyond_the_sqlite_rowid_range_are_rejected() {
+    let output = storer(&["fetch", "--path", "unused.db", "--seed", "arguments", "--id", "18446744073709551615"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("for --id: larger than the maximum 9223372036854775807"));
+    assert!(!std::path::Path::new("unused.db").exists());
+}
+
+#[test]
+fn missing_parent_directories_are_created_unless_no_mkdir() {
+    let dir = temp_dir("arguments_mkdir");
+    let path = dir.join("sub").join("table.db");
+    let generate = |path: &std::path::Path, extra: &[&str]| {
+        storer_command()
+            .args(["--path", path.to_str().unwrap(), "--seed", "mkdir", "--size", "16", "--n", "5"])
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let refused = generate(&path, &["--no_mkdir"]);
+    assert_eq!(refused.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains(&format!("directory {} does not exist", dir.join("sub").display())), "{}", stderr);
+    assert!(!dir.exists());
+
+    assert!(generate(&path, &[]).status.success());
+    assert!(path.exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
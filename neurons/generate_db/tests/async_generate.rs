@@ -0,0 +1,68 @@
+#![cfg(feature = "async")]
+
+use storer_db_project::{CancelToken, GenerateConfig, ProgressUpdate, generate};
+
+mod common;
+use common::{storer, temp_db};
+
+#[tokio::test]
+async fn generate_reports_every_batch_and_matches_the_binary() {
+    let path = temp_db("async_generate");
+    let flags = ["--path", path.to_str().unwrap(), "--seed", "service", "--n", "250", "--size", "32", "--batch_size", "100"];
+    let run = |flags: &[&str]| {
+        let config = GenerateConfig::from_flags(flags.iter().copied()).unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        async move {
+            let summary = generate(config, CancelToken::new(), sender).await.unwrap();
+            let mut rows = Vec::new();
+            while let Some(ProgressUpdate { table, rows: committed, total }) = receiver.recv().await {
+                assert_eq!((table.as_str(), total), ("DBservice", 250));
+                rows.push(committed);
+            }
+            (summary, rows)
+        }
+    };
+
+    let (summary, rows) = run(&flags).await;
+    assert_eq!(rows, [0, 100, 200, 250]);
+    let table = &summary.tables[0];
+    assert_eq!((table.table.as_str(), table.status, table.rows_written), ("DBservice", "written", 250));
+    assert!(summary.failed.is_empty());
+    let final_seed = storer(&["final-seed", "--n", "250", "--size", "32", "--seed", "service"]);
+    assert_eq!(String::from_utf8_lossy(&final_seed.stdout).trim(), hex::encode(&table.final_seed));
+
+    // A finished table is reported once, at its full size.
+    let (summary, rows) = run(&flags).await;
+    assert_eq!(rows, [250]);
+    assert_eq!(summary.tables[0].status, "complete");
+
+    let refused = GenerateConfig::from_flags([&flags[..], &["--max_runtime_secs", "5"]].concat()).err().unwrap();
+    assert_eq!(refused.code(), "invalid_arg");
+    assert!(GenerateConfig::from_flags(["verify", "--path", "x.db", "--seed", "service"]).is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn dropping_the_future_cancels_the_run() {
+    let path = temp_db("async_cancel");
+    let flags = ["--path", path.to_str().unwrap(), "--seed", "cancel", "--n", "20000", "--size", "32", "--batch_size", "100"];
+    let config = GenerateConfig::from_flags(flags).unwrap();
+    let cancel = CancelToken::new();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut run = Box::pin(generate(config.clone(), cancel.clone(), sender));
+    tokio::select! {
+        _ = &mut run => panic!("the run ended before its first batch was reported"),
+        update = receiver.recv() => assert!(update.is_some()),
+    }
+    drop(run);
+    assert!(cancel.is_cancelled());
+    // The sender goes with the run, once it has stopped.
+    while receiver.recv().await.is_some() {}
+
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let summary = generate(config, CancelToken::new(), sender).await.unwrap();
+    let table = &summary.tables[0];
+    assert_eq!(table.status, "written");
+    assert!(table.rows_written > 0 && table.rows_written < 20_000, "{}", table.rows_written);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use storer_db_project::{ChunkGenerator, ChunkHasher, Sha256Hasher};
+
+mod common;
+use common::{storer, storer_command, succeeds, temp_db};
+
+fn challenge(path: &Path, id: usize) -> bool {
+    succeeds(&["challenge", "--path", path.to_str().unwrap(), "--seed", "ckpt", "--id", &id.to_string()])
+}
+
+#[test]
+fn checkpoint_regeneration_matches_full_replay() {
+    let path = temp_db("checkpoints");
+    assert!(succeeds(&[
+        "--path", path.to_str().unwrap(), "--n", "60", "--size", "32", "--seed", "ckpt",
+        "--checkpoint_interval", "7", "--threads", "3", "--json",
+    ]));
+
+    // Each checkpoint holds the seed the full chain reaches at that id: the
+    // genesis seed for row 0 and the previous row's rng_state after that.
+    let conn = Connection::open(&path).unwrap();
+    let mut stmt = conn.prepare("SELECT id, seed FROM seed_checkpoints WHERE table_name = 'ckpt' ORDER BY id").unwrap();
+    let checkpoints: Vec<(i64, Vec<u8>)> = stmt.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let ids: Vec<i64> = checkpoints.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, (0..60).step_by(7).collect::<Vec<_>>());
+    assert_eq!(checkpoints[0].1, ChunkGenerator::salted_genesis_seed(&Sha256Hasher, "DBckpt", 32));
+    for (id, seed) in &checkpoints[1..] {
+        let previous: Vec<u8> = conn
+            .query_row("SELECT rng_state FROM DBckpt WHERE id = ?", params![id - 1], |row| row.get(0))
+            .unwrap();
+        assert_eq!(seed, &previous);
+    }
+
+    // Regenerating from the nearest checkpoint and replaying from genesis
+    // both reproduce the stored rows.
+    for id in [0, 6, 7, 8, 59] {
+        assert!(challenge(&path, id));
+    }
+    assert!(succeeds(&["verify", "--path", path.to_str().unwrap(), "--seed", "ckpt", "--from", "30"]));
+    conn.execute("DELETE FROM seed_checkpoints", params![]).unwrap();
+    for id in [0, 6, 7, 8, 59] {
+        assert!(challenge(&path, id));
+    }
+    drop(stmt);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn repair_regenerates_a_torn_tail() {
+    let path = temp_db("checkpoints_repair");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "60", "--size", "32", "--seed", "ckpt", "--checkpoint_interval", "7"]));
+
+    // Tear a row and the checkpoint before it, and lose the last row.
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBckpt SET data = x'00' WHERE id = 57", params![]).unwrap();
+    conn.execute("UPDATE seed_checkpoints SET seed = x'00' WHERE id = 56", params![]).unwrap();
+    conn.execute("DELETE FROM DBckpt WHERE id = 59", params![]).unwrap();
+    assert!(!succeeds(&["verify", "--path", path_str, "--seed", "ckpt"]));
+
+    assert!(succeeds(&["repair", "--path", path_str, "--seed", "ckpt"]));
+    assert!(succeeds(&["verify", "--path", path_str, "--seed", "ckpt"]));
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM DBckpt", params![], |row| row.get(0)).unwrap();
+    assert_eq!(rows, 59);
+    assert!(challenge(&path, 57));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn regenerate_touches_only_its_range() {
+    let path = temp_db("checkpoints_regenerate");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "40", "--size", "32", "--seed", "ckpt", "--checkpoint_interval", "7"]));
+    let regenerate = |args: &[&str]| {
+        storer_command()
+            .args(["regenerate", "--path", path_str, "--seed", "ckpt", "--from", "10", "--to", "15"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // The printed hashes are the stored ones.
+    let conn = Connection::open(&path).unwrap();
+    let output = regenerate(&[]);
+    assert!(output.status.success());
+    let printed: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(printed.len(), 5);
+    for (id, line) in (10..15).zip(&printed) {
+        let hash: String = conn.query_row("SELECT hash FROM DBckpt WHERE id = ?", params![id], |row| row.get(0)).unwrap();
+        assert_eq!(line["id"], id);
+        assert_eq!(line["hash"], hash);
+    }
+
+    // --write fixes a row inside the range and leaves one outside alone.
+    conn.execute("UPDATE DBckpt SET data = x'00' WHERE id IN (12, 30)", params![]).unwrap();
+    assert!(regenerate(&["--write"]).status.success());
+    assert!(challenge(&path, 12));
+    assert!(!challenge(&path, 30));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn parallel_verify_matches_sequential() {
+    let path = temp_db("checkpoints_parallel_verify");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "100", "--size", "32", "--seed", "ckpt", "--checkpoint_interval", "9"]));
+    let verify = |args: &[&str]| {
+        let output = storer_command()
+            .args(["verify", "--path", path_str, "--seed", "ckpt"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let same_as_sequential = |from: &str| {
+        let sequential = verify(&["--from", from]);
+        for threads in ["2", "4", "16"] {
+            assert_eq!(verify(&["--from", from, "--threads", threads]), sequential, "--from {} --threads {}", from, threads);
+        }
+        sequential
+    };
+
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBckpt SET data = x'0102', hash = 'user', flag = 'T' WHERE id = 20", params![]).unwrap();
+    assert_eq!(same_as_sequential("0").1.trim(), "Verified 99 rows (1 skipped as miner-stored data)");
+    assert_eq!(same_as_sequential("40").1.trim(), "Verified 60 rows (0 skipped as miner-stored data)");
+
+    // Each failure is reported at the lowest divergent id, whichever worker finds it.
+    conn.execute("UPDATE DBckpt SET data = upper(data) WHERE id = 75", params![]).unwrap();
+    assert!(same_as_sequential("0").2.contains("row 75 does not match the regenerated chunk (data differs)"));
+    conn.execute("DELETE FROM DBckpt WHERE id = 53", params![]).unwrap();
+    assert!(same_as_sequential("0").2.contains("row 53 does not match the regenerated chunk (id differs)"));
+    assert!(same_as_sequential("60").2.contains("row 75 does not match"));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn sample_verify_is_reproducible() {
+    let path = temp_db("checkpoints_sample_verify");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "500", "--size", "32", "--seed", "ckpt", "--checkpoint_interval", "50"]));
+    let sample = |percent: &str, seed: &str| {
+        let output = storer(&["verify", "--path", path_str, "--seed", "ckpt", "--sample_verify", percent, "--sample_seed", seed]);
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    assert_eq!(
+        sample("10", "42"),
+        (true, "Sampled 50 of 500 rows (10%) with --sample_seed 42: 0 failed (0 skipped as miner-stored data)\n".to_string())
+    );
+
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBckpt SET data = upper(data) WHERE id % 5 = 0", params![]).unwrap();
+    let (passed, report) = sample("10", "42");
+    assert!(!passed);
+    assert_eq!(sample("10", "42"), (passed, report.clone()));
+    let failed: Vec<&str> = report.lines().filter(|line| line.starts_with("row ")).collect();
+    assert!(!failed.is_empty());
+    for line in &failed {
+        let id: usize = line.split(' ').nth(1).unwrap().parse().unwrap();
+        assert_eq!(id % 5, 0, "{}", line);
+    }
+    assert!(report.ends_with(&format!("with --sample_seed 42: {} failed (0 skipped as miner-stored data)\n", failed.len())));
+    assert_ne!(sample("10", "43").1, report);
+
+    // A full sample finds every damaged row.
+    assert!(sample("100", "1").1.contains("Sampled 500 of 500 rows (100%) with --sample_seed 1: 100 failed"));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn hash_only_verify_skips_the_data() {
+    let path = temp_db("checkpoints_verify_only_hashes");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "80", "--size", "64", "--seed", "ckpt", "--checkpoint_interval", "16"]));
+    let verify = |args: &[&str]| {
+        let output = storer_command()
+            .args(["verify", "--path", path_str, "--seed", "ckpt"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let full = verify(&[]);
+    assert!(full.0);
+    assert_eq!(verify(&["--verify_only_hashes"]), full);
+    assert_eq!(verify(&["--verify_only_hashes", "--threads", "4", "--from", "0"]), full);
+
+    // Damaged data goes unnoticed, a damaged hash or seed does not.
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBckpt SET data = upper(data) WHERE id = 30", params![]).unwrap();
+    assert!(!verify(&[]).0);
+    assert!(verify(&["--verify_only_hashes"]).0);
+    conn.execute("UPDATE DBckpt SET hash = upper(hash) WHERE id = 50", params![]).unwrap();
+    assert!(verify(&["--verify_only_hashes"]).2.contains("row 50 does not match the regenerated chunk (hash differs)"));
+    conn.execute("UPDATE DBckpt SET rng_state = x'00' WHERE id = 40", params![]).unwrap();
+    assert!(verify(&["--verify_only_hashes"]).2.contains("row 40 does not match the regenerated chunk (rng_state differs)"));
+    drop(conn);
+
+    // Hash-only tables take the same path without the flag.
+    let hashes = temp_db("checkpoints_verify_only_hashes_hashed");
+    let hashes_str = hashes.to_str().unwrap();
+    assert!(succeeds(&["--path", hashes_str, "--n", "80", "--size", "64", "--seed", "ckpt", "--hash"]));
+    assert!(succeeds(&["verify", "--path", hashes_str, "--seed", "ckpt"]));
+    let conn = Connection::open(&hashes).unwrap();
+    conn.execute("UPDATE DBckpt SET hash = upper(hash) WHERE id = 10", params![]).unwrap();
+    assert!(!succeeds(&["verify", "--path", hashes_str, "--seed", "ckpt"]));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&hashes);
+}
+
+#[test]
+fn progress_proofs_hash_the_replayed_seed() {
+    let full = temp_db("progress_full");
+    let hashes = temp_db("progress_hashes");
+    for (path, extra) in [(&full, None), (&hashes, Some("--hash"))] {
+        let mut args = vec!["--path", path.to_str().unwrap(), "--n", "30", "--size", "32", "--seed", "ckpt", "--checkpoint_interval", "7"];
+        args.extend(extra);
+        assert!(succeeds(&args));
+    }
+    let prove = |path: &Path, id: &str, nonce: &str| {
+        let output = storer(&["prove-progress", "--path", path.to_str().unwrap(), "--seed", "ckpt", "--id", id, "--nonce", nonce]);
+        output.status.success().then(|| String::from_utf8(output.stdout).unwrap().trim().to_string())
+    };
+
+    let conn = Connection::open(&full).unwrap();
+    let seed: Vec<u8> = conn.query_row("SELECT rng_state FROM DBckpt WHERE id = 20", params![], |row| row.get(0)).unwrap();
+    let expected = Sha256Hasher.hash(&[&seed[..], &[0xc0, 0xff, 0xee]].concat());
+    let proof = prove(&full, "20", "c0ffee").unwrap();
+    assert_eq!(proof, hex::encode(expected));
+    // The validator's hash-only table gives the same answer, a new nonce another.
+    assert_eq!(prove(&hashes, "20", "c0ffee").as_ref(), Some(&proof));
+    assert_ne!(prove(&full, "20", "c0ffef").as_ref(), Some(&proof));
+
+    // The seed is replayed, not read from the row.
+    conn.execute("UPDATE DBckpt SET rng_state = zeroblob(32) WHERE id = 20", params![]).unwrap();
+    assert_eq!(prove(&full, "20", "c0ffee").as_ref(), Some(&proof));
+    assert_eq!(prove(&full, "30", "c0ffee"), None);
+    drop(conn);
+    let _ = std::fs::remove_file(&full);
+    let _ = std::fs::remove_file(&hashes);
+}
+
+#[test]
+fn rehash_restores_hashes_from_trusted_data() {
+    let path = temp_db("rehash");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--n", "20", "--size", "32", "--seed", "ckpt", "--hash_bytes", "16"]));
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBckpt SET hash = 'bad' WHERE id IN (3, 11)", params![]).unwrap();
+    // Row 15's data no longer matches its own trailing hex.
+    conn.execute("UPDATE DBckpt SET data = 'x' || substr(data, 2), hash = 'bad' WHERE id = 15", params![]).unwrap();
+    assert!(!succeeds(&["verify", "--path", path_str, "--seed", "ckpt"]));
+
+    let output = storer(&["rehash", "--path", path_str, "--seed", "ckpt", "--json"]);
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!((summary["rows"].as_u64(), summary["changed"].as_u64()), (Some(19), Some(2)));
+    assert_eq!((summary["bad_data"].as_u64(), summary["first_bad_data_id"].as_u64()), (Some(1), Some(15)));
+    let hash: String = conn.query_row("SELECT hash FROM DBckpt WHERE id = 15", params![], |row| row.get(0)).unwrap();
+    assert_eq!(hash, "bad");
+
+    assert!(succeeds(&["repair", "--path", path_str, "--seed", "ckpt"]));
+    assert!(succeeds(&["verify", "--path", path_str, "--seed", "ckpt"]));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by the integration tests. Every test binary compiles its
+//! own copy, so the ones it doesn't call are dead code there.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// `{name}_{pid}` in the temp directory, with any file left there removed.
+/// The pid keeps concurrent `cargo test` runs apart.
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// `{name}_{pid}.db` in the temp directory, with any file left there removed.
+pub fn temp_db(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// `{name}_{pid}` in the temp directory, with anything left there removed.
+/// The directory itself is not created.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// The generator binary, to be given arguments.
+pub fn storer_command() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_storer_db_project"))
+}
+
+/// Runs the generator to completion.
+pub fn storer(args: &[&str]) -> Output {
+    storer_command().args(args).output().unwrap()
+}
+
+/// Whether a run of the generator succeeded.
+pub fn succeeds(args: &[&str]) -> bool {
+    storer(args).status.success()
+}
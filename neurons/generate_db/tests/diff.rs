@@ -0,0 +1,36 @@
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer, temp_db};
+
+#[test]
+fn diff_reports_the_first_divergent_row() {
+    let (miner, reference) = (temp_db("diff_miner"), temp_db("diff_reference"));
+    let (miner_str, reference_str) = (miner.to_str().unwrap(), reference.to_str().unwrap());
+    assert!(storer(&["--path", miner_str, "--n", "30", "--size", "16", "--seed", "diff"]).status.success());
+    assert!(storer(&["--path", reference_str, "--n", "30", "--size", "16", "--seed", "diff", "--compress", "zstd"]).status.success());
+    let diff = || {
+        let output = storer(&["diff", "--db_a", miner_str, "--db_b", reference_str, "--seed", "diff"]);
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.success(), report)
+    };
+
+    // Compression is undone before comparing.
+    let (same, report) = diff();
+    assert!(same);
+    assert_eq!(report["matching_rows"], 30);
+
+    let conn = Connection::open(&miner).unwrap();
+    conn.execute("UPDATE DBdiff SET data = x'00' WHERE id = 20", params![]).unwrap();
+    conn.execute("DELETE FROM DBdiff WHERE id >= 25", params![]).unwrap();
+    let (same, report) = diff();
+    assert!(!same);
+    assert_eq!((report["id"].clone(), report["column"].clone()), (20.into(), "data".into()));
+
+    conn.execute("DELETE FROM DBdiff WHERE id = 20", params![]).unwrap();
+    let (_, report) = diff();
+    assert_eq!((report["id"].clone(), report["missing_from"].clone()), (20.into(), "db_a".into()));
+    drop(conn);
+    let _ = std::fs::remove_file(&miner);
+    let _ = std::fs::remove_file(&reference);
+}
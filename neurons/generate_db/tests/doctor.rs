@@ -0,0 +1,69 @@
+use std::process::Output;
+
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer, temp_db};
+
+/// Status of every check of a `doctor --json` run, by name.
+fn statuses(output: &Output) -> Vec<(String, String)> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|check| check.get("check").is_some())
+        .map(|check| (check["check"].as_str().unwrap().to_string(), check["status"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+fn status<'a>(statuses: &'a [(String, String)], name: &str) -> &'a str {
+    &statuses.iter().find(|(check, _)| check == name).unwrap().1
+}
+
+#[test]
+fn doctor_reports_each_check() {
+    let path = temp_db("doctor");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(&["--path", path_str, "--n", "300", "--size", "64", "--seed", "doc"]).status.success());
+    let doctor = |args: &[&str]| storer(&[&["doctor", "--path", path_str, "--seed", "doc", "--json"], args].concat());
+
+    let healthy = doctor(&[]);
+    assert!(healthy.status.success(), "{}", String::from_utf8_lossy(&healthy.stdout));
+    let all_pass = ["schema", "metadata", "trailing_rows", "disk_space", "pragmas"].map(|name| (name.to_string(), "pass".to_string()));
+    assert_eq!(statuses(&healthy), all_pass);
+
+    // A disk that can't hold the rest of the table fails the run.
+    let too_big = doctor(&["--n", "1000000000000"]);
+    assert!(!too_big.status.success());
+    assert_eq!(status(&statuses(&too_big), "disk_space"), "fail");
+    assert!(String::from_utf8_lossy(&too_big.stdout).contains("\"error\":\"checks_failed\""));
+
+    // Damage is found within the trailing rows only.
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBdoc SET data = upper(data) WHERE id = 150", params![]).unwrap();
+    assert!(doctor(&[]).status.success());
+    let deep = doctor(&["--rows", "200"]);
+    assert!(!deep.status.success());
+    assert_eq!(status(&statuses(&deep), "trailing_rows"), "fail");
+
+    // A missing setting is only a warning, an unreadable one a failure.
+    conn.execute("UPDATE DBdoc SET data = lower(data) WHERE id = 150", params![]).unwrap();
+    conn.execute("DELETE FROM metadata WHERE table_name = 'doc' AND key = 'generator_version'", params![]).unwrap();
+    let legacy = doctor(&[]);
+    assert!(legacy.status.success());
+    assert_eq!(status(&statuses(&legacy), "metadata"), "warn");
+    conn.execute("UPDATE metadata SET value = 'md4' WHERE table_name = 'doc' AND key = 'hash_algo'", params![]).unwrap();
+    assert_eq!(status(&statuses(&doctor(&[])), "metadata"), "fail");
+
+    // The observed journal mode is reported, whatever switched it.
+    conn.execute_batch("PRAGMA journal_mode = DELETE").unwrap();
+    let rollback = doctor(&[]);
+    assert_eq!(status(&statuses(&rollback), "pragmas"), "warn");
+    assert!(String::from_utf8_lossy(&rollback.stdout).contains("journal_mode=delete, not WAL: only WAL keeps"));
+
+    // Without the table, only the database itself is checked.
+    let missing = storer(&["doctor", "--path", path_str, "--seed", "other", "--json"]);
+    assert!(!missing.status.success());
+    assert_eq!(statuses(&missing), [("schema", "fail"), ("pragmas", "warn")].map(|(name, status)| (name.to_string(), status.to_string())));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,26 @@
+mod common;
+use common::{storer, temp_db};
+
+/// Stdout of a run that is expected to succeed.
+fn stdout(args: &[&str]) -> Vec<u8> {
+    let output = storer(args);
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn size_estimate_matches_the_generated_file() {
+    let path = temp_db("estimate");
+    let path_str = path.to_str().unwrap();
+    for generate in [&["--size", "1024"][..], &["--size", "32", "--hash"]] {
+        let _ = std::fs::remove_file(&path);
+        stdout(&[&["--path", path_str, "--n", "3000", "--seed", "estimate", "--synchronous", "OFF"][..], generate].concat());
+
+        let info: serde_json::Value =
+            serde_json::from_slice(&stdout(&["info", "--path", path_str, "--seed", "estimate", "--json"])).unwrap();
+        let file_size = info["file_size"].as_f64().unwrap();
+        let estimated = info["estimated_size"].as_f64().unwrap();
+        assert!((estimated / file_size - 1.0).abs() < 0.05, "{:?}: estimated {} for {}", generate, estimated, file_size);
+    }
+    let _ = std::fs::remove_file(&path);
+}
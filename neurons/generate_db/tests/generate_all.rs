@@ -0,0 +1,131 @@
+use storer_db_project::{ChunkGenerator, ChunkHasher, PartitionId, Sha256Hasher};
+
+mod common;
+use common::{storer, storer_command, succeeds, temp_dir};
+
+/// `final_seed` of every table in a `--json` run, in output order.
+fn final_seeds(args: &[&str]) -> Vec<String> {
+    let output = storer(args);
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            let summary: serde_json::Value = serde_json::from_str(line).unwrap();
+            summary["final_seed"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn concurrent_partitions_match_sequential_ones() {
+    let concurrent = temp_dir("generate_all");
+    let sequential = temp_dir("generate_all_sequential");
+    let status = concurrent.join("status");
+
+    // 0.0001 GiB of 512-byte chunks is 210 chunks in total.
+    let from_pool = final_seeds(&[
+        "generate-all", "--db_root_path", concurrent.to_str().unwrap(), "--partitions", "5",
+        "--size_gb", "0.0001", "--size", "512", "--seed", "all", "--jobs", "3", "--json",
+        "--status_file", status.to_str().unwrap(),
+    ]);
+    let one_by_one = final_seeds(&[
+        "--db_root_path", sequential.to_str().unwrap(), "--partitions", "5",
+        "--n", "210", "--size", "512", "--seed", "all", "--json",
+    ]);
+    assert_eq!(from_pool.len(), 5);
+    assert_eq!(from_pool, one_by_one);
+
+    // Every partition leaves its own final heartbeat.
+    for index in 0..5 {
+        let text = std::fs::read_to_string(format!("{}.{}", status.display(), index)).unwrap();
+        let heartbeat: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(heartbeat["table"], format!("DBall_{}", index));
+        assert_eq!(heartbeat["id"], heartbeat["total"]);
+        assert_eq!(heartbeat["eta_s"], 0);
+    }
+
+    let _ = std::fs::remove_dir_all(&concurrent);
+    let _ = std::fs::remove_dir_all(&sequential);
+}
+
+#[test]
+fn continue_on_error_generates_the_other_partitions() {
+    let root = temp_dir("generate_all_errors");
+    std::fs::create_dir_all(&root).unwrap();
+    // Partition 1 holds a table of its name that isn't a generated one.
+    let conn = rusqlite::Connection::open(root.join("DBbest_1")).unwrap();
+    conn.execute("CREATE TABLE DBbest_1 (x INTEGER)", rusqlite::params![]).unwrap();
+    drop(conn);
+
+    for jobs in ["1", "2"] {
+        let output = storer_command()
+            .args([
+                "generate-all", "--db_root_path", root.to_str().unwrap(), "--partitions", "3", "--size_gb", "0.00001",
+                "--size", "512", "--seed", "best", "--jobs", jobs, "--continue_on_error", "--json",
+            ])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let lines: Vec<serde_json::Value> =
+            String::from_utf8_lossy(&output.stdout).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let statuses: Vec<_> = lines.iter().map(|line| (line["table"].as_str(), line["status"].as_str())).collect();
+        // The second run finds the good partitions already generated.
+        let done = Some(if jobs == "1" { "written" } else { "complete" });
+        assert_eq!(statuses[..3], [(Some("DBbest_0"), done), (Some("DBbest_2"), done), (Some("DBbest_1"), Some("failed"))]);
+        assert_eq!(lines[2]["error"], "schema_mismatch");
+        assert_eq!(lines[3]["message"], "1 of 3 tables failed: DBbest_1");
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn partition_genesis_mixes_in_the_little_endian_index() {
+    // Earlier versions started partition K from the hash of the base genesis
+    // seed followed by K as 8 bytes, least significant first, and recorded
+    // only that seed. Such a partition resumes on it.
+    let root = temp_dir("generate_all_legacy_partition");
+    std::fs::create_dir_all(&root).unwrap();
+    let base = ChunkGenerator::genesis_seed(&Sha256Hasher, 16);
+    let legacy = hex::encode(Sha256Hasher.hash(&[&base[..], &[1, 0, 0, 0, 0, 0, 0, 0]].concat()));
+    let partition = root.join("DBorder_1");
+    let partition_str = partition.to_str().unwrap();
+    assert!(succeeds(&["--path", partition_str, "--seed", "order_1", "--n", "1", "--size", "16", "--genesis_seed", &legacy]));
+
+    let output = storer(&["--db_root_path", root.to_str().unwrap(), "--partitions", "2", "--n", "4", "--size", "16", "--seed", "order"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let conn = rusqlite::Connection::open(&partition).unwrap();
+    let metadata = |key: &str| -> Option<String> {
+        conn.query_row("SELECT value FROM metadata WHERE table_name = 'order_1' AND key = ?", rusqlite::params![key], |row| row.get(0))
+            .ok()
+    };
+    assert_eq!((metadata("genesis_seed"), metadata("partition_index")), (Some(legacy), None));
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM DBorder_1", rusqlite::params![], |row| row.get(0)).unwrap();
+    assert_eq!(rows, 2);
+    assert!(succeeds(&["verify", "--path", partition_str, "--seed", "order_1"]));
+    drop(conn);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn partitions_are_generated_as_partition_indices() {
+    let root = temp_dir("generate_all_byte_order");
+    let output = storer(&["--db_root_path", root.to_str().unwrap(), "--partitions", "2", "--n", "2", "--size", "16", "--seed", "order"]);
+    assert!(output.status.success());
+    let conn = rusqlite::Connection::open(root.join("DBorder_1")).unwrap();
+    let metadata = |key: &str| -> String {
+        conn.query_row("SELECT value FROM metadata WHERE table_name = 'order_1' AND key = ?", rusqlite::params![key], |row| row.get(0))
+            .unwrap()
+    };
+    // The chain of `--partition_index 1`: index 1 as 8 bytes, least
+    // significant first, then the table name and the zero chunk.
+    let expected = PartitionId(1).genesis_seed(&Sha256Hasher, "DBorder_1", 16);
+    assert_eq!(metadata("genesis_seed"), hex::encode(expected));
+    assert_eq!(metadata("partition_index"), "1");
+    drop(conn);
+    let _ = std::fs::remove_dir_all(&root);
+
+    // A single genesis seed can't start several disjoint chains.
+    let output = storer(&["--db_root_path", root.to_str().unwrap(), "--partitions", "2", "--n", "2", "--seed", "order", "--genesis_seed", "00"]);
+    assert!(!output.status.success());
+    assert!(!root.exists());
+}
@@ -0,0 +1,78 @@
+use std::process::Command;
+
+mod common;
+use common::{storer, temp_db};
+
+fn final_seed_from_json(output: &[u8]) -> String {
+    let stdout = String::from_utf8_lossy(output);
+    let summary: serde_json::Value = serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+    summary["final_seed"].as_str().unwrap().to_string()
+}
+
+fn genhash(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_genhash")).args(args).output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn genhash_matches_generated_table() {
+    let path = temp_db("genhash");
+    let output = storer(&["--path", path.to_str().unwrap(), "--n", "50", "--size", "100", "--seed", "genhash", "--json"]);
+    assert!(output.status.success());
+    let generated = final_seed_from_json(&output.stdout);
+
+    assert_eq!(genhash(&["--n", "50", "--size", "100", "--seed", "genhash"]), generated);
+    let output = storer(&["final-seed", "--n", "50", "--size", "100", "--seed", "genhash"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), generated);
+
+    // Continuing from an intermediate seed reaches the same end of the chain.
+    let midpoint = genhash(&["--n", "20", "--size", "100", "--seed", "genhash"]);
+    assert_eq!(
+        genhash(&["--n", "50", "--size", "100", "--start_index", "20", "--start_seed", &midpoint]),
+        generated
+    );
+
+    // Tables whose genesis seed is not salted with their name share one chain.
+    let output = storer(&["--path", path.to_str().unwrap(), "--n", "50", "--size", "100", "--seed", "legacy", "--no_salt", "--json"]);
+    assert!(output.status.success());
+    let legacy = final_seed_from_json(&output.stdout);
+    assert_ne!(legacy, generated);
+    assert_eq!(genhash(&["--n", "50", "--size", "100", "--no_salt"]), legacy);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn testvectors_match_generated_rows() {
+    let path = temp_db("testvectors");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(&["--path", path_str, "--n", "20", "--size", "24", "--seed", "vectors", "--rng", "chacha20"]).status.success());
+
+    let output = storer(&["testvectors", "--n", "5", "--chunk_size", "24", "--seed", "vectors", "--rng", "chacha20"]);
+    assert!(output.status.success());
+    let vectors: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(vectors["rng"], "chacha20");
+    assert_eq!(vectors["genesis"]["table"], "DBvectors");
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let genesis: String = conn
+        .query_row("SELECT value FROM metadata WHERE table_name = 'vectors' AND key = 'genesis_seed'", rusqlite::params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(vectors["genesis"]["seed_hex"], genesis);
+    let vectors = vectors["vectors"].as_array().unwrap();
+    assert_eq!(vectors.len(), 5);
+    for vector in vectors {
+        let (data, hash): (Vec<u8>, String) = conn
+            .query_row("SELECT data, hash FROM DBvectors WHERE id = ?", rusqlite::params![vector["id"].as_i64().unwrap()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(vector["data_hex"], hex::encode(data));
+        assert_eq!(vector["hash_hex"], hash);
+    }
+    let final_seed = storer(&["final-seed", "--n", "5", "--size", "24", "--seed", "vectors", "--rng", "chacha20"]);
+    assert_eq!(vectors[4]["hash_hex"], String::from_utf8_lossy(&final_seed.stdout).trim());
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,76 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OpenFlags};
+
+mod common;
+use common::{storer, storer_command, temp_db};
+
+#[test]
+fn generation_waits_for_a_locked_database() {
+    let path = temp_db("locking");
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch("CREATE TABLE other (id INTEGER); BEGIN EXCLUSIVE;").unwrap();
+
+    // Another process holding the lock only delays the run.
+    let waiting = storer_command()
+        .args(["--path", path.to_str().unwrap(), "--n", "10", "--size", "16", "--seed", "locked"])
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    conn.execute_batch("COMMIT").unwrap();
+    assert!(waiting.wait_with_output().unwrap().status.success());
+
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM DBlocked", params![], |row| row.get(0)).unwrap();
+    assert_eq!(rows, 10);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn readers_see_committed_rows_during_generation() {
+    let path = temp_db("locking_readers");
+    let path_str = path.to_str().unwrap().to_string();
+    let unsafe_run = storer(&["--path", &path_str, "--n", "10", "--size", "16", "--seed", "read", "--reader_safe", "--journal", "OFF"]);
+    assert!(String::from_utf8_lossy(&unsafe_run.stderr).contains("--reader_safe needs WAL"));
+
+    // Throttled to about 3 seconds, so the reader gets in between the batches.
+    let mut writer = storer_command()
+        .args(["--path", &path_str, "--n", "3000", "--size", "1024", "--seed", "read", "--batch_size", "100"])
+        .args(["--max_write_mbps", "1", "--reader_safe", "--progress", "never"])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let done = Arc::clone(&done);
+        let path_str = path_str.clone();
+        std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            while !done.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(100));
+                let Ok(conn) = Connection::open_with_flags(&path_str, OpenFlags::SQLITE_OPEN_READ_ONLY) else { continue };
+                let Ok(Some(last)) = conn.query_row("SELECT MAX(id) FROM DBread", params![], |row| row.get::<_, Option<i64>>(0)) else {
+                    continue;
+                };
+                // Every committed batch is complete, and readable by the other subcommands.
+                assert_eq!((last + 1) % 100, 0, "read inside a batch");
+                let id = last.to_string();
+                for subcommand in ["fetch", "challenge"] {
+                    let output = storer(&[subcommand, "--path", &path_str, "--seed", "read", "--id", &id]);
+                    assert!(output.status.success(), "{} {}: {}", subcommand, id, String::from_utf8_lossy(&output.stderr));
+                }
+                seen.push(last);
+            }
+            seen
+        })
+    };
+    assert!(writer.wait().unwrap().success());
+    done.store(true, Ordering::Relaxed);
+    let seen = reader.join().unwrap();
+    assert!(seen.iter().any(|&last| last < 2999), "no read during generation: {:?}", seen);
+    assert!(storer(&["verify", "--path", &path_str, "--seed", "read"]).status.success());
+    let _ = std::fs::remove_file(&path);
+}
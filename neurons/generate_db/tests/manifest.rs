@@ -0,0 +1,101 @@
+use rusqlite::{params, Connection};
+use storer_db_project::{merkle, Sha256Hasher};
+
+mod common;
+use common::{storer, temp_db};
+
+#[test]
+fn manifests_catch_damaged_hashes_but_not_stored_data() {
+    let path = temp_db("manifest");
+    let path_str = path.to_str().unwrap();
+    let generated = storer(&["--path", path_str, "--seed", "manifest", "--n", "40", "--size", "32", "--json"]);
+    assert!(generated.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&generated.stdout).unwrap();
+    let root = summary["manifest_root"].as_str().unwrap().to_string();
+    let merkle_root = storer(&["merkle-root", "--path", path_str, "--seed", "manifest"]);
+    assert_eq!(root, String::from_utf8_lossy(&merkle_root.stdout).trim());
+
+    let verify = || storer(&["verify-manifest", "--path", path_str, "--seed", "manifest"]);
+    let verified = verify();
+    assert!(verified.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&verified.stdout).trim(),
+        format!("Manifest of DBmanifest matches: root {} over 40 rows", root)
+    );
+
+    // Storing user data replaces the hash of a row, but not its chunk hash.
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBmanifest SET data = x'0102', hash = 'user', flag = 'T' WHERE id = 7", params![]).unwrap();
+    assert!(verify().status.success());
+
+    conn.execute("UPDATE DBmanifest SET hash = '00' || substr(hash, 3) WHERE id = 12", params![]).unwrap();
+    let damaged = verify();
+    assert!(!damaged.status.success());
+    assert!(String::from_utf8_lossy(&damaged.stderr).contains("manifest mismatch in DBmanifest"));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn manifests_recorded_before_the_leaf_prefix_still_verify() {
+    let path = temp_db("manifest_unprefixed");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(&["--path", path_str, "--seed", "old", "--n", "9", "--size", "32"]).status.success());
+
+    // Record the manifest as it was built before leaves were prefixed.
+    let conn = Connection::open(&path).unwrap();
+    let leaves: Vec<Vec<u8>> = conn
+        .prepare("SELECT hash FROM DBold ORDER BY id")
+        .unwrap()
+        .query_map(params![], |row| row.get::<_, String>(0))
+        .unwrap()
+        .map(|hash| hex::decode(hash.unwrap()).unwrap())
+        .collect();
+    let old_root = hex::encode(merkle::unprefixed_root(&Sha256Hasher, leaves).unwrap());
+    conn.execute("DELETE FROM metadata WHERE table_name = 'old' AND key = 'manifest_version'", params![]).unwrap();
+    conn.execute(
+        "UPDATE metadata SET value = ? WHERE table_name = 'old' AND key = 'manifest_root'",
+        params![old_root],
+    )
+    .unwrap();
+    let verified = storer(&["verify-manifest", "--path", path_str, "--seed", "old"]);
+    assert!(verified.status.success(), "{}", String::from_utf8_lossy(&verified.stderr));
+
+    // The next run records it afresh, even with no rows to add.
+    let rerun = storer(&["--path", path_str, "--seed", "old", "--n", "9", "--size", "32"]);
+    assert_eq!(rerun.status.code(), Some(3));
+    let version: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE table_name = 'old' AND key = 'manifest_version'",
+            params![],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(version, "2");
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn appended_rows_extend_the_recorded_manifest() {
+    let path = temp_db("manifest_appended");
+    let path_str = path.to_str().unwrap();
+    let generate = |n: &str| storer(&["--path", path_str, "--seed", "grown", "--n", n, "--size", "32", "--log_level", "info"]);
+    assert!(generate("40").status.success());
+
+    // Only the new rows are read, and the root is the one of the whole table.
+    let grown = generate("100");
+    assert!(grown.status.success());
+    assert!(String::from_utf8_lossy(&grown.stderr).contains("rows=100"));
+    assert!(String::from_utf8_lossy(&grown.stderr).contains("rows_read=60"));
+    let verified = storer(&["verify-manifest", "--path", path_str, "--seed", "grown"]);
+    assert!(verified.status.success(), "{}", String::from_utf8_lossy(&verified.stderr));
+    let merkle_root = storer(&["merkle-root", "--path", path_str, "--seed", "grown"]);
+    assert!(String::from_utf8_lossy(&verified.stdout).contains(String::from_utf8_lossy(&merkle_root.stdout).trim()));
+
+    // A run that drops rows reads the table again.
+    assert!(storer(&["truncate", "--path", path_str, "--seed", "grown", "--to", "70"]).status.success());
+    let regrown = generate("100");
+    assert!(String::from_utf8_lossy(&regrown.stderr).contains("rows_read=100"));
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,46 @@
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer, temp_dir};
+
+#[test]
+fn merged_partitions_form_one_chain() {
+    let dir = temp_dir("merge");
+    std::fs::create_dir_all(&dir).unwrap();
+    let root = dir.to_str().unwrap();
+    assert!(storer(&["--db_root_path", root, "--partitions", "3", "--n", "90", "--size", "40", "--seed", "parts"]).status.success());
+    let part = |index: usize| dir.join(format!("DBparts_{}", index)).to_str().unwrap().to_string();
+    let conn = Connection::open(part(1)).unwrap();
+    conn.execute("UPDATE DBparts_1 SET data = x'0102', hash = 'user', flag = 'T' WHERE id = 4", params![]).unwrap();
+    drop(conn);
+
+    let output = dir.join("merged.db");
+    let output_str = output.to_str().unwrap();
+    let inputs = [part(0), part(1), part(2)].join(",");
+    let merged = storer(&["merge", "--inputs", &inputs, "--output", output_str, "--table", "merged", "--json"]);
+    assert!(merged.status.success(), "{}", String::from_utf8_lossy(&merged.stderr));
+    let summary: serde_json::Value = serde_json::from_slice(&merged.stdout).unwrap();
+    assert_eq!(summary["rows_written"], 90);
+
+    // The chain is the one a single table of the combined length has.
+    let final_seed = storer(&["final-seed", "--n", "90", "--size", "40", "--seed", "merged"]);
+    assert_eq!(summary["final_seed"], String::from_utf8_lossy(&final_seed.stdout).trim());
+    assert!(storer(&["verify", "--path", output_str, "--seed", "merged"]).status.success());
+
+    // Miner-stored rows move to their renumbered ids.
+    let conn = Connection::open(&output).unwrap();
+    let (data, flag): (Vec<u8>, String) = conn
+        .query_row("SELECT data, flag FROM DBmerged WHERE id = 34", params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!((data, flag.as_str()), (vec![1, 2], "T"));
+
+    // Inputs generated with other settings don't make one chain.
+    let other = dir.join("other.db");
+    let other_str = other.to_str().unwrap();
+    assert!(storer(&["--path", other_str, "--n", "10", "--size", "48", "--seed", "other"]).status.success());
+    let mixed = storer(&["merge", "--inputs", &format!("{},{}", part(0), other_str), "--output", dir.join("mixed.db").to_str().unwrap(), "--table", "mixed"]);
+    assert!(!mixed.status.success());
+    assert!(String::from_utf8_lossy(&mixed.stderr).contains("DBother has size 48, DBparts_0 has 40"));
+    drop(conn);
+    let _ = std::fs::remove_dir_all(&dir);
+}
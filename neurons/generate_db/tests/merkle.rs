@@ -0,0 +1,46 @@
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer, succeeds, temp_db};
+
+#[test]
+fn proofs_round_trip_and_reject_tampering() {
+    let path = temp_db("merkle");
+    let path_str = path.to_str().unwrap();
+    assert!(succeeds(&["--path", path_str, "--seed", "merkle", "--n", "13", "--size", "32"]));
+    let output = |args: &[&str]| {
+        let run = storer(args);
+        assert!(run.status.success(), "{:?}", args);
+        String::from_utf8_lossy(&run.stdout).trim().to_string()
+    };
+    let root = output(&["merkle-root", "--path", path_str, "--seed", "merkle"]);
+    let conn = Connection::open(&path).unwrap();
+    let verifies = |id: i64, proof: &str| {
+        let leaf: String = conn.query_row("SELECT hash FROM DBmerkle WHERE id = ?", params![id], |row| row.get(0)).unwrap();
+        succeeds(&["verify-proof", "--leaf", &leaf, "--proof", proof, "--root", &root])
+    };
+
+    for id in [0, 5, 12] {
+        let proof = output(&["prove", "--path", path_str, "--seed", "merkle", "--id", &id.to_string()]);
+        assert!(proof.starts_with(&format!("{}/13,", id)), "{}", proof);
+        assert!(verifies(id, &proof));
+
+        // The proof of another row doesn't hold for this one, and a proof
+        // moved to another index or cut short doesn't hold at all.
+        assert!(!verifies((id + 1) % 13, &proof));
+        let (_, path) = proof.split_once(',').unwrap();
+        assert!(!verifies(id, &format!("{}/13,{}", (id + 1) % 13, path)));
+        let (truncated, _) = proof.rsplit_once(',').unwrap();
+        assert!(!verifies(id, truncated));
+    }
+
+    // Row 12 is carried up past the levels where it has no sibling, so its
+    // path is shorter, and an empty path only proves the single leaf of a
+    // tree of one.
+    let last = output(&["prove", "--path", path_str, "--seed", "merkle", "--id", "12"]);
+    assert_eq!(last.matches(',').count(), 2);
+    assert!(!verifies(12, "12/13"));
+    assert!(!storer(&["prove", "--path", path_str, "--seed", "merkle", "--id", "13"]).status.success());
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
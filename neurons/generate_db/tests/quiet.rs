@@ -0,0 +1,46 @@
+mod common;
+use common::{storer_command, temp_db};
+
+#[test]
+fn quiet_runs_log_one_line_per_batch() {
+    let path = temp_db("quiet");
+    let output = storer_command()
+        .args(["--path", path.to_str().unwrap(), "--n", "250", "--size", "16", "--seed", "quiet"])
+        .args(["--batch_size", "100", "--quiet", "--json"])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The summary stays alone on stdout, the batches go to stderr.
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["rows_written"], 250);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let batches: Vec<&str> = stderr.lines().filter_map(|line| line.split("] ").nth(1)).filter(|line| line.starts_with("wrote")).collect();
+    assert_eq!(batches, ["wrote ids 0..100 of DBquiet", "wrote ids 100..200 of DBquiet", "wrote ids 200..250 of DBquiet"]);
+    assert!(!stderr.contains('\u{1b}'));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn captured_stderr_gets_log_lines_instead_of_bars() {
+    let path = temp_db("quiet_auto");
+    let generate = |args: &[&str]| {
+        storer_command()
+            .args(["--path", path.to_str().unwrap(), "--size", "16", "--seed", "auto", "--batch_size", "100"])
+            .args(args)
+            .env_remove("RUST_LOG")
+            .output()
+            .unwrap()
+    };
+
+    // The test harness captures stderr, so it is no terminal.
+    let output = generate(&["--n", "100"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("wrote ids 0..100 of DBauto"), "{}", stderr);
+    assert!(!stderr.contains('\u{1b}'));
+    assert!(String::from_utf8_lossy(&generate(&["--n", "200", "--progress", "never"]).stderr).contains("wrote ids 100..200"));
+    assert!(!generate(&["--n", "300", "--quiet", "--progress", "always"]).status.success());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::{storer, temp_path};
+
+/// A raw file path with no file, index or sidecar at it yet.
+fn raw_path(name: &str) -> PathBuf {
+    let path = temp_path(name);
+    remove(&path);
+    path
+}
+
+fn remove(path: &Path) {
+    let path = path.to_str().unwrap();
+    for file in [path.to_string(), format!("{}.idx", path), format!("{}.json", path)] {
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+#[test]
+fn rawfile_backend_matches_sqlite_rows() {
+    let raw = raw_path("rawfile");
+    let db = raw_path("rawfile_sqlite.db");
+    let (raw_str, db_str) = (raw.to_str().unwrap(), db.to_str().unwrap());
+    let generate = ["--n", "40", "--size", "24", "--seed", "raw", "--json"];
+
+    // Generate the first half, then resume to the full length.
+    assert!(storer(&[&["--backend", "rawfile", "--path", raw_str, "--n", "20"][..], &generate[2..]].concat()).status.success());
+    assert!(storer(&[&["--backend", "rawfile", "--path", raw_str][..], &generate].concat()).status.success());
+    assert!(storer(&[&["--path", db_str][..], &generate].concat()).status.success());
+    assert!(storer(&["verify", "--backend", "rawfile", "--path", raw_str, "--seed", "raw"]).status.success());
+
+    // Both backends store the same chain.
+    for id in ["0", "19", "20", "39"] {
+        let from_raw = storer(&["fetch", "--backend", "rawfile", "--path", raw_str, "--seed", "raw", "--id", id]);
+        let from_db = storer(&["fetch", "--path", db_str, "--seed", "raw", "--id", id]);
+        assert!(from_raw.status.success());
+        assert_eq!(from_raw.stdout, from_db.stdout);
+    }
+    for format in ["raw", "ndjson"] {
+        let from_raw = storer(&["export", "--backend", "rawfile", "--path", raw_str, "--seed", "raw", "--format", format]);
+        let from_db = storer(&["export", "--path", db_str, "--seed", "raw", "--format", format]);
+        assert!(from_raw.status.success());
+        assert_eq!(from_raw.stdout, from_db.stdout);
+    }
+    let exported = storer(&["export", "--path", db_str, "--seed", "raw"]).stdout;
+    // The records are followed by the 256 byte footer, whose chunk size and
+    // count are little-endian on every platform.
+    assert_eq!(exported.len(), 40 * (24 + 64) + 256);
+    let footer = &exported[40 * (24 + 64)..];
+    assert_eq!(footer[112..128], [24, 0, 0, 0, 0, 0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 0]);
+
+    // A flipped byte fails the challenge for that chunk only.
+    let mut data = std::fs::read(&raw).unwrap();
+    let record_len = 24 + 64;
+    data[10 * record_len] ^= 1;
+    std::fs::write(&raw, data).unwrap();
+    let challenge = |id: &str| storer(&["challenge", "--backend", "rawfile", "--path", raw_str, "--seed", "raw", "--id", id]);
+    assert!(!challenge("10").status.success());
+    assert!(challenge("11").status.success());
+    let verify = storer(&["verify", "--backend", "rawfile", "--path", raw_str, "--seed", "raw", "--threads", "3"]);
+    assert!(String::from_utf8_lossy(&verify.stderr).contains("row 10 does not match"));
+
+    remove(&raw);
+    let _ = std::fs::remove_file(&db);
+}
+
+#[test]
+fn raw_exports_import_back() {
+    let raw = raw_path("rawfile_import");
+    let (db, imported) = (raw_path("rawfile_import_source.db"), raw_path("rawfile_import_target.db"));
+    let exported = raw_path("rawfile_import.bin");
+    let (raw_str, db_str, imported_str) = (raw.to_str().unwrap(), db.to_str().unwrap(), imported.to_str().unwrap());
+    let generate = ["--n", "30", "--size", "20", "--seed", "portable", "--rng", "chacha20"];
+    assert!(storer(&[&["--path", db_str][..], &generate].concat()).status.success());
+    assert!(storer(&[&["--backend", "rawfile", "--path", raw_str][..], &generate].concat()).status.success());
+
+    // Either backend's export is enough to rebuild the table.
+    let from_db = storer(&["export", "--path", db_str, "--seed", "portable"]).stdout;
+    assert_eq!(storer(&["export", "--backend", "rawfile", "--path", raw_str, "--seed", "portable"]).stdout, from_db);
+    std::fs::write(&exported, &from_db).unwrap();
+    let import = storer(&["import", "--from", exported.to_str().unwrap(), "--db", imported_str]);
+    assert!(import.status.success(), "{}", String::from_utf8_lossy(&import.stderr));
+    assert!(storer(&["verify", "--path", imported_str, "--seed", "portable"]).status.success());
+    assert!(storer(&["verify-manifest", "--path", imported_str, "--seed", "portable"]).status.success());
+    assert_eq!(storer(&["export", "--path", imported_str, "--seed", "portable"]).stdout, from_db);
+
+    // A damaged chunk is caught before its batch is written, and importing
+    // the intact file again resumes the table.
+    let _ = std::fs::remove_file(&imported);
+    let mut damaged = from_db.clone();
+    damaged[7 * (20 + 64) + 3] ^= 1;
+    std::fs::write(&exported, &damaged).unwrap();
+    let import = storer(&["import", "--from", exported.to_str().unwrap(), "--db", imported_str]);
+    assert!(!import.status.success());
+    assert!(String::from_utf8_lossy(&import.stderr).contains("row 7 does not match the regenerated chunk (data differs)"));
+    let rows: i64 = rusqlite::Connection::open(&imported).unwrap()
+        .query_row("SELECT COUNT(*) FROM DBportable", rusqlite::params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(rows, 0);
+    std::fs::write(&exported, &from_db).unwrap();
+    assert!(storer(&["import", "--from", exported.to_str().unwrap(), "--db", imported_str]).status.success());
+    assert_eq!(storer(&["export", "--path", imported_str, "--seed", "portable"]).stdout, from_db);
+    let _ = std::fs::remove_file(&imported);
+
+    // So is a file cut short.
+    std::fs::write(&exported, &from_db[..from_db.len() - 300]).unwrap();
+    assert!(!storer(&["import", "--from", exported.to_str().unwrap(), "--db", imported_str]).status.success());
+
+    remove(&raw);
+    for path in [&db, &exported] {
+        let _ = std::fs::remove_file(path);
+    }
+}
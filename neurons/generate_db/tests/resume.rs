@@ -0,0 +1,181 @@
+use std::process::Output;
+
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer, temp_db};
+
+#[test]
+fn torn_rng_state_is_reported_by_row() {
+    let path = temp_db("resume_torn");
+    let path_str = path.to_str().unwrap();
+    let generate = ["--path", path_str, "--n", "12", "--size", "16", "--seed", "torn"];
+    assert!(storer(&["--path", path_str, "--n", "10", "--size", "16", "--seed", "torn"]).status.success());
+
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("UPDATE DBtorn SET rng_state = substr(rng_state, 1, 30) WHERE id = 8", params![]).unwrap();
+
+    let strict = storer(&[&generate[..], &["--strict_resume", "--json"]].concat());
+    assert!(!strict.status.success());
+    let stderr = String::from_utf8_lossy(&strict.stderr);
+    assert!(stderr.contains("the seed chain breaks at row 8"), "{}", stderr);
+    let error: serde_json::Value = serde_json::from_slice(&strict.stdout).unwrap();
+    assert_eq!(error["error"], "corrupt_seed_chain");
+
+    // Without --strict_resume the torn rows are dropped and regenerated.
+    assert!(storer(&generate).status.success());
+    assert!(storer(&["verify", "--path", path_str, "--seed", "torn"]).status.success());
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn in_memory_database_runs_the_whole_pipeline() {
+    let output = storer(&["--path", ":memory:", "--n", "50", "--size", "16", "--seed", "memory", "--json", "--verify_after"]);
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["rows_written"], 50);
+    assert_eq!(summary["verified_rows"], 50);
+    assert!(!std::path::Path::new(":memory:").exists());
+}
+
+#[test]
+fn time_budget_stops_at_a_batch_boundary() {
+    let path = temp_db("resume_budget");
+    let path_str = path.to_str().unwrap();
+    let generate = [
+        "--path", path_str, "--n", "10000000", "--size", "16", "--seed", "budget",
+        "--batch_size", "100", "--max_runtime_secs", "1", "--json",
+    ];
+
+    for run in 1..=2 {
+        let output = storer(&generate);
+        assert!(output.status.success());
+        let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(summary["out_of_time"], true);
+        let rows = summary["rows_written"].as_u64().unwrap();
+        assert!(rows > 0 && rows.is_multiple_of(100), "run {} wrote {} rows", run, rows);
+    }
+    assert!(storer(&["verify", "--path", path_str, "--seed", "budget"]).status.success());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn hash_only_tables_fill_in_their_data() {
+    let (hashed, full) = (temp_db("resume_fill"), temp_db("resume_fill_full"));
+    let (hashed_str, full_str) = (hashed.to_str().unwrap(), full.to_str().unwrap());
+    let generate = |path: &str, n: &str, args: &[&str]| {
+        storer(&[&["--path", path, "--n", n, "--size", "32", "--seed", "fill", "--checkpoint_interval", "16"][..], args].concat())
+    };
+    assert!(generate(hashed_str, "40", &["--hash"]).status.success());
+    assert!(generate(full_str, "50", &[]).status.success());
+
+    // A stored hash that the replayed chain doesn't reproduce stops the fill.
+    let conn = Connection::open(&hashed).unwrap();
+    let hash: String = conn.query_row("SELECT hash FROM DBfill WHERE id = 30", params![], |row| row.get(0)).unwrap();
+    conn.execute("UPDATE DBfill SET hash = 'bad' WHERE id = 30", params![]).unwrap();
+    let tampered = generate(hashed_str, "50", &["--fill_data", "--batch_size", "8"]);
+    assert!(!tampered.status.success());
+    assert!(String::from_utf8_lossy(&tampered.stderr).contains("row 30 does not match the regenerated chunk (hash differs)"));
+
+    // Filled batches are kept, and the rerun picks up where the fill stopped.
+    conn.execute("UPDATE DBfill SET hash = ? WHERE id = 30", params![hash]).unwrap();
+    let filled = generate(hashed_str, "50", &["--fill_data", "--batch_size", "8", "--json"]);
+    assert!(filled.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&filled.stdout).unwrap();
+    assert_eq!((summary["rows_filled"].as_u64(), summary["rows_written"].as_u64()), (Some(16), Some(10)));
+
+    let rows = |path: &std::path::Path| {
+        let conn = Connection::open(path).unwrap();
+        let mut stmt = conn.prepare("SELECT id, data, hash, rng_state FROM DBfill ORDER BY id").unwrap();
+        let rows: Vec<(i64, Vec<u8>, String, Vec<u8>)> = stmt
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        rows
+    };
+    assert_eq!(rows(&hashed), rows(&full));
+    drop(conn);
+    let _ = std::fs::remove_file(&hashed);
+    let _ = std::fs::remove_file(&full);
+}
+
+#[test]
+fn an_interrupted_run_resumes_to_the_single_shot_table() {
+    let (single, resumed) = (temp_db("resume_single"), temp_db("resume_resumed"));
+    let (single_str, resumed_str) = (single.to_str().unwrap(), resumed.to_str().unwrap());
+    let generate = |path: &str, n: &str| {
+        let output = storer(&["--path", path, "--n", n, "--size", "24", "--seed", "harness", "--batch_size", "16", "--json"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+    let at_once = generate(single_str, "100");
+    // A run that stopped part way through a batch, then the rest.
+    assert_eq!(generate(resumed_str, "37")["rows_written"], 37);
+    let rest = generate(resumed_str, "100");
+    assert_eq!(rest["rows_written"], 63);
+    assert_eq!(rest["final_seed"], at_once["final_seed"]);
+
+    assert!(storer(&["verify", "--path", resumed_str, "--seed", "harness"]).status.success());
+    let diff = storer(&["diff", "--db_a", single_str, "--db_b", resumed_str, "--seed", "harness"]);
+    assert!(diff.status.success(), "{}", String::from_utf8_lossy(&diff.stdout));
+    for path in [&single, &resumed] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn a_table_of_n_chunks_ends_at_id_n_minus_one() {
+    let path = temp_db("resume_boundary");
+    let path_str = path.to_str().unwrap();
+    let generate = |n: &str| storer(&["--path", path_str, "--n", n, "--size", "16", "--seed", "boundary", "--json"]);
+    let summary = |output: &Output| serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    let ids = || {
+        let conn = Connection::open(&path).unwrap();
+        conn.query_row("SELECT COUNT(*), MAX(id) FROM DBboundary", params![], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+            .unwrap()
+    };
+    assert!(generate("11").status.success());
+    assert_eq!(ids(), (11, 10));
+
+    // Next id one past --n: the last row goes.
+    let truncated = generate("10");
+    assert!(truncated.status.success());
+    assert_eq!((summary(&truncated)["status"].as_str(), summary(&truncated)["rows_deleted"].as_u64()), (Some("truncated"), Some(1)));
+    assert_eq!(ids(), (10, 9));
+
+    // Next id at --n: nothing to write, and the run exits as complete.
+    let complete = generate("10");
+    assert_eq!(complete.status.code(), Some(3));
+    assert_eq!((summary(&complete)["status"].as_str(), summary(&complete)["rows_written"].as_u64()), (Some("complete"), Some(0)));
+    assert_eq!(summary(&complete)["final_seed"], summary(&truncated)["final_seed"]);
+    assert_eq!(ids(), (10, 9));
+
+    let final_seed = storer(&["final-seed", "--n", "10", "--size", "16", "--seed", "boundary"]);
+    assert_eq!(summary(&complete)["final_seed"], String::from_utf8_lossy(&final_seed.stdout).trim());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_conflicting_insert_names_the_duplicate_id() {
+    let path = temp_db("resume_duplicate");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(&["--path", path_str, "--n", "5", "--size", "16", "--seed", "duplicate"]).status.success());
+
+    // Something else claims id 7 just before the resumed run writes it.
+    let conn = Connection::open(&path).unwrap();
+    conn.execute(
+        "CREATE TRIGGER claim BEFORE INSERT ON DBduplicate WHEN NEW.id = 7 \
+         BEGIN INSERT INTO DBduplicate VALUES (7, x'', 'claimed', 'F', x''); END",
+        params![],
+    )
+    .unwrap();
+
+    let resumed = storer(&["--path", path_str, "--n", "10", "--size", "16", "--seed", "duplicate"]);
+    assert_eq!(resumed.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&resumed.stderr);
+    assert!(stderr.contains("duplicate id 7 in DBduplicate"), "{}", stderr);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
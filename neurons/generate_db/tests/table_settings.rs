@@ -0,0 +1,421 @@
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{storer_command, temp_db};
+
+/// Whether a run on table `DBsettings` at `path` succeeded.
+fn storer(path: &str, args: &[&str]) -> bool {
+    let output = storer_command()
+        .args(["--path", path, "--seed", "settings"])
+        .args(args)
+        .output()
+        .unwrap();
+    output.status.success()
+}
+
+#[test]
+fn resume_refuses_other_generation_settings() {
+    let path = temp_db("table_settings");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32"]));
+
+    for mismatch in [
+        &["--hash_algo", "blake3"][..],
+        &["--size", "64"],
+        &["--rng", "chacha20"],
+        &["--charset", "bytes"],
+        &["--hash_bytes", "16"],
+        &["--hash_encoding", "blob"],
+        &["--no_salt"],
+    ] {
+        let args = [&["--n", "20", "--size", "32"][..], mismatch].concat();
+        assert!(!storer(path_str, &args), "resumed with {:?}", mismatch);
+    }
+    let conn = Connection::open(&path).unwrap();
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM DBsettings", params![], |row| row.get(0)).unwrap();
+    assert_eq!(rows, 10);
+    let chunk_size: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE table_name = 'settings' AND key = 'chunk_size'",
+            params![],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(chunk_size, "32");
+
+    // The recorded settings resume as before, and --force overrides them.
+    assert!(storer(path_str, &["--n", "20", "--size", "32"]));
+    assert!(storer(path_str, &["--n", "20", "--size", "32", "--rng", "chacha20", "--force"]));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn verify_uses_the_recorded_chunk_size() {
+    let path = temp_db("table_settings_hash_only");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32", "--hash"]));
+
+    // Hash-only rows hold no data to infer the size from.
+    let verify = |args: &[&str]| {
+        storer_command()
+            .args(["verify", "--path", path_str, "--seed", "settings"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    assert!(verify(&[]));
+    assert!(verify(&["--size", "32"]));
+    assert!(!verify(&["--size", "33"]));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn truncated_hashes_keep_the_full_seed_chain() {
+    let path = temp_db("table_settings_hash_bytes");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32", "--hash", "--hash_bytes", "16"]));
+    assert!(storer(path_str, &["--n", "20", "--size", "32", "--hash", "--hash_bytes", "16"]));
+
+    let conn = Connection::open(&path).unwrap();
+    let (hash, rng_state): (String, Vec<u8>) = conn
+        .query_row("SELECT hash, rng_state FROM DBsettings WHERE id = 15", params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!(hash, hex::encode(&rng_state[..16]));
+    assert_eq!(rng_state.len(), 32);
+    let verify = common::storer(&["verify", "--path", path_str, "--seed", "settings"]);
+    assert!(verify.status.success());
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn truncate_keeps_the_auto_vacuum_mode() {
+    let path = temp_db("table_settings_truncate");
+    let path_str = path.to_str().unwrap();
+    let auto_vacuum = || -> i64 {
+        Connection::open(&path).unwrap().query_row("PRAGMA auto_vacuum", params![], |row| row.get(0)).unwrap()
+    };
+    let truncate = |args: &[&str]| {
+        storer_command()
+            .args(["truncate", "--path", path_str, "--seed", "settings"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    assert!(storer(path_str, &["--n", "200", "--size", "32", "--auto_vacuum", "NONE"]));
+    assert_eq!(auto_vacuum(), 0);
+    assert!(truncate(&["--to", "100"]));
+    assert_eq!(auto_vacuum(), 0);
+
+    // Only an explicit mode is switched to on the way.
+    assert!(truncate(&["--to", "50", "--auto_vacuum", "incremental"]));
+    assert_eq!(auto_vacuum(), 2);
+    let rows: i64 = Connection::open(&path).unwrap()
+        .query_row("SELECT COUNT(*) FROM DBsettings", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(rows, 50);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn blob_hashes_read_back_as_hex() {
+    let blob = temp_db("table_settings_blob");
+    let hex = temp_db("table_settings_hex");
+    let (blob_str, hex_str) = (blob.to_str().unwrap(), hex.to_str().unwrap());
+    assert!(storer(blob_str, &["--n", "10", "--size", "32", "--hash", "--hash_encoding", "blob"]));
+    assert!(storer(blob_str, &["--n", "20", "--size", "32", "--hash", "--hash_encoding", "blob"]));
+    assert!(storer(hex_str, &["--n", "20", "--size", "32", "--hash"]));
+
+    let conn = Connection::open(&blob).unwrap();
+    let (kind, len): (String, i64) = conn
+        .query_row("SELECT typeof(hash), length(hash) FROM DBsettings WHERE id = 15", params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!((kind.as_str(), len), ("blob", 32));
+
+    assert!(common::storer(&["verify", "--path", blob_str, "--seed", "settings"]).status.success());
+    let fetch = |path: &str| common::storer(&["fetch", "--path", path, "--seed", "settings", "--id", "15"]).stdout;
+    assert_eq!(fetch(blob_str), fetch(hex_str));
+    assert!(common::storer(&["diff", "--db_a", blob_str, "--db_b", hex_str, "--seed", "settings"]).status.success());
+    drop(conn);
+    let _ = std::fs::remove_file(&blob);
+    let _ = std::fs::remove_file(&hex);
+}
+
+#[test]
+fn incompressible_chunks_do_not_compress() {
+    let path = temp_db("table_settings_incompressible");
+    let path_str = path.to_str().unwrap();
+    let conn = Connection::open(&path).unwrap();
+    let zstd_ratio = |table: &str| {
+        let mut statement = conn.prepare(&format!("SELECT data FROM DB{}", table)).unwrap();
+        let chunks: Vec<Vec<u8>> = statement.query_map(params![], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+        let data: Vec<u8> = chunks.into_iter().flat_map(|data| data[..4096].to_vec()).collect();
+        zstd::encode_all(data.as_slice(), 19).unwrap().len() as f64 / data.len() as f64
+    };
+
+    let generate = |seed: &str, n: &str, args: &[&str]| {
+        storer_command()
+            .args(["--path", path_str, "--n", n, "--size", "4096", "--seed", seed])
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    assert!(generate("keystream", "16", &["--incompressible"]));
+    assert!(generate("alphanumeric", "16", &[]));
+    assert!(zstd_ratio("keystream") > 0.99);
+    assert!(zstd_ratio("alphanumeric") < 0.8);
+
+    // The table records the keystream as its rng and charset.
+    assert!(!generate("keystream", "20", &[]));
+    assert!(generate("keystream", "20", &["--rng", "chacha20", "--charset", "bytes"]));
+    assert!(generate("keystream", "24", &["--incompressible"]));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn tables_with_other_columns_are_refused() {
+    let path = temp_db("table_settings_schema");
+    let path_str = path.to_str().unwrap();
+    let conn = Connection::open(&path).unwrap();
+    conn.execute(
+        "CREATE TABLE DBsettings (id INTEGER PRIMARY KEY, data TEXT NOT NULL, flag TEXT NOT NULL, rng_state BLOB NOT NULL)",
+        params![],
+    )
+    .unwrap();
+
+    let output = common::storer(&["--path", path_str, "--seed", "settings", "--n", "10", "--size", "32"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "schema mismatch in DBsettings: expected columns [id INTEGER, data BLOB, hash TEXT, flag TEXT, rng_state BLOB], \
+         found [id INTEGER, data TEXT, flag TEXT, rng_state BLOB]"
+    ));
+
+    // Tables from before the data column was declared BLOB still resume.
+    conn.execute_batch(
+        "DROP TABLE DBsettings;
+         CREATE TABLE DBsettings (id INTEGER PRIMARY KEY, data TEXT NOT NULL, hash TEXT NOT NULL, flag TEXT NOT NULL, rng_state BLOB NOT NULL);",
+    )
+    .unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32"]));
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn partition_indices_give_disjoint_chains() {
+    let paths: Vec<PathBuf> = (0..3).map(|copy| temp_db(&format!("table_settings_partition_{}", copy))).collect();
+    let path_strs: Vec<&str> = paths.iter().map(|path| path.to_str().unwrap()).collect();
+    for (path, index) in path_strs.iter().zip(["7", "7", "8"]) {
+        assert!(storer(path, &["--n", "40", "--size", "32", "--partition_index", index]));
+    }
+    let chunks = |path: &PathBuf| {
+        let conn = Connection::open(path).unwrap();
+        let mut statement = conn.prepare("SELECT data FROM DBsettings ORDER BY id").unwrap();
+        let chunks: Vec<Vec<u8>> = statement.query_map(params![], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+        chunks
+    };
+    assert_eq!(chunks(&paths[0]), chunks(&paths[1]));
+    let other = chunks(&paths[2]);
+    assert!(chunks(&paths[0]).iter().all(|chunk| !other.contains(chunk)));
+
+    // The index is recorded, pinned on resume and enough to replay the chain.
+    let conn = Connection::open(&paths[0]).unwrap();
+    let index: String = conn
+        .query_row("SELECT value FROM metadata WHERE table_name = 'settings' AND key = 'partition_index'", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(index, "7");
+    assert!(!storer(path_strs[0], &["--n", "50", "--size", "32", "--partition_index", "8"]));
+    assert!(storer(path_strs[0], &["--n", "50", "--size", "32"]));
+    assert!(common::storer(&["verify", "--path", path_strs[0], "--seed", "settings"]).status.success());
+    let rng_state: Vec<u8> = conn.query_row("SELECT rng_state FROM DBsettings WHERE id = 49", params![], |row| row.get(0)).unwrap();
+    let final_seed = common::storer(&["final-seed", "--n", "50", "--size", "32", "--seed", "settings", "--partition_index", "7"]);
+    assert_eq!(String::from_utf8_lossy(&final_seed.stdout).trim(), hex::encode(rng_state));
+    drop(conn);
+    for path in &paths {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn overwrite_regenerates_with_new_settings() {
+    let path = temp_db("table_settings_overwrite");
+    let path_str = path.to_str().unwrap();
+    let run = |args: &[&str]| {
+        storer_command()
+            .args(["--path", path_str, "--seed", "settings", "--size", "32"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    assert!(run(&["--n", "20"]).status.success());
+
+    let changed = run(&["--n", "10", "--hash_algo", "blake3"]);
+    assert!(!changed.status.success());
+    assert!(String::from_utf8_lossy(&changed.stderr).contains("not blake3 (pass --overwrite to regenerate it with the new settings)"));
+
+    // Without a terminal to confirm on, only --yes lets it delete anything.
+    let unconfirmed = run(&["--n", "10", "--hash_algo", "blake3", "--overwrite"]);
+    assert!(!unconfirmed.status.success());
+    assert!(String::from_utf8_lossy(&unconfirmed.stderr).contains("pass --yes"));
+    assert!(run(&["--n", "10", "--hash_algo", "blake3", "--overwrite", "--yes"]).status.success());
+
+    let conn = Connection::open(&path).unwrap();
+    let (rows, hash_algo): (i64, String) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM DBsettings), value FROM metadata WHERE table_name = 'settings' AND key = 'hash_algo'",
+            params![],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((rows, hash_algo.as_str()), (10, "blake3"));
+    let verify = common::storer(&["verify", "--path", path_str, "--seed", "settings"]);
+    assert!(verify.status.success());
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn strided_ids_keep_the_sequential_chain() {
+    let paths = [temp_db("table_settings_sequential"), temp_db("table_settings_strided")];
+    let [sequential, strided] = [paths[0].to_str().unwrap(), paths[1].to_str().unwrap()];
+    let layout = ["--id_stride", "256", "--id_offset", "5"];
+    assert!(storer(sequential, &["--n", "30", "--size", "32"]));
+    // Resumed in parallel and past a seed checkpoint, both stored by chain step.
+    assert!(storer(strided, &[&["--n", "12", "--size", "32"][..], &layout].concat()));
+    assert!(storer(strided, &[&["--n", "30", "--size", "32", "--threads", "3", "--checkpoint_interval", "8"][..], &layout].concat()));
+    let rows = |path: &PathBuf| {
+        let conn = Connection::open(path).unwrap();
+        let mut statement = conn.prepare("SELECT id, data, hash, rng_state FROM DBsettings ORDER BY id").unwrap();
+        let rows: Vec<(i64, Vec<u8>, String, Vec<u8>)> = statement
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        rows
+    };
+    let (plain, interleaved) = (rows(&paths[0]), rows(&paths[1]));
+    assert_eq!(interleaved.len(), 30);
+    for (step, (plain, interleaved)) in plain.iter().zip(&interleaved).enumerate() {
+        assert_eq!(interleaved.0, 5 + 256 * step as i64);
+        assert_eq!((&plain.1, &plain.2, &plain.3), (&interleaved.1, &interleaved.2, &interleaved.3));
+    }
+
+    // A stored id maps back to its chain step, so any row can be checked.
+    let verify_from = |from: &str| common::storer(&["verify", "--path", strided, "--seed", "settings", "--from", from]);
+    assert_eq!(String::from_utf8_lossy(&verify_from("0").stdout).trim(), "Verified 30 rows (0 skipped as miner-stored data)");
+    assert_eq!(String::from_utf8_lossy(&verify_from("2000").stdout).trim(), "Verified 22 rows (0 skipped as miner-stored data)");
+    let challenge = common::storer(&["challenge", "--path", strided, "--seed", "settings", "--id", "5125"]);
+    assert!(String::from_utf8_lossy(&challenge.stdout).contains("\"match\":true"));
+
+    // The chunk of one step stored at the id of another doesn't verify.
+    let conn = Connection::open(&paths[1]).unwrap();
+    conn.execute("UPDATE DBsettings SET data = ?, hash = ? WHERE id = 2309", params![interleaved[10].1, interleaved[10].2]).unwrap();
+    assert!(!verify_from("0").status.success());
+    // Nor does resuming with another layout.
+    assert!(!storer(strided, &["--n", "30", "--size", "32"]));
+    drop(conn);
+    for path in &paths {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn config_file_sets_defaults_that_flags_override() {
+    let path = temp_db("config_defaults");
+    let path_str = path.to_str().unwrap();
+    let config = path.with_extension("toml");
+    let config_str = config.to_str().unwrap();
+    std::fs::write(&config, "size = 48\nhash_algo = \"blake3\"\nhash_bytes = 16\n").unwrap();
+    let metadata = |key: &str| -> String {
+        let conn = Connection::open(&path).unwrap();
+        conn.query_row("SELECT value FROM metadata WHERE table_name = 'settings' AND key = ?", params![key], |row| row.get(0))
+            .unwrap()
+    };
+
+    // The size flag, required otherwise, comes from the file.
+    assert!(storer(path_str, &["--n", "10", "--config", config_str]));
+    assert_eq!((metadata("chunk_size"), metadata("hash_algo")), ("48".to_string(), "blake3".to_string()));
+    // So resuming without the file is refused, and with it succeeds.
+    assert!(!storer(path_str, &["--n", "20", "--size", "48"]));
+    assert!(storer(path_str, &["--n", "20", "--config", config_str]));
+
+    // A flag on the command line wins over the file.
+    let _ = std::fs::remove_file(&path);
+    assert!(storer(path_str, &["--n", "10", "--config", config_str, "--hash_algo=sha256"]));
+    assert_eq!(metadata("hash_algo"), "sha256");
+
+    std::fs::write(&config, "size = 48\nseed = \"other\"\n").unwrap();
+    let output = common::storer(&["--path", path_str, "--seed", "settings", "--n", "10", "--config", config_str]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown setting 'seed'"));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&config);
+}
+
+#[test]
+fn json_runs_report_errors_by_code() {
+    let path = temp_db("json_errors");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32"]));
+    let output = common::storer(&["--path", path_str, "--seed", "settings", "--n", "20", "--size", "32", "--hash_algo", "blake3", "--json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!((error["error"].as_str(), error["recoverable"].as_bool()), (Some("param_mismatch"), Some(false)));
+    assert!(error["message"].as_str().unwrap().starts_with("table was generated with hash_algo sha256"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_wider_hash_algo_is_reported_as_a_mismatch() {
+    // The 64 stored hash bytes of SHA-512 don't fit SHA-256, which must not
+    // hide which setting differs.
+    let path = temp_db("hash_algo_mismatch");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32", "--hash_algo", "sha512"]));
+    let output = common::storer(&["--path", path_str, "--seed", "settings", "--n", "20", "--size", "32", "--json"]);
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(error["error"].as_str(), Some("param_mismatch"));
+    assert!(error["message"].as_str().unwrap().starts_with("table was generated with hash_algo sha512, not sha256"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn list_reports_every_table_in_the_file() {
+    let path = temp_db("list_tables");
+    let path_str = path.to_str().unwrap();
+    assert!(storer(path_str, &["--n", "10", "--size", "32"]));
+    let other = common::storer(&["--path", path_str, "--seed", "other", "--n", "4", "--size", "48"]);
+    assert!(other.status.success());
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("DELETE FROM metadata WHERE table_name = 'other' AND key = 'chunk_size'", params![]).unwrap();
+    conn.execute("CREATE TABLE DBstray (x INTEGER)", params![]).unwrap();
+
+    let output = storer_command().args(["list", "--path", path_str, "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let tables: Vec<serde_json::Value> =
+        output.stdout.split(|&b| b == b'\n').filter(|line| !line.is_empty()).map(|line| serde_json::from_slice(line).unwrap()).collect();
+    let summary: Vec<_> = tables
+        .iter()
+        .map(|table| {
+            let name = table["table"].as_str().unwrap();
+            (name, table["rows"].as_i64(), table["chunk_size"].as_u64(), table["chunk_size_from"].as_str(), table["stray"].as_bool())
+        })
+        .collect();
+    assert_eq!(summary, [
+        ("DBother", Some(4), Some(48), Some("inferred"), Some(false)),
+        ("DBsettings", Some(10), Some(32), Some("metadata"), Some(false)),
+        ("DBstray", Some(0), None, None, Some(true)),
+    ]);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+mod common;
+use common::{succeeds, temp_db};
+
+type Rows = Vec<(i64, Vec<u8>, String, Vec<u8>)>;
+
+fn rows(path: &Path) -> (Rows, Vec<(i64, Vec<u8>)>) {
+    let conn = Connection::open(path).unwrap();
+    let rows = conn
+        .prepare("SELECT id, data, hash, rng_state FROM DBthreads ORDER BY id")
+        .unwrap()
+        .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let checkpoints = conn
+        .prepare("SELECT id, seed FROM seed_checkpoints WHERE table_name = 'threads' ORDER BY id")
+        .unwrap()
+        .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    (rows, checkpoints)
+}
+
+#[test]
+fn threaded_tables_match_the_single_threaded_one() {
+    let generated = |name: &str, extra: &[&str]| {
+        let path = temp_db(name);
+        let mut args = vec![
+            "--path", path.to_str().unwrap(), "--n", "250", "--size", "48", "--seed", "threads",
+            "--batch_size", "16", "--checkpoint_interval", "10",
+        ];
+        args.extend_from_slice(extra);
+        assert!(succeeds(&args), "{:?}", extra);
+        let stored = rows(&path);
+        let _ = std::fs::remove_file(&path);
+        stored
+    };
+
+    let single = generated("threads_single", &["--threads", "1"]);
+    assert_eq!(single.0.len(), 250);
+    assert_eq!(generated("threads_pipelined", &["--threads", "4"]), single);
+    assert_eq!(generated("threads_split", &["--threads", "4", "--split_chain"]), single);
+
+    let compressed = generated("threads_zstd_single", &["--threads", "1", "--compress", "zstd"]);
+    assert_eq!(generated("threads_zstd", &["--threads", "4", "--compress", "zstd"]), compressed);
+}
@@ -0,0 +1,56 @@
+mod common;
+use common::{storer_command, temp_db};
+
+#[test]
+fn write_bandwidth_is_capped() {
+    let path = temp_db("throttle");
+    let (n, size) = (300, 10_000);
+    let output = storer_command()
+        .args(["--path", path.to_str().unwrap(), "--n", &n.to_string(), "--size", &size.to_string(), "--seed", "throttle"])
+        .args(["--batch_size", "20", "--max_write_mbps", "1", "--synchronous", "OFF", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["rows_written"], n);
+
+    // Three megabytes at one per second, the first of them from the full bucket.
+    let elapsed_ms = summary["elapsed_ms"].as_u64().unwrap();
+    assert!(elapsed_ms >= 1800, "{} MB written in {} ms", n * size / 1_000_000, elapsed_ms);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn timing_histogram_reports_every_batch() {
+    let path = temp_db("throttle_timing");
+    let run = |args: &[&str]| {
+        let output = storer_command()
+            .args(["--path", path.to_str().unwrap(), "--size", "256", "--seed", "timing", "--batch_size", "10"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        output
+    };
+    let output = run(&["--n", "100", "--timing_histogram", "--json"]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let timing = &summary["batch_timing"];
+    assert_eq!(timing["batches"], 10);
+    for phase in ["generate_us", "commit_us"] {
+        let (p50, p99, max) = (timing[phase]["p50"].as_u64().unwrap(), timing[phase]["p99"].as_u64().unwrap(), timing[phase]["max"].as_u64().unwrap());
+        assert!(p50 <= p99 && p99 <= max, "{}: {}", phase, timing);
+    }
+    // Hashing and inserting ten rows takes well over 10 microseconds.
+    assert!(timing["generate_us"]["max"].as_u64().unwrap() >= 10, "{}", timing);
+
+    let output = run(&["--n", "150", "--timing_histogram"]);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("Batch timing of DBtiming over 5 batches:"), "{}", report);
+    assert!(report.contains("  commit   p50 "), "{}", report);
+
+    // Without the flag nothing is recorded.
+    let output = run(&["--n", "160", "--json"]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(summary.get("batch_timing").is_none());
+    let _ = std::fs::remove_file(&path);
+}